@@ -415,12 +415,12 @@ impl Ast {
   }
 
   fn parse_macro(&mut self, item_macro: &ItemMacro) {
-    if !item_macro
+    if item_macro
       .mac
       .path
       .segments
       .first()
-      .is_some_and(|s| s.ident == "inherit_variants")
+      .is_none_or(|s| s.ident != "inherit_variants")
     {
       return;
     }
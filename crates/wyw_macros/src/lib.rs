@@ -5,6 +5,7 @@ use quote::ToTokens;
 use syn::{ImplItem, ImplItemFn, Item, ItemImpl};
 
 mod ast;
+mod processors;
 mod traverse;
 
 fn add_hook_fn(prefix: &str, item: &mut ItemImpl, node_type: &AstType) {
@@ -43,3 +44,9 @@ pub fn traverse(_args: TokenStream, input: TokenStream) -> TokenStream {
 pub fn define_traverse(input: TokenStream) -> TokenStream {
   traverse::define(input)
 }
+
+// Generates napi bindings for a list of `wyw_processor::Processor` implementations
+#[proc_macro]
+pub fn define_processors(input: TokenStream) -> TokenStream {
+  processors::define(input)
+}
@@ -0,0 +1,316 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Path, Token};
+
+struct ProcessorList {
+  processors: Punctuated<Path, Token![,]>,
+}
+
+impl Parse for ProcessorList {
+  fn parse(input: ParseStream) -> syn::Result<Self> {
+    Ok(Self {
+      processors: Punctuated::parse_terminated(input)?,
+    })
+  }
+}
+
+// `SampleTagProcessor` becomes `SampleTag`
+fn variant_name(processor: &Path) -> syn::Ident {
+  let ident = &processor
+    .segments
+    .last()
+    .expect("Processor path cannot be empty")
+    .ident;
+  let name = ident.to_string();
+  let name = name
+    .strip_suffix("Processor")
+    .filter(|name| !name.is_empty())
+    .unwrap_or(&name);
+
+  format_ident!("{}", name)
+}
+
+pub(crate) fn define(input: TokenStream) -> TokenStream {
+  let list = syn::parse_macro_input!(input as ProcessorList);
+
+  let processors = list.processors.iter().collect::<Vec<_>>();
+  let variants = processors
+    .iter()
+    .map(|p| variant_name(p))
+    .collect::<Vec<_>>();
+
+  // napi recognizes `Env` arguments only by the name, so it has to be imported
+  quote! {
+    mod napi_bindings {
+      use super::*;
+      use ::wyw_napi::napi::Env;
+
+      #[::napi_derive::napi(string_enum)]
+      #[derive(Eq, Hash, PartialEq)]
+      pub enum TransformTargetProcessors {
+        #(#variants,)*
+      }
+
+      impl TransformTargetProcessors {
+        fn create(&self) -> ::std::sync::Arc<dyn ::wyw_napi::wyw_processor::Processor> {
+          match self {
+            #(Self::#variants => ::std::sync::Arc::new(<#processors>::default()),)*
+          }
+        }
+      }
+
+      #[::napi_derive::napi(object)]
+      pub struct TransformTarget {
+        pub specifier: String,
+        pub source: String,
+        pub processor: TransformTargetProcessors,
+      }
+
+      #[::napi_derive::napi(object)]
+      pub struct TransformOptions {
+        pub targets: Vec<TransformTarget>,
+        pub features: Option<::wyw_napi::FeatureFlags>,
+        pub input_source_map: Option<String>,
+        // The project root where `tsconfig.json` and `.pnp.cjs` for resolving imports are looked up
+        pub root: Option<String>,
+      }
+
+      impl TransformOptions {
+        fn features(&self) -> ::wyw_napi::wyw_transform::features::Features {
+          self
+            .features
+            .as_ref()
+            .map(::wyw_napi::FeatureFlags::features)
+            .unwrap_or_default()
+        }
+      }
+
+      fn create_registry(
+        targets: &[TransformTarget],
+      ) -> ::wyw_napi::wyw_processor::registry::ProcessorRegistry {
+        let mut registry = ::wyw_napi::wyw_processor::registry::ProcessorRegistry::new();
+        for target in targets {
+          let processor = target.processor.create();
+          let id = processor.id().to_string();
+          registry
+            .register(processor)
+            .add_tag(&target.specifier, &target.source, id);
+        }
+
+        registry
+      }
+
+      #[::napi_derive::napi]
+      pub fn transform(
+        env: Env,
+        filename: String,
+        source_code: String,
+        options: TransformOptions,
+      ) -> ::wyw_napi::napi::Result<::wyw_napi::TransformResult> {
+        let registry = create_registry(&options.targets);
+        let resolver = ::wyw_napi::create_resolver(options.root.as_deref());
+        let result =
+          ::wyw_napi::wyw_transform::transform(&filename, &source_code, &registry, &resolver);
+
+        ::wyw_napi::to_js_result(
+          env,
+          &filename,
+          &source_code,
+          options.input_source_map.as_deref(),
+          result,
+          &options.features(),
+        )
+      }
+
+      // Replaces processor call sites with their eval-time values and prepares the code for the evaluator
+      #[::napi_derive::napi]
+      pub fn preeval(
+        env: Env,
+        filename: String,
+        source_code: String,
+        options: TransformOptions,
+      ) -> ::wyw_napi::napi::Result<::wyw_napi::preeval::PreevalResult> {
+        let registry = create_registry(&options.targets);
+        let resolver = ::wyw_napi::create_resolver(options.root.as_deref());
+        let result = ::wyw_napi::wyw_transform::preeval::preeval(
+          &filename,
+          &source_code,
+          &registry,
+          &resolver,
+          &options.features(),
+        );
+        match result {
+          Ok(output) => Ok(::wyw_napi::preeval::PreevalResult::new(&filename, &source_code, output)),
+          Err(error) => Err(::wyw_napi::error::to_js_error(env, &source_code, error)),
+        }
+      }
+
+      // Static values of processor interpolations and what has to be evaluated for the rest
+      #[::napi_derive::napi]
+      pub fn build_static_plan(
+        env: Env,
+        filename: String,
+        source_code: String,
+        options: TransformOptions,
+      ) -> ::wyw_napi::napi::Result<::wyw_napi::static_plan::StaticPlan> {
+        let registry = create_registry(&options.targets);
+        let root = options.root;
+        match ::wyw_napi::static_plan::build_static_plan(&filename, &source_code, &registry, root) {
+          Ok(plan) => Ok(::wyw_napi::static_plan::StaticPlan::new(&source_code, plan)),
+          Err(error) => Err(::wyw_napi::error::to_js_error(env, &source_code, error)),
+        }
+      }
+
+      // Keeps the registry, the resolver, the module cache and the thread pool between calls
+      #[::napi_derive::napi]
+      pub struct NativeTransformer {
+        registry: ::std::sync::Arc<::wyw_napi::wyw_processor::registry::ProcessorRegistry>,
+        resolver: ::std::sync::Arc<::wyw_napi::wyw_transform::resolver::NativeResolver>,
+        cache: ::std::sync::Arc<::wyw_napi::wyw_transform::cache::ModuleCache>,
+        features: ::wyw_napi::wyw_transform::features::Features,
+        pool: ::wyw_napi::task::TransformPool,
+      }
+
+      #[::napi_derive::napi]
+      impl NativeTransformer {
+        // `concurrency` is the number of threads that run the transforms, one per CPU by default
+        #[napi(constructor)]
+        pub fn new(
+          options: TransformOptions,
+          concurrency: Option<u32>,
+        ) -> ::wyw_napi::napi::Result<Self> {
+          Ok(Self {
+            registry: ::std::sync::Arc::new(create_registry(&options.targets)),
+            resolver: ::std::sync::Arc::new(::wyw_napi::create_resolver(options.root.as_deref())),
+            cache: ::std::sync::Arc::new(::wyw_napi::wyw_transform::cache::ModuleCache::new()),
+            features: options.features(),
+            pool: ::wyw_napi::task::TransformPool::new(
+              concurrency.map(|concurrency| concurrency as usize),
+            )?,
+          })
+        }
+
+        #[napi]
+        pub fn transform(
+          &self,
+          env: Env,
+          file: ::wyw_napi::TransformFile,
+        ) -> ::wyw_napi::napi::Result<::wyw_napi::TransformResult> {
+          let result =
+            ::wyw_napi::transform_file(&file, &self.registry, &self.resolver, &self.cache);
+          ::wyw_napi::to_js_result(
+            env,
+            &file.filename,
+            &file.source_code,
+            file.input_source_map.as_deref(),
+            result,
+            &self.features,
+          )
+        }
+
+        // Throws the first error that is not downgraded by `softErrors`
+        #[napi]
+        pub fn transform_many(
+          &self,
+          env: Env,
+          files: Vec<::wyw_napi::TransformFile>,
+        ) -> ::wyw_napi::napi::Result<Vec<::wyw_napi::TransformResult>> {
+          self
+            .pool
+            .install(|| {
+              ::wyw_napi::transform_files(&files, &self.registry, &self.resolver, &self.cache)
+            })
+            .into_iter()
+            .zip(&files)
+            .map(|(result, file)| {
+              ::wyw_napi::to_js_result(
+                env,
+                &file.filename,
+                &file.source_code,
+                file.input_source_map.as_deref(),
+                result,
+                &self.features,
+              )
+            })
+            .collect()
+        }
+
+        // Runs on the pool of the transformer, at most `concurrency` files at a time
+        #[napi(ts_return_type = "Promise<TransformResult>")]
+        pub fn transform_async(
+          &self,
+          env: Env,
+          file: ::wyw_napi::TransformFile,
+        ) -> ::wyw_napi::napi::Result<::wyw_napi::napi::JsObject> {
+          ::wyw_napi::task::spawn_transform(
+            env,
+            &self.pool,
+            file,
+            self.registry.clone(),
+            self.resolver.clone(),
+            self.cache.clone(),
+            self.features.clone(),
+          )
+        }
+
+        #[napi]
+        pub fn preeval(
+          &self,
+          env: Env,
+          file: ::wyw_napi::TransformFile,
+        ) -> ::wyw_napi::napi::Result<::wyw_napi::preeval::PreevalResult> {
+          let result = ::wyw_napi::wyw_transform::preeval::preeval(
+            &file.filename,
+            &file.source_code,
+            &self.registry,
+            self.resolver.as_ref(),
+            &self.features,
+          );
+          match result {
+            Ok(output) => Ok(::wyw_napi::preeval::PreevalResult::new(
+              &file.filename,
+              &file.source_code,
+              output,
+            )),
+            Err(error) => Err(::wyw_napi::error::to_js_error(env, &file.source_code, error)),
+          }
+        }
+
+        #[napi]
+        pub fn clear_cache(&self) {
+          self.cache.clear();
+          self.registry.clear_cache();
+          self.resolver.clear_cache();
+        }
+      }
+
+      // Runs on the libuv thread pool without a bound of its own, so a large batch queues up
+      // there and delays fs, dns and zlib calls. `NativeTransformer` limits the concurrency.
+      #[::napi_derive::napi]
+      pub fn transform_async(
+        filename: String,
+        source_code: String,
+        options: TransformOptions,
+        signal: Option<::wyw_napi::napi::bindgen_prelude::AbortSignal>,
+      ) -> ::wyw_napi::napi::bindgen_prelude::AsyncTask<::wyw_napi::task::TransformTask> {
+        let registry = create_registry(&options.targets);
+        let resolver = ::wyw_napi::create_resolver(options.root.as_deref());
+        let features = options.features();
+        let file = ::wyw_napi::TransformFile {
+          filename,
+          source_code,
+          input_source_map: options.input_source_map,
+        };
+        ::wyw_napi::napi::bindgen_prelude::AsyncTask::with_optional_signal(
+          ::wyw_napi::task::TransformTask::new(file, registry, resolver, features),
+          signal,
+        )
+      }
+    }
+
+    pub use napi_bindings::*;
+  }
+  .into()
+}
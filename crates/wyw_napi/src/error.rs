@@ -0,0 +1,27 @@
+use crate::Location;
+use napi::{Env, JsObject};
+use wyw_processor::error::WywError;
+
+fn create_js_error(env: Env, source_code: &str, error: &WywError) -> napi::Result<JsObject> {
+  let mut js_error = env.create_error(napi::Error::from_reason(error.to_string()))?;
+  js_error.set_named_property("code", error.code.as_str())?;
+  js_error.set_named_property("reason", error.message.as_str())?;
+  if let Some(filename) = &error.filename {
+    js_error.set_named_property("filename", filename.as_str())?;
+  }
+
+  if let Some(span) = error.span {
+    js_error.set_named_property("start", Location::new(source_code, span.start))?;
+    js_error.set_named_property("end", Location::new(source_code, span.end))?;
+  }
+
+  Ok(js_error)
+}
+
+// Converts an error into a JS `Error` with `code`, `filename`, `start` and `end` properties
+pub fn to_js_error(env: Env, source_code: &str, error: WywError) -> napi::Error {
+  match create_js_error(env, source_code, &error) {
+    Ok(js_error) => napi::Error::from(js_error.into_unknown()),
+    Err(_) => napi::Error::from_reason(error.to_string()),
+  }
+}
@@ -0,0 +1,252 @@
+// JS-facing types shared by all native processor crates.
+// Bindings for a particular set of processors are generated by `wyw_macros::define_processors!`.
+
+pub mod error;
+pub mod preeval;
+pub mod static_plan;
+pub mod task;
+
+use crate::error::to_js_error;
+use napi::bindgen_prelude::Either3;
+use napi::Env;
+use rayon::prelude::*;
+use std::path::PathBuf;
+use wyw_processor::diagnostics::{self, code_frame, Diagnostic, Severity};
+use wyw_processor::error::WywResult;
+use wyw_processor::registry::ProcessorRegistry;
+use wyw_transform::cache::ModuleCache;
+use wyw_transform::features::{FeatureFlag, Features};
+use wyw_transform::resolver::{NativeResolver, ResolverOptions};
+use wyw_transform::TransformOutput;
+
+#[macro_use]
+extern crate napi_derive;
+
+pub use napi;
+pub use wyw_processor;
+pub use wyw_transform;
+
+// The same as `FeatureFlags` from `@wyw-in-js/shared`
+#[napi(object)]
+pub struct FeatureFlags {
+  pub soft_errors: Option<Either3<bool, String, Vec<String>>>,
+  pub dangerous_code_remover: Option<Either3<bool, String, Vec<String>>>,
+}
+
+fn feature_flag(value: &Option<Either3<bool, String, Vec<String>>>, default: bool) -> FeatureFlag {
+  match value {
+    None => FeatureFlag::Bool(default),
+    Some(Either3::A(value)) => FeatureFlag::Bool(*value),
+    Some(Either3::B(pattern)) => FeatureFlag::patterns([pattern]),
+    Some(Either3::C(patterns)) => FeatureFlag::patterns(patterns),
+  }
+}
+
+impl FeatureFlags {
+  pub fn features(&self) -> Features {
+    Features {
+      soft_errors: feature_flag(&self.soft_errors, false),
+      dangerous_code_remover: feature_flag(&self.dangerous_code_remover, true),
+    }
+  }
+}
+
+#[napi(string_enum)]
+pub enum DiagnosticSeverity {
+  #[napi(value = "error")]
+  Error,
+  #[napi(value = "warning")]
+  Warning,
+}
+
+impl From<Severity> for DiagnosticSeverity {
+  fn from(severity: Severity) -> Self {
+    match severity {
+      Severity::Error => Self::Error,
+      Severity::Warning => Self::Warning,
+    }
+  }
+}
+
+#[napi(object)]
+pub struct Location {
+  pub line: u32,
+  pub column: u32,
+}
+
+impl Location {
+  fn new(source_code: &str, offset: u32) -> Self {
+    let location = diagnostics::Location::from_offset(source_code, offset);
+    Self {
+      line: location.line as u32,
+      column: location.column as u32,
+    }
+  }
+}
+
+#[napi(object)]
+pub struct DiagnosticLabel {
+  pub start: Location,
+  pub end: Location,
+  pub message: Option<String>,
+}
+
+#[napi(object)]
+pub struct TransformDiagnostic {
+  pub severity: DiagnosticSeverity,
+  pub code: String,
+  pub message: String,
+  pub filename: String,
+  pub start: Location,
+  pub end: Location,
+  pub secondary: Vec<DiagnosticLabel>,
+  pub code_frame: String,
+}
+
+impl TransformDiagnostic {
+  fn new(filename: &str, source_code: &str, diagnostic: Diagnostic) -> Self {
+    let span = diagnostic.primary.span;
+    Self {
+      severity: diagnostic.severity.into(),
+      code: diagnostic.code,
+      message: diagnostic.message,
+      filename: filename.to_string(),
+      start: Location::new(source_code, span.start),
+      end: Location::new(source_code, span.end),
+      secondary: diagnostic
+        .secondary
+        .into_iter()
+        .map(|label| DiagnosticLabel {
+          start: Location::new(source_code, label.span.start),
+          end: Location::new(source_code, label.span.end),
+          message: label.message,
+        })
+        .collect(),
+      code_frame: code_frame(source_code, span),
+    }
+  }
+}
+
+#[napi(object)]
+pub struct ProcessedTag {
+  pub processor: String,
+  pub source: String,
+  pub imported: String,
+  pub class_name: Option<String>,
+  pub start: Location,
+  pub end: Location,
+}
+
+impl ProcessedTag {
+  fn new(source_code: &str, tag: wyw_transform::ProcessedTag) -> Self {
+    Self {
+      processor: tag.processor,
+      source: tag.tag_source.source,
+      imported: tag.tag_source.imported,
+      class_name: tag.class_name,
+      start: Location::new(source_code, tag.span.start),
+      end: Location::new(source_code, tag.span.end),
+    }
+  }
+}
+
+// Mirrors the result of `transform` from `@wyw-in-js/transform`
+#[napi(object)]
+pub struct TransformResult {
+  pub code: String,
+  pub source_map: Option<String>,
+  pub css_text: Option<String>,
+  pub processed_tags: Vec<ProcessedTag>,
+  // Resolved paths of the files that should be watched
+  pub dependencies: Vec<String>,
+  // `true` if the file doesn't need the evaluator
+  pub can_skip_evaluation: bool,
+  pub diagnostics: Vec<TransformDiagnostic>,
+}
+
+impl TransformResult {
+  pub fn new(filename: &str, source_code: &str, output: TransformOutput) -> Self {
+    Self {
+      code: output.code,
+      source_map: output.source_map.map(|map| map.to_json_string()),
+      css_text: output.css_text,
+      processed_tags: output
+        .processed_tags
+        .into_iter()
+        .map(|tag| ProcessedTag::new(source_code, tag))
+        .collect(),
+      dependencies: output.dependencies,
+      can_skip_evaluation: output.can_skip_evaluation,
+      diagnostics: output
+        .diagnostics
+        .into_iter()
+        .map(|diagnostic| TransformDiagnostic::new(filename, source_code, diagnostic))
+        .collect(),
+    }
+  }
+}
+
+#[napi(object)]
+pub struct TransformFile {
+  pub filename: String,
+  pub source_code: String,
+  pub input_source_map: Option<String>,
+}
+
+// Converts the result of a transform into a JS value,
+// composing its source map with the input one and applying `softErrors`
+pub fn to_js_result(
+  env: Env,
+  filename: &str,
+  source_code: &str,
+  input_source_map: Option<&str>,
+  result: WywResult<TransformOutput>,
+  features: &Features,
+) -> napi::Result<TransformResult> {
+  let result = result.and_then(|output| output.with_input_source_map(input_source_map));
+  match result {
+    Ok(output) => Ok(TransformResult::new(filename, source_code, output)),
+    Err(error) if features.soft_errors.is_enabled(filename) => Ok(TransformResult::new(
+      filename,
+      source_code,
+      TransformOutput::from_soft_error(source_code, error),
+    )),
+    Err(error) => Err(to_js_error(env, source_code, error)),
+  }
+}
+
+// Imports are resolved relative to `root`, where `tsconfig.json` and `.pnp.cjs` are looked up
+pub fn create_resolver(root: Option<&str>) -> NativeResolver {
+  NativeResolver::new(&ResolverOptions {
+    root: root.map(PathBuf::from),
+    ..Default::default()
+  })
+}
+
+pub fn transform_file(
+  file: &TransformFile,
+  registry: &ProcessorRegistry,
+  resolver: &NativeResolver,
+  cache: &ModuleCache,
+) -> WywResult<TransformOutput> {
+  wyw_transform::transform_with_cache(
+    &file.filename,
+    &file.source_code,
+    registry,
+    resolver,
+    Some(cache),
+  )
+}
+
+// Transforms files in parallel, results are in the same order as files
+pub fn transform_files(
+  files: &[TransformFile],
+  registry: &ProcessorRegistry,
+  resolver: &NativeResolver,
+  cache: &ModuleCache,
+) -> Vec<WywResult<TransformOutput>> {
+  files
+    .par_iter()
+    .map(|file| transform_file(file, registry, resolver, cache))
+    .collect()
+}
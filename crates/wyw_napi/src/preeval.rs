@@ -0,0 +1,88 @@
+use crate::{Location, ProcessedTag, TransformDiagnostic};
+use wyw_transform::preeval::{DynamicImportKind, PreevalOutput};
+
+#[napi(string_enum)]
+pub enum PreevalDynamicImportKind {
+  #[napi(value = "literal")]
+  Literal,
+  #[napi(value = "static-prefix")]
+  StaticPrefix,
+  #[napi(value = "dynamic")]
+  Dynamic,
+}
+
+impl From<DynamicImportKind> for PreevalDynamicImportKind {
+  fn from(kind: DynamicImportKind) -> Self {
+    match kind {
+      DynamicImportKind::Literal => Self::Literal,
+      DynamicImportKind::StaticPrefix => Self::StaticPrefix,
+      DynamicImportKind::Dynamic => Self::Dynamic,
+    }
+  }
+}
+
+#[napi(object)]
+pub struct PreevalDynamicImport {
+  pub kind: PreevalDynamicImportKind,
+  pub specifier: Option<String>,
+  pub start: Location,
+  pub end: Location,
+}
+
+#[napi(object)]
+pub struct PreevalRemovedCode {
+  pub name: String,
+  pub value: String,
+  pub start: Location,
+  pub end: Location,
+}
+
+// The same as the result of `runOxcPreevalStage`
+#[napi(object)]
+pub struct PreevalResult {
+  pub code: String,
+  pub dependency_names: Vec<String>,
+  pub processed_tags: Vec<ProcessedTag>,
+  pub removed: Vec<PreevalRemovedCode>,
+  pub dynamic_imports: Vec<PreevalDynamicImport>,
+  pub diagnostics: Vec<TransformDiagnostic>,
+}
+
+impl PreevalResult {
+  pub fn new(filename: &str, source_code: &str, output: PreevalOutput) -> Self {
+    Self {
+      code: output.code,
+      dependency_names: output.dependency_names,
+      processed_tags: output
+        .processed_tags
+        .into_iter()
+        .map(|tag| ProcessedTag::new(source_code, tag))
+        .collect(),
+      removed: output
+        .removed
+        .into_iter()
+        .map(|removed| PreevalRemovedCode {
+          name: removed.name,
+          value: removed.value,
+          start: Location::new(source_code, removed.span.start),
+          end: Location::new(source_code, removed.span.end),
+        })
+        .collect(),
+      dynamic_imports: output
+        .dynamic_imports
+        .into_iter()
+        .map(|import| PreevalDynamicImport {
+          kind: import.kind.into(),
+          specifier: import.specifier,
+          start: Location::new(source_code, import.span.start),
+          end: Location::new(source_code, import.span.end),
+        })
+        .collect(),
+      diagnostics: output
+        .diagnostics
+        .into_iter()
+        .map(|diagnostic| TransformDiagnostic::new(filename, source_code, diagnostic))
+        .collect(),
+    }
+  }
+}
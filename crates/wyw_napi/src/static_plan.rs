@@ -0,0 +1,272 @@
+use crate::{create_resolver, Location};
+use wyw_processor::error::WywResult;
+use wyw_processor::registry::ProcessorRegistry;
+use wyw_processor::static_semantics::InterpolationKind;
+use wyw_transform::static_plan::{self, StaticNeed, StaticNeedReason, StaticNeedRequestKind};
+
+#[napi(string_enum)]
+pub enum StaticInterpolationKind {
+  #[napi(value = "literal")]
+  Literal,
+  #[napi(value = "static-binding")]
+  StaticBinding,
+  #[napi(value = "runtime-callback")]
+  RuntimeCallback,
+  #[napi(value = "dynamic")]
+  Dynamic,
+}
+
+impl From<InterpolationKind> for StaticInterpolationKind {
+  fn from(kind: InterpolationKind) -> Self {
+    match kind {
+      InterpolationKind::Literal => Self::Literal,
+      InterpolationKind::StaticBinding => Self::StaticBinding,
+      InterpolationKind::RuntimeCallback => Self::RuntimeCallback,
+      InterpolationKind::Dynamic => Self::Dynamic,
+    }
+  }
+}
+
+#[napi(string_enum)]
+pub enum StaticPlanNeedKind {
+  #[napi(value = "export")]
+  Export,
+  #[napi(value = "eval")]
+  Eval,
+}
+
+#[napi(string_enum)]
+pub enum StaticPlanNeedReason {
+  #[napi(value = "processor-static-interpolation")]
+  ProcessorStaticInterpolation,
+  #[napi(value = "unresolved-static-value")]
+  UnresolvedStaticValue,
+}
+
+impl From<StaticNeedReason> for StaticPlanNeedReason {
+  fn from(reason: StaticNeedReason) -> Self {
+    match reason {
+      StaticNeedReason::ProcessorStaticInterpolation => Self::ProcessorStaticInterpolation,
+      StaticNeedReason::UnresolvedStaticValue => Self::UnresolvedStaticValue,
+    }
+  }
+}
+
+#[napi(string_enum)]
+pub enum StaticPlanNeedRequestKind {
+  #[napi(value = "dependency")]
+  Dependency,
+  #[napi(value = "eval")]
+  Eval,
+}
+
+impl From<StaticNeedRequestKind> for StaticPlanNeedRequestKind {
+  fn from(kind: StaticNeedRequestKind) -> Self {
+    match kind {
+      StaticNeedRequestKind::Dependency => Self::Dependency,
+      StaticNeedRequestKind::Eval => Self::Eval,
+    }
+  }
+}
+
+// Values are identified by the location of the interpolation, `name` is its code
+#[napi(object)]
+pub struct StaticPlanValue {
+  pub name: String,
+  pub start: Location,
+  pub end: Location,
+  // JS code of the value, e.g. `{ "color": "red" }`
+  pub code: String,
+}
+
+#[napi(object)]
+pub struct StaticPlanUnresolved {
+  pub name: String,
+  pub start: Location,
+  pub end: Location,
+  // The module where the resolution failed
+  pub filename: String,
+  pub reason: String,
+}
+
+#[napi(object)]
+pub struct StaticPlanEnv {
+  pub dependencies: Vec<String>,
+  pub values: Vec<StaticPlanValue>,
+  pub unresolved: Vec<StaticPlanUnresolved>,
+}
+
+// The same as `StaticNeed` from `static-plan/types.ts`, `name` is set for `export` needs
+#[napi(object)]
+pub struct StaticPlanNeed {
+  pub kind: StaticPlanNeedKind,
+  pub importer: String,
+  pub source: String,
+  pub name: Option<String>,
+  pub only: Option<Vec<String>>,
+  pub reason: StaticPlanNeedReason,
+}
+
+#[napi(object)]
+pub struct StaticPlanNeedRequest {
+  pub kind: StaticPlanNeedRequestKind,
+  pub importer: String,
+  pub source: String,
+  pub only: Vec<String>,
+  pub reasons: Vec<StaticPlanNeedReason>,
+}
+
+#[napi(object)]
+pub struct StaticPlanProcessorUsage {
+  pub processor: String,
+  pub source: String,
+  pub imported: String,
+  pub local: String,
+  pub start: Location,
+  pub end: Location,
+  pub interpolations: Vec<StaticInterpolationKind>,
+  pub static_value_names: Vec<String>,
+  pub can_skip_evaluation: bool,
+}
+
+#[napi(object)]
+pub struct StaticPlanAttribution {
+  pub need_count: u32,
+  pub need_request_count: u32,
+  pub runtime_dependency_count: u32,
+  pub static_value_count: u32,
+  pub unresolved_count: u32,
+  pub usage_count: u32,
+}
+
+#[napi(object)]
+pub struct StaticPlan {
+  pub filename: String,
+  pub env: StaticPlanEnv,
+  pub needs: Vec<StaticPlanNeed>,
+  pub need_requests: Vec<StaticPlanNeedRequest>,
+  pub processor_usages: Vec<StaticPlanProcessorUsage>,
+  pub attribution: StaticPlanAttribution,
+  pub can_skip_evaluation: bool,
+}
+
+impl StaticPlanNeed {
+  fn new(need: StaticNeed) -> Self {
+    match need {
+      StaticNeed::Export {
+        importer,
+        source,
+        name,
+        reason,
+      } => Self {
+        kind: StaticPlanNeedKind::Export,
+        importer,
+        source,
+        name: Some(name),
+        only: None,
+        reason: reason.into(),
+      },
+      StaticNeed::Eval {
+        importer,
+        source,
+        only,
+        reason,
+      } => Self {
+        kind: StaticPlanNeedKind::Eval,
+        importer,
+        source,
+        name: None,
+        only: Some(only),
+        reason: reason.into(),
+      },
+    }
+  }
+}
+
+impl StaticPlan {
+  pub fn new(source_code: &str, plan: static_plan::StaticPlan) -> Self {
+    let can_skip_evaluation = plan.can_skip_evaluation();
+    let attribution = plan.attribution;
+    Self {
+      filename: plan.filename,
+      env: StaticPlanEnv {
+        dependencies: plan.env.dependencies,
+        values: plan
+          .env
+          .values
+          .into_iter()
+          .map(|(span, value)| StaticPlanValue {
+            name: span.source_text(source_code).to_string(),
+            start: Location::new(source_code, span.start),
+            end: Location::new(source_code, span.end),
+            code: value.to_code(),
+          })
+          .collect(),
+        unresolved: plan
+          .env
+          .unresolved
+          .into_iter()
+          .map(|(span, unresolved)| StaticPlanUnresolved {
+            name: span.source_text(source_code).to_string(),
+            start: Location::new(source_code, span.start),
+            end: Location::new(source_code, span.end),
+            reason: unresolved.reason.to_string(),
+            filename: unresolved.filename,
+          })
+          .collect(),
+      },
+      needs: plan.needs.into_iter().map(StaticPlanNeed::new).collect(),
+      need_requests: plan
+        .need_requests
+        .into_iter()
+        .map(|request| StaticPlanNeedRequest {
+          kind: request.kind.into(),
+          importer: request.importer,
+          source: request.source,
+          only: request.only,
+          reasons: request.reasons.into_iter().map(Into::into).collect(),
+        })
+        .collect(),
+      processor_usages: plan
+        .processor_usages
+        .into_iter()
+        .map(|usage| StaticPlanProcessorUsage {
+          can_skip_evaluation: usage.can_skip_evaluation(),
+          processor: usage.processor,
+          source: usage.tag_source.source,
+          imported: usage.tag_source.imported,
+          local: usage.local,
+          start: Location::new(source_code, usage.span.start),
+          end: Location::new(source_code, usage.span.end),
+          interpolations: usage.interpolations.into_iter().map(Into::into).collect(),
+          static_value_names: usage
+            .static_values
+            .iter()
+            .map(|span| span.source_text(source_code).to_string())
+            .collect(),
+        })
+        .collect(),
+      attribution: StaticPlanAttribution {
+        need_count: attribution.need_count as u32,
+        need_request_count: attribution.need_request_count as u32,
+        runtime_dependency_count: attribution.runtime_dependency_count as u32,
+        static_value_count: attribution.static_value_count as u32,
+        unresolved_count: attribution.unresolved_count as u32,
+        usage_count: attribution.usage_count as u32,
+      },
+      can_skip_evaluation,
+    }
+  }
+}
+
+// Imports of the file are resolved relative to `root`, where `tsconfig.json` and `.pnp.cjs` are looked up
+pub fn build_static_plan(
+  filename: &str,
+  source_code: &str,
+  registry: &ProcessorRegistry,
+  root: Option<String>,
+) -> WywResult<static_plan::StaticPlan> {
+  let resolver = create_resolver(root.as_deref());
+
+  static_plan::build_static_plan(filename, source_code, registry, &resolver)
+}
@@ -0,0 +1,155 @@
+use crate::{to_js_result, transform_file, TransformFile, TransformResult};
+use napi::{Env, JsObject, Task};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::Arc;
+use wyw_processor::error::WywResult;
+use wyw_processor::registry::ProcessorRegistry;
+use wyw_transform::cache::ModuleCache;
+use wyw_transform::features::Features;
+use wyw_transform::resolver::NativeResolver;
+use wyw_transform::TransformOutput;
+
+// A thread pool of a `NativeTransformer`. Async transforms run here instead of the libuv pool,
+// so a large batch neither blocks the event loop nor takes threads from fs, dns and zlib calls.
+pub struct TransformPool {
+  pool: ThreadPool,
+}
+
+impl TransformPool {
+  // `None` means one thread per CPU
+  pub fn new(concurrency: Option<usize>) -> napi::Result<Self> {
+    let pool = ThreadPoolBuilder::new()
+      .num_threads(concurrency.unwrap_or_default())
+      .thread_name(|idx| format!("wyw-transform-{idx}"))
+      .build()
+      .map_err(|error| {
+        napi::Error::from_reason(format!(
+          "Cannot create a thread pool for transforms: {error}"
+        ))
+      })?;
+
+    Ok(Self { pool })
+  }
+
+  pub fn install<R: Send>(&self, job: impl FnOnce() -> R + Send) -> R {
+    self.pool.install(job)
+  }
+
+  pub fn spawn(&self, job: impl FnOnce() + Send + 'static) {
+    self.pool.spawn(job);
+  }
+}
+
+// Transforms a file on the pool and returns a promise of `TransformResult`
+pub fn spawn_transform(
+  env: Env,
+  pool: &TransformPool,
+  file: TransformFile,
+  registry: Arc<ProcessorRegistry>,
+  resolver: Arc<NativeResolver>,
+  cache: Arc<ModuleCache>,
+  features: Features,
+) -> napi::Result<JsObject> {
+  let (deferred, promise) = env.create_deferred()?;
+  pool.spawn(move || {
+    let result = transform_file(&file, &registry, &resolver, &cache);
+    deferred.resolve(move |env| {
+      to_js_result(
+        env,
+        &file.filename,
+        &file.source_code,
+        file.input_source_map.as_deref(),
+        result,
+        &features,
+      )
+    });
+  });
+
+  Ok(promise)
+}
+
+pub struct TransformTask {
+  filename: String,
+  source_code: String,
+  input_source_map: Option<String>,
+  registry: ProcessorRegistry,
+  resolver: NativeResolver,
+  features: Features,
+}
+
+impl TransformTask {
+  pub fn new(
+    file: TransformFile,
+    registry: ProcessorRegistry,
+    resolver: NativeResolver,
+    features: Features,
+  ) -> Self {
+    Self {
+      filename: file.filename,
+      source_code: file.source_code,
+      input_source_map: file.input_source_map,
+      registry,
+      resolver,
+      features,
+    }
+  }
+}
+
+impl Task for TransformTask {
+  type Output = WywResult<TransformOutput>;
+  type JsValue = TransformResult;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    Ok(wyw_transform::transform(
+      &self.filename,
+      &self.source_code,
+      &self.registry,
+      &self.resolver,
+    ))
+  }
+
+  fn resolve(&mut self, env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    to_js_result(
+      env,
+      &self.filename,
+      &self.source_code,
+      self.input_source_map.as_deref(),
+      output,
+      &self.features,
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::sync::mpsc;
+  use std::thread;
+  use std::time::Duration;
+
+  #[test]
+  fn test_pool_concurrency() {
+    let pool = TransformPool::new(Some(2)).unwrap();
+    let active = Arc::new(AtomicUsize::new(0));
+    let max_active = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::channel();
+
+    for _ in 0..8 {
+      let (active, max_active, sender) = (active.clone(), max_active.clone(), sender.clone());
+      pool.spawn(move || {
+        let current = active.fetch_add(1, Ordering::SeqCst) + 1;
+        max_active.fetch_max(current, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(10));
+        active.fetch_sub(1, Ordering::SeqCst);
+        sender.send(()).unwrap();
+      });
+    }
+
+    for _ in 0..8 {
+      receiver.recv().unwrap();
+    }
+
+    assert_eq!(max_active.load(Ordering::SeqCst), 2);
+  }
+}
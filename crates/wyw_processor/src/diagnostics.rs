@@ -0,0 +1,257 @@
+use oxc::span::Span;
+use std::fmt::Display;
+
+const CODE_FRAME_CONTEXT_LINES: usize = 2;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+  Error,
+  Warning,
+}
+
+impl Display for Severity {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Error => write!(f, "error"),
+      Self::Warning => write!(f, "warning"),
+    }
+  }
+}
+
+// A span in the original source with an optional explanation
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+  pub span: Span,
+  pub message: Option<String>,
+}
+
+impl Label {
+  pub fn new(span: Span) -> Self {
+    Self {
+      span,
+      message: None,
+    }
+  }
+
+  pub fn with_message(span: Span, message: impl Display) -> Self {
+    Self {
+      span,
+      message: Some(message.to_string()),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+  pub severity: Severity,
+  // Stable identifier of the diagnostic, e.g. `sample-tag/empty-template`
+  pub code: String,
+  pub message: String,
+  pub primary: Label,
+  pub secondary: Vec<Label>,
+}
+
+impl Diagnostic {
+  pub fn new(severity: Severity, code: impl Display, message: impl Display, span: Span) -> Self {
+    Self {
+      severity,
+      code: code.to_string(),
+      message: message.to_string(),
+      primary: Label::new(span),
+      secondary: vec![],
+    }
+  }
+
+  pub fn error(code: impl Display, message: impl Display, span: Span) -> Self {
+    Self::new(Severity::Error, code, message, span)
+  }
+
+  pub fn warning(code: impl Display, message: impl Display, span: Span) -> Self {
+    Self::new(Severity::Warning, code, message, span)
+  }
+
+  pub fn with_secondary(mut self, span: Span, message: impl Display) -> Self {
+    self.secondary.push(Label::with_message(span, message));
+    self
+  }
+
+  pub fn is_error(&self) -> bool {
+    self.severity == Severity::Error
+  }
+}
+
+// Collects diagnostics reported by processors while a file is being transformed
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+  items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+  pub fn push(&mut self, diagnostic: Diagnostic) {
+    self.items.push(diagnostic);
+  }
+
+  pub fn has_errors(&self) -> bool {
+    self.items.iter().any(Diagnostic::is_error)
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.items.is_empty()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+    self.items.iter()
+  }
+
+  pub fn into_vec(self) -> Vec<Diagnostic> {
+    self.items
+  }
+}
+
+// The same as `Location` from `@wyw-in-js/shared`: lines are 1-based, columns are 0-based
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Location {
+  pub line: usize,
+  pub column: usize,
+}
+
+impl Location {
+  // Columns are counted in UTF-16 code units, just like in JS
+  pub fn from_offset(source: &str, offset: u32) -> Self {
+    let (line, line_prefix) = split_at_offset(source, offset);
+
+    Self {
+      line,
+      column: line_prefix.encode_utf16().count(),
+    }
+  }
+}
+
+// Returns the 1-based line number of the offset and the part of that line before the offset
+fn split_at_offset(source: &str, offset: u32) -> (usize, &str) {
+  let mut offset = (offset as usize).min(source.len());
+  while !source.is_char_boundary(offset) {
+    offset -= 1;
+  }
+
+  let before = &source[..offset];
+  let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+
+  (before.matches('\n').count() + 1, &before[line_start..])
+}
+
+// Renders a babel-like code frame with the span highlighted
+pub fn code_frame(source: &str, span: Span) -> String {
+  let (start_line, start_prefix) = split_at_offset(source, span.start);
+  let (end_line, end_prefix) = split_at_offset(source, span.end.max(span.start));
+
+  let lines = source.lines().collect::<Vec<_>>();
+  let first = start_line.saturating_sub(CODE_FRAME_CONTEXT_LINES).max(1);
+  let last = (end_line + CODE_FRAME_CONTEXT_LINES).min(lines.len().max(1));
+  let gutter_width = last.to_string().len();
+
+  let mut frame = vec![];
+  for line_number in first..=last {
+    let line = lines.get(line_number - 1).copied().unwrap_or_default();
+    let is_marked = (start_line..=end_line).contains(&line_number);
+    let marker = if is_marked { ">" } else { " " };
+    let gutter = format!("{line_number:>gutter_width$}");
+
+    if line.is_empty() {
+      frame.push(format!("{marker} {gutter} |"));
+    } else {
+      frame.push(format!("{marker} {gutter} | {line}"));
+    }
+
+    if !is_marked {
+      continue;
+    }
+
+    let line_length = line.chars().count();
+    let from = if line_number == start_line {
+      start_prefix.chars().count()
+    } else {
+      0
+    };
+    let to = if line_number == end_line {
+      end_prefix.chars().count()
+    } else {
+      line_length
+    };
+    let padding = line
+      .chars()
+      .take(from)
+      .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+      .collect::<String>();
+    let underline = "^".repeat(to.saturating_sub(from).max(1));
+    frame.push(format!(
+      "  {} | {padding}{underline}",
+      " ".repeat(gutter_width)
+    ));
+  }
+
+  frame.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_location() {
+    let source = "const a = 1;\nconst b = `ü${a}`;";
+
+    assert_eq!(
+      Location::from_offset(source, 0),
+      Location { line: 1, column: 0 }
+    );
+    assert_eq!(
+      Location::from_offset(source, 19),
+      Location { line: 2, column: 6 }
+    );
+    assert_eq!(
+      Location::from_offset(source, source.len() as u32),
+      Location {
+        line: 2,
+        column: 18
+      }
+    );
+  }
+
+  #[test]
+  fn test_code_frame() {
+    let source = "import { css } from 'lib';\n\nconst a = css``;\nexport default a;";
+    let start = source.find("css``").unwrap() as u32;
+    let frame = code_frame(source, Span::new(start, start + 5));
+
+    assert_eq!(
+      frame,
+      [
+        "  1 | import { css } from 'lib';",
+        "  2 |",
+        "> 3 | const a = css``;",
+        "    |           ^^^^^",
+        "  4 | export default a;",
+      ]
+      .join("\n")
+    );
+  }
+
+  #[test]
+  fn test_diagnostics() {
+    let mut diagnostics = Diagnostics::default();
+    diagnostics.push(Diagnostic::warning(
+      "test/warning",
+      "Warning",
+      Span::new(0, 1),
+    ));
+    assert!(!diagnostics.has_errors());
+
+    diagnostics.push(
+      Diagnostic::error("test/error", "Error", Span::new(0, 1))
+        .with_secondary(Span::new(2, 3), "declared here"),
+    );
+    assert!(diagnostics.has_errors());
+    assert_eq!(diagnostics.into_vec()[1].secondary.len(), 1);
+  }
+}
@@ -0,0 +1,82 @@
+use crate::diagnostics::Diagnostic;
+use oxc::span::Span;
+use std::fmt::Display;
+
+// A failure that prevents a file from being transformed.
+// Problems that a processor can recover from are reported as `Diagnostic`s instead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WywError {
+  // Stable identifier of the error, e.g. `wyw/parse-error`
+  pub code: String,
+  pub message: String,
+  pub filename: Option<String>,
+  pub span: Option<Span>,
+}
+
+pub type WywResult<T> = Result<T, WywError>;
+
+impl WywError {
+  pub fn new(code: impl Display, message: impl Display) -> Self {
+    Self {
+      code: code.to_string(),
+      message: message.to_string(),
+      filename: None,
+      span: None,
+    }
+  }
+
+  pub fn with_filename(mut self, filename: impl Display) -> Self {
+    self.filename = Some(filename.to_string());
+    self
+  }
+
+  pub fn with_span(mut self, span: Span) -> Self {
+    self.span = Some(span);
+    self
+  }
+
+  // Used when errors are downgraded by the `softErrors` feature
+  pub fn into_warning(self) -> Diagnostic {
+    Diagnostic::warning(self.code, self.message, self.span.unwrap_or_default())
+  }
+}
+
+impl Display for WywError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match &self.filename {
+      Some(filename) => write!(f, "{filename}: {} ({})", self.message, self.code),
+      None => write!(f, "{} ({})", self.message, self.code),
+    }
+  }
+}
+
+impl std::error::Error for WywError {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::diagnostics::Severity;
+
+  #[test]
+  fn test_display() {
+    let error = WywError::new("wyw/parse-error", "Unexpected token");
+    assert_eq!(error.to_string(), "Unexpected token (wyw/parse-error)");
+
+    let error = error.with_filename("src/App.tsx");
+    assert_eq!(
+      error.to_string(),
+      "src/App.tsx: Unexpected token (wyw/parse-error)"
+    );
+  }
+
+  #[test]
+  fn test_into_warning() {
+    let warning = WywError::new("wyw/parse-error", "Unexpected token")
+      .with_span(Span::new(1, 2))
+      .into_warning();
+
+    assert_eq!(warning.severity, Severity::Warning);
+    assert_eq!(warning.code, "wyw/parse-error");
+    assert_eq!(warning.primary.span, Span::new(1, 2));
+  }
+}
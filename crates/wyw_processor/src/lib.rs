@@ -1,5 +1,61 @@
-pub trait Processor {
+pub mod diagnostics;
+pub mod error;
+pub mod registry;
+pub mod slug;
+pub mod static_semantics;
+pub mod to_css;
+pub mod units;
+pub mod variable_name;
+
+use crate::diagnostics::Diagnostics;
+use crate::registry::TagSource;
+use crate::static_semantics::StaticSemantics;
+use oxc::ast::ast::TaggedTemplateExpression;
+
+// Everything a processor knows about a call site
+pub struct ProcessorParams<'a> {
+  pub filename: &'a str,
+  pub source_text: &'a str,
+  pub tag_source: &'a TagSource,
+  pub node: &'a TaggedTemplateExpression<'a>,
+  // Index of the call site in the file
+  pub idx: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcessorOutput {
+  // The code that replaces the whole call site
+  pub replacement: String,
+  // The class name generated for the call site, if any
+  pub class_name: Option<String>,
+  // Extracted CSS rules
+  pub css_text: Option<String>,
+}
+
+pub trait Processor: Send + Sync {
   fn id(&self) -> &str;
 
-  fn transform(&self) -> String;
+  // Returns `None` if the call site should be left untouched
+  fn transform(
+    &self,
+    params: &ProcessorParams,
+    diagnostics: &mut Diagnostics,
+  ) -> Option<ProcessorOutput>;
+
+  // The code that replaces the call site before evaluation, so other templates can interpolate it.
+  // Uses the regular replacement by default.
+  fn evaltime_replacement(
+    &self,
+    params: &ProcessorParams,
+    diagnostics: &mut Diagnostics,
+  ) -> Option<String> {
+    self
+      .transform(params, diagnostics)
+      .map(|output| output.replacement)
+  }
+
+  // Processors that don't need evaluated values can opt out of the VM
+  fn static_semantics(&self) -> StaticSemantics {
+    StaticSemantics::Evaluated
+  }
 }
@@ -0,0 +1,450 @@
+use crate::Processor;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display};
+use std::sync::{Arc, Mutex};
+
+// The same as `TagResolverMeta` from `@wyw-in-js/shared`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TagResolverMeta<'a> {
+  pub source_file: Option<&'a str>,
+  pub resolved_source: Option<&'a str>,
+}
+
+// An equivalent of `tagResolver(source, tag, meta)`.
+// Instead of a path to a processor, it returns an id of one of the registered processors.
+pub type TagResolver = dyn Fn(&str, &str, &TagResolverMeta) -> Option<String> + Send + Sync;
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TagSource {
+  pub source: String,
+  pub imported: String,
+}
+
+impl TagSource {
+  pub fn new(source: impl Display, imported: impl Display) -> Self {
+    Self {
+      source: source.to_string(),
+      imported: imported.to_string(),
+    }
+  }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Imported {
+  Default,
+  Named(String),
+  Namespace,
+}
+
+// A binding created by an import declaration, e.g. `import { css as styles } from '@linaria/core'`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ImportBinding {
+  pub local: String,
+  pub source: String,
+  pub imported: Imported,
+  // The resolved path of `source`, it is passed to `tagResolver` as `resolvedSource`
+  pub resolved: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct DefinedTag {
+  pub processor: Arc<dyn Processor>,
+  // The original source and name of the tag after following re-exports
+  pub tag_source: TagSource,
+}
+
+impl Debug for DefinedTag {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("DefinedTag")
+      .field("processor", &self.processor.id())
+      .field("tag_source", &self.tag_source)
+      .finish()
+  }
+}
+
+// The same specifier can point to different modules, so the resolved source is a part of the key.
+// Static tags don't depend on the importer, so the meta is a part of the key only with `tagResolver`.
+type LookupCacheKey = (Option<String>, Option<String>, TagSource);
+
+// With `tagResolver` there are entries for every file, so the cache starts over when it's full
+const LOOKUP_CACHE_CAPACITY: usize = 4096;
+
+#[derive(Default)]
+pub struct ProcessorRegistry {
+  processors: HashMap<String, Arc<dyn Processor>>,
+  tags: HashMap<TagSource, String>,
+  reexports: HashMap<TagSource, TagSource>,
+  tag_resolver: Option<Arc<TagResolver>>,
+  cache: Mutex<HashMap<LookupCacheKey, Option<DefinedTag>>>,
+}
+
+impl ProcessorRegistry {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_tag_resolver(mut self, tag_resolver: Arc<TagResolver>) -> Self {
+    self.tag_resolver = Some(tag_resolver);
+    self
+  }
+
+  // Registers a processor under its id. The same processor can serve many tags.
+  pub fn register(&mut self, processor: Arc<dyn Processor>) -> &mut Self {
+    self
+      .processors
+      .insert(processor.id().to_string(), processor);
+    self.invalidate();
+    self
+  }
+
+  // Maps the `imported` name from the `source` module to a registered processor
+  pub fn add_tag(
+    &mut self,
+    source: impl Display,
+    imported: impl Display,
+    processor_id: impl Display,
+  ) -> &mut Self {
+    self
+      .tags
+      .insert(TagSource::new(source, imported), processor_id.to_string());
+    self.invalidate();
+    self
+  }
+
+  // Declares that `exported` from `source` is a re-export of `imported` from `from`,
+  // e.g. `export { css as styles } from '@linaria/core'` in `ui-kit`.
+  pub fn add_reexport(
+    &mut self,
+    source: impl Display,
+    exported: impl Display,
+    from: impl Display,
+    imported: impl Display,
+  ) -> &mut Self {
+    self.reexports.insert(
+      TagSource::new(source, exported),
+      TagSource::new(from, imported),
+    );
+    self.invalidate();
+    self
+  }
+
+  pub fn get(&self, processor_id: &str) -> Option<&Arc<dyn Processor>> {
+    self.processors.get(processor_id)
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.tags.is_empty() && self.tag_resolver.is_none()
+  }
+
+  // Only `tagResolver` needs resolved sources of imports
+  pub fn has_tag_resolver(&self) -> bool {
+    self.tag_resolver.is_some()
+  }
+
+  fn invalidate(&mut self) {
+    self.cache.get_mut().unwrap().clear();
+  }
+
+  // Follows the chain of re-exports to the original tag
+  fn original(&self, tag_source: TagSource) -> TagSource {
+    let mut current = tag_source;
+    let mut visited = HashSet::new();
+    while let Some(next) = self.reexports.get(&current) {
+      if !visited.insert(current.clone()) {
+        break;
+      }
+
+      current = next.clone();
+    }
+
+    current
+  }
+
+  fn find(&self, tag_source: &TagSource, meta: &TagResolverMeta) -> Option<DefinedTag> {
+    // The resolver has priority over the statically registered tags, the same as in JS
+    if let Some(resolver) = &self.tag_resolver {
+      let resolved = resolver(&tag_source.source, &tag_source.imported, meta);
+      if let Some(processor) = resolved.and_then(|id| self.processors.get(&id)) {
+        return Some(DefinedTag {
+          processor: processor.clone(),
+          tag_source: tag_source.clone(),
+        });
+      }
+    }
+
+    let original = self.original(tag_source.clone());
+    self
+      .tags
+      .get(&original)
+      .and_then(|id| self.processors.get(id))
+      .map(|processor| DefinedTag {
+        processor: processor.clone(),
+        tag_source: original,
+      })
+  }
+
+  pub fn lookup(&self, source: &str, imported: &str, meta: &TagResolverMeta) -> Option<DefinedTag> {
+    let tag_source = TagSource::new(source, imported);
+    let key = match self.tag_resolver {
+      Some(_) => (
+        meta.source_file.map(str::to_string),
+        meta.resolved_source.map(str::to_string),
+        tag_source,
+      ),
+      None => (None, None, tag_source),
+    };
+
+    if let Some(cached) = self.cache.lock().unwrap().get(&key) {
+      return cached.clone();
+    }
+
+    let result = self.find(&key.2, meta);
+    let mut cache = self.cache.lock().unwrap();
+    if cache.len() >= LOOKUP_CACHE_CAPACITY {
+      cache.clear();
+    }
+
+    cache.insert(key, result.clone());
+    result
+  }
+
+  pub fn clear_cache(&self) {
+    self.cache.lock().unwrap().clear();
+  }
+
+  // Looks up a tag imported by `binding` in `source_file`
+  pub fn lookup_binding(
+    &self,
+    binding: &ImportBinding,
+    imported: &str,
+    source_file: Option<&str>,
+  ) -> Option<DefinedTag> {
+    let meta = TagResolverMeta {
+      source_file,
+      resolved_source: binding.resolved.as_deref(),
+    };
+
+    self.lookup(&binding.source, imported, &meta)
+  }
+
+  // Returns the tags that are available in a file with the specified imports
+  pub fn defined_tags<'r>(
+    &'r self,
+    imports: &[ImportBinding],
+    source_file: Option<&'r str>,
+  ) -> DefinedTags<'r> {
+    DefinedTags {
+      registry: self,
+      bindings: imports
+        .iter()
+        .map(|binding| (binding.local.clone(), binding.clone()))
+        .collect(),
+      source_file,
+    }
+  }
+}
+
+// Processors available in a specific file
+pub struct DefinedTags<'r> {
+  registry: &'r ProcessorRegistry,
+  bindings: HashMap<String, ImportBinding>,
+  source_file: Option<&'r str>,
+}
+
+impl DefinedTags<'_> {
+  // Resolves a tag by its qualified local name, e.g. `css`, `styled.div` or `ui.css`.
+  // Returns the processor and the number of name segments that the tag occupies.
+  pub fn resolve(&self, qualified_name: &str) -> Option<(DefinedTag, usize)> {
+    let mut segments = qualified_name.split('.');
+    let root = segments.next()?;
+    let binding = self.bindings.get(root)?;
+
+    let (imported, length) = match &binding.imported {
+      Imported::Default => ("default", 1),
+      Imported::Named(name) => (name.as_str(), 1),
+      // `import * as ui from 'ui-kit'` and `ui.css`
+      Imported::Namespace => (segments.next()?, 2),
+    };
+
+    self
+      .registry
+      .lookup_binding(binding, imported, self.source_file)
+      .map(|tag| (tag, length))
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.bindings.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::diagnostics::Diagnostics;
+  use crate::{ProcessorOutput, ProcessorParams};
+
+  struct TestProcessor(&'static str);
+
+  impl Processor for TestProcessor {
+    fn id(&self) -> &str {
+      self.0
+    }
+
+    fn transform(
+      &self,
+      _params: &ProcessorParams,
+      _diagnostics: &mut Diagnostics,
+    ) -> Option<ProcessorOutput> {
+      None
+    }
+  }
+
+  fn registry() -> ProcessorRegistry {
+    let mut registry = ProcessorRegistry::new();
+    registry
+      .register(Arc::new(TestProcessor("css")))
+      .register(Arc::new(TestProcessor("styled")))
+      .add_tag("@linaria/core", "css", "css")
+      .add_tag("@linaria/react", "styled", "styled")
+      .add_reexport("ui-kit", "styles", "@linaria/core", "css")
+      .add_reexport("ui-kit", "default", "@linaria/react", "styled");
+    registry
+  }
+
+  fn binding(local: &str, source: &str, imported: Imported) -> ImportBinding {
+    ImportBinding {
+      local: local.to_string(),
+      source: source.to_string(),
+      imported,
+      resolved: None,
+    }
+  }
+
+  #[test]
+  fn test_lookup() {
+    let registry = registry();
+    let meta = TagResolverMeta::default();
+
+    let tag = registry.lookup("@linaria/core", "css", &meta).unwrap();
+    assert_eq!(tag.processor.id(), "css");
+
+    let tag = registry.lookup("ui-kit", "styles", &meta).unwrap();
+    assert_eq!(tag.processor.id(), "css");
+    assert_eq!(tag.tag_source, TagSource::new("@linaria/core", "css"));
+
+    assert!(registry.lookup("@linaria/core", "cx", &meta).is_none());
+  }
+
+  #[test]
+  fn test_defined_tags() {
+    let registry = registry();
+    let imports = [
+      binding("css", "@linaria/core", Imported::Named("css".into())),
+      binding("styled", "ui-kit", Imported::Default),
+      binding("core", "@linaria/core", Imported::Namespace),
+      binding("cx", "@linaria/core", Imported::Named("cx".into())),
+    ];
+    let tags = registry.defined_tags(&imports, None);
+
+    let id = |name: &str| {
+      tags
+        .resolve(name)
+        .map(|(tag, len)| (tag.processor.id().to_string(), len))
+    };
+    assert_eq!(id("css"), Some(("css".into(), 1)));
+    assert_eq!(id("styled.div"), Some(("styled".into(), 1)));
+    assert_eq!(id("core.css"), Some(("css".into(), 2)));
+    assert_eq!(id("core"), None);
+    assert_eq!(id("cx"), None);
+    assert_eq!(id("unknown"), None);
+  }
+
+  #[test]
+  fn test_tag_resolver() {
+    let registry = registry().with_tag_resolver(Arc::new(|source, tag, meta| {
+      (source == "./local" && tag == "myCss" && meta.source_file == Some("/app/index.ts"))
+        .then(|| "css".to_string())
+    }));
+
+    let meta = TagResolverMeta {
+      source_file: Some("/app/index.ts"),
+      resolved_source: None,
+    };
+    let tag = registry.lookup("./local", "myCss", &meta).unwrap();
+    assert_eq!(tag.processor.id(), "css");
+    assert_eq!(tag.tag_source, TagSource::new("./local", "myCss"));
+
+    assert!(registry
+      .lookup("./local", "myCss", &TagResolverMeta::default())
+      .is_none());
+    // Static tags still work when the resolver returns nothing
+    assert!(registry.lookup("@linaria/core", "css", &meta).is_some());
+  }
+
+  #[test]
+  fn test_tag_resolver_resolved_source() {
+    let registry = registry().with_tag_resolver(Arc::new(|_source, _tag, meta| {
+      (meta.resolved_source == Some("/app/styles/index.ts")).then(|| "css".to_string())
+    }));
+
+    let meta = |resolved_source| TagResolverMeta {
+      source_file: Some("/app/index.ts"),
+      resolved_source,
+    };
+    assert!(registry
+      .lookup(
+        "styles",
+        "css",
+        &meta(Some("/app/node_modules/styles/index.js"))
+      )
+      .is_none());
+    assert!(registry
+      .lookup("styles", "css", &meta(Some("/app/styles/index.ts")))
+      .is_some());
+  }
+
+  #[test]
+  fn test_defined_tags_resolved_source() {
+    let registry = registry().with_tag_resolver(Arc::new(|_source, _tag, meta| {
+      (meta.resolved_source == Some("/app/styles/index.ts")).then(|| "css".to_string())
+    }));
+
+    let imports = [
+      ImportBinding {
+        resolved: Some("/app/styles/index.ts".to_string()),
+        ..binding("css", "styles", Imported::Named("css".into()))
+      },
+      binding("other", "styles", Imported::Named("css".into())),
+    ];
+    let tags = registry.defined_tags(&imports, Some("/app/index.ts"));
+    assert!(tags.resolve("css").is_some());
+    assert!(tags.resolve("other").is_none());
+  }
+
+  #[test]
+  fn test_lookup_cache_capacity() {
+    let registry = registry().with_tag_resolver(Arc::new(|_, _, _| None));
+    for idx in 0..LOOKUP_CACHE_CAPACITY + 10 {
+      let source_file = format!("/app/{idx}.ts");
+      let meta = TagResolverMeta {
+        source_file: Some(&source_file),
+        resolved_source: None,
+      };
+      assert!(registry.lookup("@linaria/core", "css", &meta).is_some());
+    }
+
+    assert!(registry.cache.lock().unwrap().len() <= LOOKUP_CACHE_CAPACITY);
+  }
+
+  #[test]
+  fn test_reexport_cycle() {
+    let mut registry = registry();
+    registry
+      .add_reexport("a", "css", "b", "css")
+      .add_reexport("b", "css", "a", "css");
+
+    assert!(registry
+      .lookup("a", "css", &TagResolverMeta::default())
+      .is_none());
+  }
+}
@@ -0,0 +1,171 @@
+// murmurhash2 via https://gist.github.com/raycmorgan/588423
+// It works with UTF-16 code units to produce the same hashes as `slugify` from `@wyw-in-js/shared`
+fn do_hash(units: &[u16], seed: u32) -> u32 {
+  const M: u32 = 0x5bd1e995;
+  const R: u32 = 24;
+
+  let mut h = seed ^ units.len() as u32;
+  let mut chunks = units.chunks_exact(4);
+
+  for chunk in &mut chunks {
+    let mut k = (chunk[0] as u32)
+      .wrapping_add((chunk[1] as u32) << 8)
+      .wrapping_add((chunk[2] as u32) << 16)
+      .wrapping_add((chunk[3] as u32) << 24);
+
+    k = k.wrapping_mul(M);
+    k ^= k >> R;
+    k = k.wrapping_mul(M);
+
+    h = h.wrapping_mul(M);
+    h ^= k;
+  }
+
+  match *chunks.remainder() {
+    [a, b, c] => {
+      h ^= a as u32 + ((b as u32) << 8);
+      h ^= (c as u32) << 16;
+      h = h.wrapping_mul(M);
+    }
+    [a, b] => {
+      h ^= a as u32 + ((b as u32) << 8);
+      h = h.wrapping_mul(M);
+    }
+    [a] => {
+      h ^= a as u32;
+      h = h.wrapping_mul(M);
+    }
+    _ => {}
+  }
+
+  h ^= h >> 13;
+  h = h.wrapping_mul(M);
+  h ^= h >> 15;
+
+  h
+}
+
+fn to_base36(mut value: u32) -> String {
+  const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+  if value == 0 {
+    return "0".to_string();
+  }
+
+  let mut result = vec![];
+  while value > 0 {
+    result.push(DIGITS[(value % 36) as usize]);
+    value /= 36;
+  }
+
+  result.reverse();
+  String::from_utf8(result).unwrap()
+}
+
+pub fn slugify(code: &str) -> String {
+  let units = code.encode_utf16().collect::<Vec<_>>();
+  to_base36(do_hash(&units, 0))
+}
+
+// Replaces `[name]` placeholders in the pattern with the values of the corresponding arguments.
+// Unknown placeholders are replaced with an empty string.
+pub fn build_slug(pattern: &str, args: &[(&str, String)]) -> String {
+  build_slug_with(pattern, |name| {
+    args
+      .iter()
+      .find(|(arg, _)| *arg == name)
+      .map(|(_, value)| value.clone())
+  })
+}
+
+// The same as `build_slug`, but an argument is read for every placeholder, like a getter in JS
+pub fn build_slug_with(pattern: &str, mut arg: impl FnMut(&str) -> Option<String>) -> String {
+  let mut result = String::with_capacity(pattern.len());
+  let mut rest = pattern;
+
+  while let Some(start) = rest.find('[') {
+    let Some(end) = rest[start + 1..].find(']') else {
+      break;
+    };
+
+    let name = &rest[start + 1..start + 1 + end];
+    result.push_str(&rest[..start]);
+    if let Some(value) = arg(name) {
+      result.push_str(&value);
+    }
+
+    rest = &rest[start + end + 2..];
+  }
+
+  result.push_str(rest);
+  result
+}
+
+pub fn to_valid_css_identifier(s: &str) -> String {
+  let mut result = s
+    .chars()
+    .map(|ch| {
+      if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' || ch as u32 >= 0xa0 {
+        ch
+      } else {
+        '_'
+      }
+    })
+    .collect::<String>();
+
+  if result.starts_with(|ch: char| ch.is_ascii_digit()) {
+    result.replace_range(..1, "_");
+  }
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_slugify() {
+    // The values are taken from `slugify` in `@wyw-in-js/shared`
+    assert_eq!(slugify(""), "0");
+    assert_eq!(slugify("a"), "14mfbry");
+    assert_eq!(slugify("abcd"), "aougpt");
+    assert_eq!(slugify("src/App.tsx:0"), "uwg8pj");
+    assert_eq!(slugify("ÿÿÿÿ\u{1F600}x"), "8lmicp");
+  }
+
+  #[test]
+  fn test_build_slug() {
+    let args = [
+      ("componentName", "Title".to_string()),
+      ("index", "1".to_string()),
+    ];
+
+    assert_eq!(build_slug("[componentName]-[index]", &args), "Title-1");
+    assert_eq!(build_slug("[unknown]-[index]", &args), "-1");
+    assert_eq!(build_slug("no-placeholders", &args), "no-placeholders");
+    assert_eq!(build_slug("unclosed-[index", &args), "unclosed-[index");
+  }
+
+  #[test]
+  fn test_to_valid_css_identifier() {
+    assert_eq!(to_valid_css_identifier("1abc.def"), "_abc_def");
+    assert_eq!(to_valid_css_identifier("Title-1_ü"), "Title-1_ü");
+  }
+
+  // Outputs of `toValidCSSIdentifier.ts`, see `scripts/generate-to-css-fixtures.mjs`
+  #[test]
+  fn test_to_valid_css_identifier_fixtures() {
+    let fixtures: Vec<serde_json::Value> =
+      serde_json::from_str(include_str!("../fixtures/css_identifiers.json")).unwrap();
+
+    for fixture in fixtures {
+      let input = fixture["input"].as_str().unwrap();
+      assert_eq!(
+        to_valid_css_identifier(input),
+        fixture["output"].as_str().unwrap(),
+        "{input:?}"
+      );
+    }
+  }
+}
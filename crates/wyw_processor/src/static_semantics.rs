@@ -0,0 +1,101 @@
+// Declares whether the result of a processor can be computed without the VM.
+// It is a native counterpart of `processorStaticSemantics.ts`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum StaticSemantics {
+  // The output depends on evaluated values of interpolations
+  #[default]
+  Evaluated,
+  // The output is a pure function of the literal template text and statically known bindings.
+  // If `runtime_callbacks` is set, function interpolations (e.g. `${props => props.color}`)
+  // are turned into CSS variables and do not need evaluation either.
+  Pure {
+    runtime_callbacks: bool,
+  },
+}
+
+// What the pipeline knows about an interpolation before evaluation
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InterpolationKind {
+  // A string, number or template literal without expressions
+  Literal,
+  // A binding whose value is known statically, e.g. a const with a literal initializer
+  StaticBinding,
+  // An inline arrow or function expression
+  RuntimeCallback,
+  // Anything else: imports, calls, references to mutable bindings, etc.
+  Dynamic,
+}
+
+impl StaticSemantics {
+  pub fn is_pure(&self) -> bool {
+    matches!(self, Self::Pure { .. })
+  }
+
+  // Checks if a single call site can be processed without evaluation
+  pub fn can_skip_evaluation(
+    &self,
+    interpolations: impl IntoIterator<Item = InterpolationKind>,
+  ) -> bool {
+    let Self::Pure { runtime_callbacks } = *self else {
+      return false;
+    };
+
+    interpolations.into_iter().all(|kind| match kind {
+      InterpolationKind::Literal | InterpolationKind::StaticBinding => true,
+      InterpolationKind::RuntimeCallback => runtime_callbacks,
+      InterpolationKind::Dynamic => false,
+    })
+  }
+}
+
+// A file can skip evaluation only if every processor call site in it can
+pub fn can_skip_file_evaluation<I>(
+  call_sites: impl IntoIterator<Item = (StaticSemantics, I)>,
+) -> bool
+where
+  I: IntoIterator<Item = InterpolationKind>,
+{
+  call_sites
+    .into_iter()
+    .all(|(semantics, interpolations)| semantics.can_skip_evaluation(interpolations))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use InterpolationKind::*;
+
+  #[test]
+  fn test_can_skip_evaluation() {
+    let pure = StaticSemantics::Pure {
+      runtime_callbacks: false,
+    };
+    let styled = StaticSemantics::Pure {
+      runtime_callbacks: true,
+    };
+
+    assert!(pure.can_skip_evaluation([]));
+    assert!(pure.can_skip_evaluation([Literal, StaticBinding]));
+    assert!(!pure.can_skip_evaluation([Literal, RuntimeCallback]));
+    assert!(styled.can_skip_evaluation([Literal, RuntimeCallback]));
+    assert!(!styled.can_skip_evaluation([Dynamic]));
+    assert!(!StaticSemantics::Evaluated.can_skip_evaluation([]));
+  }
+
+  #[test]
+  fn test_can_skip_file_evaluation() {
+    let pure = StaticSemantics::Pure {
+      runtime_callbacks: false,
+    };
+
+    assert!(can_skip_file_evaluation::<Vec<_>>([]));
+    assert!(can_skip_file_evaluation([
+      (pure, vec![Literal]),
+      (pure, vec![StaticBinding]),
+    ]));
+    assert!(!can_skip_file_evaluation([
+      (pure, vec![Literal]),
+      (StaticSemantics::Evaluated, vec![]),
+    ]));
+  }
+}
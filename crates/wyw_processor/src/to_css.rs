@@ -0,0 +1,322 @@
+use crate::units::is_unitless;
+
+// A JSON-like representation of an evaluated interpolation value.
+// Objects keep their keys in insertion order, just like JS objects.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CssValue {
+  Undefined,
+  Null,
+  Bool(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<CssValue>),
+  Object(Vec<(String, CssValue)>),
+}
+
+impl CssValue {
+  fn is_property_value(&self) -> bool {
+    match self {
+      Self::String(_) => true,
+      Self::Number(n) => n.is_finite(),
+      _ => false,
+    }
+  }
+
+  // Ignore all falsy values except numbers
+  fn is_kept(&self) -> bool {
+    match self {
+      Self::Undefined | Self::Null | Self::Bool(false) => false,
+      Self::String(s) => !s.is_empty(),
+      _ => true,
+    }
+  }
+
+  pub fn is_cssable(&self) -> bool {
+    match self {
+      Self::Array(items) => items.iter().all(Self::is_cssable),
+      Self::Object(entries) => entries.iter().all(|(_, value)| value.is_cssable()),
+      _ => self.is_property_value(),
+    }
+  }
+}
+
+impl From<&str> for CssValue {
+  fn from(value: &str) -> Self {
+    Self::String(value.to_string())
+  }
+}
+
+impl From<String> for CssValue {
+  fn from(value: String) -> Self {
+    Self::String(value)
+  }
+}
+
+impl From<f64> for CssValue {
+  fn from(value: f64) -> Self {
+    Self::Number(value)
+  }
+}
+
+impl From<bool> for CssValue {
+  fn from(value: bool) -> Self {
+    Self::Bool(value)
+  }
+}
+
+// Mirrors `Number.prototype.toString()` for the values we can meet in styles
+pub fn number_to_string(value: f64) -> String {
+  if value.is_nan() {
+    return "NaN".to_string();
+  }
+
+  if value.is_infinite() {
+    return if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+  }
+
+  if value == 0.0 {
+    // It covers `-0` as well
+    return "0".to_string();
+  }
+
+  let abs = value.abs();
+  if (1e-6..1e21).contains(&abs) {
+    return format!("{value}");
+  }
+
+  // JS switches to the exponential notation and always prints the exponent sign
+  let formatted = format!("{value:e}");
+  match formatted.split_once('e') {
+    Some((mantissa, exponent)) if !exponent.starts_with('-') => {
+      format!("{mantissa}e+{exponent}")
+    }
+    _ => formatted,
+  }
+}
+
+fn hyphenate(s: &str) -> String {
+  if s.starts_with("--") {
+    // It's a custom property which is already well formatted.
+    return s.to_string();
+  }
+
+  // Hyphenate CSS property names from camelCase version from JS string
+  let mut result = String::with_capacity(s.len() + 4);
+  for ch in s.chars() {
+    if ch.is_ascii_uppercase() {
+      result.push('-');
+      result.push(ch.to_ascii_lowercase());
+    } else {
+      result.push(ch);
+    }
+  }
+
+  // Special case for `-ms` because in JS it starts with `ms` unlike `Webkit`
+  if result.starts_with("ms-") {
+    result.insert(0, '-');
+  }
+
+  result
+}
+
+// Strip vendor prefixes when checking if the value is unitless
+fn strip_vendor_prefix(key: &str) -> String {
+  for prefix in ["Webkit", "Moz", "O", "ms"] {
+    let Some(rest) = key.strip_prefix(prefix) else {
+      continue;
+    };
+
+    let mut chars = rest.chars();
+    if let Some(first) = chars.next().filter(char::is_ascii_uppercase) {
+      let tail = chars.as_str();
+      if !tail.is_empty() {
+        return format!("{}{tail}", first.to_ascii_lowercase());
+      }
+    }
+  }
+
+  key.to_string()
+}
+
+fn property_value_to_string(key: &str, value: &CssValue) -> String {
+  match value {
+    CssValue::Number(n) if *n != 0.0 && !is_unitless(&strip_vendor_prefix(key)) => {
+      format!("{}px", number_to_string(*n))
+    }
+    CssValue::Number(n) => number_to_string(*n),
+    CssValue::String(s) => s.clone(),
+    _ => unreachable!("only property values are expected here"),
+  }
+}
+
+// Some tools such as polished.js output JS objects
+// To support them transparently, we convert JS objects to CSS strings
+pub fn to_css(value: &CssValue) -> String {
+  match value {
+    CssValue::Array(items) => items.iter().map(to_css).collect::<Vec<_>>().join("\n"),
+    CssValue::String(s) => s.clone(),
+    CssValue::Number(n) if n.is_finite() => number_to_string(*n),
+    CssValue::Object(entries) => entries
+      .iter()
+      .filter(|(_, value)| value.is_kept())
+      .map(|(key, value)| {
+        if value.is_property_value() {
+          format!(
+            "{}: {};",
+            hyphenate(key),
+            property_value_to_string(key, value)
+          )
+        } else {
+          format!("{key} {{ {} }}", to_css(value))
+        }
+      })
+      .collect::<Vec<_>>()
+      .join(" "),
+    // `Object.entries` of any other primitive is empty
+    _ => String::new(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::units::UNITLESS;
+
+  fn object(entries: Vec<(&str, CssValue)>) -> CssValue {
+    CssValue::Object(
+      entries
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect(),
+    )
+  }
+
+  #[test]
+  fn test_primitives() {
+    assert_eq!(to_css(&"red".into()), "red");
+    assert_eq!(to_css(&42.0.into()), "42");
+    assert_eq!(to_css(&1.5.into()), "1.5");
+  }
+
+  #[test]
+  fn test_properties() {
+    let value = object(vec![
+      ("backgroundColor", "red".into()),
+      ("fontSize", 12.0.into()),
+      ("margin", 0.0.into()),
+      ("opacity", 0.5.into()),
+      ("WebkitFlexGrow", 1.0.into()),
+      ("msTransition", "none".into()),
+      ("WebkitTransitionDelay", 2.0.into()),
+      ("--customProperty", 10.0.into()),
+    ]);
+
+    assert_eq!(
+      to_css(&value),
+      "background-color: red; font-size: 12px; margin: 0; opacity: 0.5; \
+       -webkit-flex-grow: 1; -ms-transition: none; -webkit-transition-delay: 2px; \
+       --customProperty: 10px;"
+    );
+  }
+
+  #[test]
+  fn test_falsy_values() {
+    let value = object(vec![
+      ("color", CssValue::Null),
+      ("display", CssValue::Undefined),
+      ("content", "".into()),
+      ("visibility", false.into()),
+      ("top", 0.0.into()),
+    ]);
+
+    assert_eq!(to_css(&value), "top: 0;");
+  }
+
+  #[test]
+  fn test_nested_selectors_and_arrays() {
+    let value = object(vec![
+      ("color", "red".into()),
+      ("&:hover", object(vec![("color", "blue".into())])),
+      (
+        "@media (min-width: 200px)",
+        object(vec![("& > span", object(vec![("paddingTop", 4.0.into())]))]),
+      ),
+    ]);
+
+    assert_eq!(
+      to_css(&value),
+      "color: red; &:hover { color: blue; } \
+       @media (min-width: 200px) { & > span { padding-top: 4px; } }"
+    );
+
+    let list = CssValue::Array(vec![
+      object(vec![("color", "red".into())]),
+      "display: block;".into(),
+    ]);
+    assert_eq!(to_css(&list), "color: red;\ndisplay: block;");
+  }
+
+  #[test]
+  fn test_unitless_properties() {
+    for property in UNITLESS {
+      let value = object(vec![(property, 2.0.into())]);
+      assert!(!to_css(&value).ends_with("px;"), "{property}");
+
+      let mut chars = property.chars();
+      let first = chars.next().unwrap().to_ascii_uppercase();
+      let prefixed = format!("Webkit{first}{}", chars.as_str());
+      let value = object(vec![(&prefixed, 2.0.into())]);
+      assert!(!to_css(&value).ends_with("px;"), "{prefixed}");
+    }
+  }
+
+  #[test]
+  fn test_number_to_string() {
+    assert_eq!(number_to_string(-0.0), "0");
+    assert_eq!(number_to_string(0.1 + 0.2), "0.30000000000000004");
+    assert_eq!(number_to_string(1e21), "1e+21");
+    assert_eq!(number_to_string(1.5e-7), "1.5e-7");
+    assert_eq!(number_to_string(0.000001), "0.000001");
+    assert_eq!(number_to_string(-12.25), "-12.25");
+  }
+
+  fn from_json(value: &serde_json::Value) -> CssValue {
+    match value {
+      serde_json::Value::Null => CssValue::Null,
+      serde_json::Value::Bool(b) => CssValue::Bool(*b),
+      serde_json::Value::Number(n) => CssValue::Number(n.as_f64().unwrap()),
+      serde_json::Value::String(s) => CssValue::String(s.clone()),
+      serde_json::Value::Array(items) => CssValue::Array(items.iter().map(from_json).collect()),
+      serde_json::Value::Object(entries) => CssValue::Object(
+        entries
+          .iter()
+          .map(|(key, value)| (key.clone(), from_json(value)))
+          .collect(),
+      ),
+    }
+  }
+
+  // Random values rendered by `toCSS.ts`, see `scripts/generate-to-css-fixtures.mjs`
+  #[test]
+  fn test_js_fixtures() {
+    let fixtures: serde_json::Value =
+      serde_json::from_str(include_str!("../fixtures/to_css.json")).unwrap();
+
+    for fixture in fixtures.as_array().unwrap() {
+      let input = from_json(&fixture["input"]);
+      assert_eq!(
+        to_css(&input),
+        fixture["output"].as_str().unwrap(),
+        "{}",
+        fixture["input"]
+      );
+    }
+  }
+
+  #[test]
+  fn test_is_cssable() {
+    assert!(object(vec![("a", CssValue::Array(vec!["b".into()]))]).is_cssable());
+    assert!(!object(vec![("a", CssValue::Null)]).is_cssable());
+    assert!(!CssValue::Number(f64::NAN).is_cssable());
+  }
+}
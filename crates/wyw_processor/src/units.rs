@@ -0,0 +1,120 @@
+// https://www.w3.org/TR/css-values-4/
+#[rustfmt::skip]
+pub const UNITS: &[&str] = &[
+  // font relative lengths
+  "em",
+  "ex",
+  "cap",
+  "ch",
+  "ic",
+  "rem",
+  "lh",
+  "rlh",
+
+  // viewport percentage lengths
+  "vw",
+  "vh",
+  "vi",
+  "vb",
+  "vmin",
+  "vmax",
+
+  // absolute lengths
+  "cm",
+  "mm",
+  "Q",
+  "in",
+  "pc",
+  "pt",
+  "px",
+
+  // angle units
+  "deg",
+  "grad",
+  "rad",
+  "turn",
+
+  // duration units
+  "s",
+  "ms",
+
+  // frequency units
+  "Hz",
+  "kHz",
+
+  // resolution units
+  "dpi",
+  "dpcm",
+  "dppx",
+  "x",
+
+  // https://www.w3.org/TR/css-grid-1/#fr-unit
+  "fr",
+
+  // percentages
+  "%",
+];
+
+pub const UNITLESS: &[&str] = &[
+  "animationIterationCount",
+  "borderImageOutset",
+  "borderImageSlice",
+  "borderImageWidth",
+  "boxFlex",
+  "boxFlexGroup",
+  "boxOrdinalGroup",
+  "columnCount",
+  "columns",
+  "flex",
+  "flexGrow",
+  "flexPositive",
+  "flexShrink",
+  "flexNegative",
+  "flexOrder",
+  "gridRow",
+  "gridRowEnd",
+  "gridRowSpan",
+  "gridRowStart",
+  "gridColumn",
+  "gridColumnEnd",
+  "gridColumnSpan",
+  "gridColumnStart",
+  "fontWeight",
+  "lineClamp",
+  "lineHeight",
+  "opacity",
+  "order",
+  "orphans",
+  "tabSize",
+  "widows",
+  "zIndex",
+  "zoom",
+  // SVG-related properties
+  "fillOpacity",
+  "floodOpacity",
+  "stopOpacity",
+  "strokeDasharray",
+  "strokeDashoffset",
+  "strokeMiterlimit",
+  "strokeOpacity",
+  "strokeWidth",
+];
+
+pub fn is_unitless(property: &str) -> bool {
+  UNITLESS.contains(&property)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Lists exported from `units.ts`, see `scripts/generate-to-css-fixtures.mjs`
+  #[test]
+  fn test_js_lists() {
+    let fixture: serde_json::Value =
+      serde_json::from_str(include_str!("../fixtures/units.json")).unwrap();
+
+    assert_eq!(fixture["units"], serde_json::json!(UNITS));
+    assert_eq!(fixture["unitless"], serde_json::json!(UNITLESS));
+  }
+}
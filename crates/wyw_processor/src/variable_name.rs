@@ -0,0 +1,285 @@
+use crate::slug::{build_slug_with, slugify, to_valid_css_identifier};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+use std::sync::Arc;
+
+// Controls how an interpolation is referenced from the generated CSS
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum VariableNameConfig {
+  // `var(--id)`
+  #[default]
+  Var,
+  // `--id`
+  Dashes,
+  // `id`
+  Raw,
+}
+
+impl VariableNameConfig {
+  pub fn variable_name(&self, var_id: &str) -> String {
+    match self {
+      Self::Var => format!("var(--{var_id})"),
+      Self::Dashes => format!("--{var_id}"),
+      Self::Raw => var_id.to_string(),
+    }
+  }
+}
+
+impl FromStr for VariableNameConfig {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "var" => Ok(Self::Var),
+      "dashes" => Ok(Self::Dashes),
+      "raw" => Ok(Self::Raw),
+      unknown => Err(format!(
+        "Unknown variableNameConfig \"{unknown}\", expected \"var\", \"dashes\" or \"raw\""
+      )),
+    }
+  }
+}
+
+// The same set of values as `IVariableContext` from `@wyw-in-js/shared`
+#[derive(Clone, Debug)]
+pub struct VariableContext<'a> {
+  pub component_name: String,
+  pub component_slug: String,
+  pub preceding_css: String,
+  pub processor: String,
+  pub source: String,
+  pub unit: String,
+  pub value_slug: String,
+  next_index: &'a Cell<usize>,
+}
+
+impl VariableContext<'_> {
+  // `index` is a getter in JS, so every read takes the next index of the component
+  pub fn index(&self) -> usize {
+    let index = self.next_index.get();
+    self.next_index.set(index + 1);
+    index
+  }
+
+  // Arguments of `variableNameSlug` patterns, `index` is read for every placeholder
+  fn slug_arg(&self, name: &str) -> Option<String> {
+    let value = match name {
+      "componentName" => self.component_name.clone(),
+      "componentSlug" => self.component_slug.clone(),
+      "index" => self.index().to_string(),
+      "precedingCss" => self.preceding_css.clone(),
+      "processor" => self.processor.clone(),
+      "source" => self.source.clone(),
+      "unit" => self.unit.clone(),
+      "valueSlug" => self.value_slug.clone(),
+      _ => return None,
+    };
+
+    Some(value)
+  }
+}
+
+pub type VariableNameFn = dyn Fn(&VariableContext<'_>) -> String + Send + Sync;
+
+// `variableNameSlug` can be either a pattern with `[placeholders]` or a function
+#[derive(Clone)]
+pub enum VariableNameSlug {
+  Pattern(String),
+  Function(Arc<VariableNameFn>),
+}
+
+impl VariableNameSlug {
+  pub fn build(&self, context: &VariableContext) -> String {
+    match self {
+      Self::Pattern(pattern) => build_slug_with(pattern, |name| context.slug_arg(name)),
+      Self::Function(func) => func(context),
+    }
+  }
+}
+
+impl Debug for VariableNameSlug {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Pattern(pattern) => write!(f, "Pattern({pattern:?})"),
+      Self::Function(_) => write!(f, "Function"),
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct VariableNameOptions {
+  pub config: VariableNameConfig,
+  pub slug: Option<VariableNameSlug>,
+}
+
+// Generates names of CSS custom properties for the dynamic interpolations of a single component.
+// Should be created once per processed tag.
+#[derive(Debug)]
+pub struct VariableNames {
+  component_name: String,
+  component_slug: String,
+  processor: String,
+  options: VariableNameOptions,
+  next_index: Cell<usize>,
+  cache: HashMap<String, String>,
+}
+
+impl VariableNames {
+  pub fn new(
+    component_name: impl Display,
+    component_slug: impl Display,
+    processor: impl Display,
+    options: VariableNameOptions,
+  ) -> Self {
+    Self {
+      component_name: component_name.to_string(),
+      component_slug: component_slug.to_string(),
+      processor: processor.to_string(),
+      options,
+      next_index: Cell::new(0),
+      cache: HashMap::new(),
+    }
+  }
+
+  fn context(&self, source: &str, unit: &str, preceding_css: &str) -> VariableContext<'_> {
+    VariableContext {
+      component_name: self.component_name.clone(),
+      component_slug: self.component_slug.clone(),
+      preceding_css: preceding_css.to_string(),
+      processor: self.processor.clone(),
+      source: source.to_string(),
+      unit: unit.to_string(),
+      value_slug: slugify(&format!("{source}{unit}")),
+      next_index: &self.next_index,
+    }
+  }
+
+  // Returns an identifier of the variable without `--` and `var()`.
+  // Interpolations with the same source and unit share the same variable
+  // unless a custom `variableNameSlug` is specified.
+  pub fn variable_id(&mut self, source: &str, unit: &str, preceding_css: &str) -> String {
+    let value = format!("{source}{unit}");
+    if let Some(id) = self.cache.get(&value) {
+      return id.clone();
+    }
+
+    let context = self.context(source, unit, preceding_css);
+    if let Some(slug) = &self.options.slug {
+      return to_valid_css_identifier(&slug.build(&context));
+    }
+
+    let id = format!("{}-{}", self.component_slug, context.index());
+    self.cache.insert(value, id.clone());
+    id
+  }
+
+  // Returns the reference to the variable that should be inserted into the CSS
+  pub fn variable_name(&mut self, source: &str, unit: &str, preceding_css: &str) -> String {
+    let id = self.variable_id(source, unit, preceding_css);
+    self.options.config.variable_name(&id)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn names(options: VariableNameOptions) -> VariableNames {
+    VariableNames::new("Title", "t1xyz", "StyledProcessor", options)
+  }
+
+  #[test]
+  fn test_config() {
+    assert_eq!(VariableNameConfig::Var.variable_name("a-0"), "var(--a-0)");
+    assert_eq!(VariableNameConfig::Dashes.variable_name("a-0"), "--a-0");
+    assert_eq!(VariableNameConfig::Raw.variable_name("a-0"), "a-0");
+    assert_eq!("dashes".parse(), Ok(VariableNameConfig::Dashes));
+    assert!("unknown".parse::<VariableNameConfig>().is_err());
+  }
+
+  #[test]
+  fn test_default_names() {
+    let mut names = names(VariableNameOptions::default());
+
+    assert_eq!(
+      names.variable_name("props.size", "px", "width: "),
+      "var(--t1xyz-0)"
+    );
+    assert_eq!(
+      names.variable_name("props.color", "", "color: "),
+      "var(--t1xyz-1)"
+    );
+    assert_eq!(
+      names.variable_name("props.size", "px", "height: "),
+      "var(--t1xyz-0)"
+    );
+    assert_eq!(
+      names.variable_name("props.size", "em", "height: "),
+      "var(--t1xyz-2)"
+    );
+  }
+
+  #[test]
+  fn test_slug_pattern() {
+    let mut names = names(VariableNameOptions {
+      config: VariableNameConfig::Dashes,
+      slug: Some(VariableNameSlug::Pattern(
+        "[componentName]-[index]-[unit]".to_string(),
+      )),
+    });
+
+    assert_eq!(names.variable_name("props.size", "px", ""), "--Title-0-px");
+    assert_eq!(names.variable_name("props.size", "%", ""), "--Title-1-_");
+  }
+
+  #[test]
+  fn test_slug_pattern_index() {
+    // Every `[index]` reads the getter again, like `buildSlug` in JS
+    let mut names = names(VariableNameOptions {
+      config: VariableNameConfig::Raw,
+      slug: Some(VariableNameSlug::Pattern("v[index]-[index]".to_string())),
+    });
+
+    assert_eq!(names.variable_name("props.size", "px", ""), "v0-1");
+    assert_eq!(names.variable_name("props.gap", "px", ""), "v2-3");
+  }
+
+  #[test]
+  fn test_slug_function() {
+    let mut names = names(VariableNameOptions {
+      config: VariableNameConfig::Raw,
+      slug: Some(VariableNameSlug::Function(Arc::new(|ctx| {
+        format!("{}.{}", ctx.component_slug, ctx.value_slug)
+      }))),
+    });
+
+    assert_eq!(
+      names.variable_name("props.size", "px", ""),
+      format!("t1xyz_{}", slugify("props.sizepx"))
+    );
+  }
+
+  #[test]
+  fn test_slug_function_index() {
+    // The index is taken only when the function reads it
+    let mut names = names(VariableNameOptions {
+      config: VariableNameConfig::Raw,
+      slug: Some(VariableNameSlug::Function(Arc::new(|ctx| {
+        if ctx.unit == "px" {
+          format!("v{}-{}", ctx.index(), ctx.index())
+        } else {
+          ctx.value_slug.clone()
+        }
+      }))),
+    });
+
+    assert_eq!(
+      names.variable_name("props.color", "", ""),
+      slugify("props.color")
+    );
+    assert_eq!(names.variable_name("props.size", "px", ""), "v0-1");
+    assert_eq!(names.variable_name("props.gap", "px", ""), "v2-3");
+  }
+}
@@ -1,9 +1,23 @@
 import {
+  buildStaticPlan,
+  NativeTransformer,
+  preeval,
   type TransformOptions,
   TransformTargetProcessors,
   transform,
+  transformAsync,
 } from '../index';
 
+const options: TransformOptions = {
+  targets: [
+    {
+      specifier: 'sample-tag',
+      source: 'sampleTag',
+      processor: TransformTargetProcessors.SampleTag,
+    },
+  ],
+};
+
 describe('sample-tag-processor', () => {
   it('should transform', () => {
     const code = `
@@ -11,18 +25,204 @@ describe('sample-tag-processor', () => {
     
     export const styles = sampleTag\`Hello, world!\`;
   `;
-    const options: TransformOptions = {
-      targets: [
-        {
-          specifier: 'sample-tag',
-          source: 'sampleTag',
-          processor: TransformTargetProcessors.SampleTag,
-        },
-      ],
-    };
 
     const result = transform('index.ts', code, options);
 
-    expect(result).toBe('Hello, World!');
+    expect(result.code.trim()).toBe('export const styles = "Hello, world!";');
+    expect(result.diagnostics).toEqual([]);
+    expect(result.dependencies).toEqual([]);
+    expect(result.processedTags).toHaveLength(1);
+    expect(result.processedTags[0]).toMatchObject({
+      processor: 'sample-tag-processor',
+      source: 'sample-tag',
+      imported: 'sampleTag',
+      start: { line: 4, column: 26 },
+    });
+  });
+
+  it('should ignore tags from other packages', () => {
+    const code = `
+    import { sampleTag } from 'another-tag';
+
+    export const styles = sampleTag\`Hello, world!\`;
+  `;
+
+    const result = transform('index.ts', code, options);
+
+    expect(result.code).toBe(code);
+    expect(result.processedTags).toEqual([]);
+  });
+
+  it('should report interpolations', () => {
+    const code = `import { sampleTag } from 'sample-tag';
+export const styles = sampleTag\`Hello, \${name}!\`;`;
+
+    const result = transform('index.ts', code, options);
+
+    expect(result.code).toBe(code);
+    expect(result.diagnostics).toHaveLength(1);
+    expect(result.diagnostics[0]).toMatchObject({
+      code: 'sample-tag/unsupported-interpolation',
+      severity: 'error',
+      start: { line: 2, column: 41 },
+      end: { line: 2, column: 45 },
+    });
+  });
+
+  it('should transform asynchronously', async () => {
+    const codes = ['first', 'second', 'third'].map(
+      (text) => `import { sampleTag } from 'sample-tag';
+export const styles = sampleTag\`${text}\`;`
+    );
+
+    const results = await Promise.all(
+      codes.map((code) => transformAsync('index.ts', code, options))
+    );
+
+    expect(results.map((result) => result.code)).toEqual([
+      'export const styles = "first";',
+      'export const styles = "second";',
+      'export const styles = "third";',
+    ]);
+  });
+
+  it('should transform asynchronously with NativeTransformer', async () => {
+    const transformer = new NativeTransformer(options, 2);
+    const files = ['first', 'second', 'third'].map((text) => ({
+      filename: `${text}.ts`,
+      sourceCode: `import { sampleTag } from 'sample-tag';
+export const styles = sampleTag\`${text}\`;`,
+    }));
+
+    const results = await Promise.all(
+      files.map((file) => transformer.transformAsync(file))
+    );
+
+    expect(results.map((result) => result.code)).toEqual([
+      'export const styles = "first";',
+      'export const styles = "second";',
+      'export const styles = "third";',
+    ]);
+  });
+
+  it('should transform many files with NativeTransformer', () => {
+    const transformer = new NativeTransformer(options);
+    const files = ['a', 'b'].map((name) => ({
+      filename: `${name}.ts`,
+      sourceCode: `import { sampleTag } from 'sample-tag';
+export const styles = sampleTag\`${name}\`;`,
+    }));
+
+    const results = transformer.transformMany(files);
+
+    expect(results.map((result) => result.code)).toEqual([
+      'export const styles = "a";',
+      'export const styles = "b";',
+    ]);
+    expect(transformer.transform(files[0]).code).toBe(results[0].code);
+  });
+
+  it('should throw errors with codes and locations', () => {
+    expect(() => transform('index.ts', 'const a = ;', options)).toThrow(
+      expect.objectContaining({
+        code: 'wyw/parse-error',
+        filename: 'index.ts',
+        start: { line: 1, column: 10 },
+      })
+    );
+  });
+
+  it('should downgrade errors with softErrors', () => {
+    const result = transform('index.ts', 'const a = ;', {
+      ...options,
+      features: { softErrors: '**/*.ts' },
+    });
+
+    expect(result.code).toBe('const a = ;');
+    expect(result.diagnostics[0]).toMatchObject({
+      code: 'wyw/parse-error',
+      severity: 'warning',
+    });
+  });
+
+  it('should return a source map', () => {
+    const code = `import { sampleTag } from 'sample-tag';
+export const styles = sampleTag\`Hello, world!\`;`;
+
+    const result = transform('index.ts', code, options);
+    const map = JSON.parse(result.sourceMap!);
+
+    expect(map).toMatchObject({
+      version: 3,
+      sources: ['index.ts'],
+      sourcesContent: [code],
+    });
+  });
+
+  it('should build a static plan', () => {
+    const code = `import { sampleTag } from 'sample-tag';
+const name = 'world';
+let counter = 0;
+counter += 1;
+export const a = sampleTag\`Hello, \${name}!\`;
+export const b = sampleTag\`\${counter}\`;`;
+
+    const plan = buildStaticPlan('index.ts', code, options);
+
+    expect(plan.canSkipEvaluation).toBe(false);
+    expect(plan.env.values).toEqual([
+      {
+        name: 'name',
+        start: { line: 5, column: 36 },
+        end: { line: 5, column: 40 },
+        code: '"world"',
+      },
+    ]);
+    expect(plan.env.unresolved).toMatchObject([
+      {
+        name: 'counter',
+        start: { line: 6, column: 29 },
+        end: { line: 6, column: 36 },
+      },
+    ]);
+    expect(plan.processorUsages.map((usage) => usage.interpolations)).toEqual([
+      ['static-binding'],
+      ['dynamic'],
+    ]);
+    expect(plan.needs).toEqual([
+      {
+        kind: 'eval',
+        importer: 'index.ts',
+        source: 'index.ts',
+        only: ['counter'],
+        reason: 'unresolved-static-value',
+      },
+    ]);
+  });
+
+  it('should prepare CommonJS code for evaluation', () => {
+    const code = `import { sampleTag } from 'sample-tag';
+exports.styles = sampleTag\`Hello, world!\`;`;
+
+    const result = preeval('index.js', code, options);
+
+    expect(result.code).toBe(
+      'exports.styles = "Hello, world!";\nexports.__wywPreval = {};'
+    );
+    expect(result.processedTags).toHaveLength(1);
+    expect(result.dependencyNames).toEqual([]);
+  });
+
+  it('should prepare code for evaluation with NativeTransformer', () => {
+    const transformer = new NativeTransformer(options);
+    const result = transformer.preeval({
+      filename: 'index.js',
+      sourceCode: `import { sampleTag } from 'sample-tag';
+export const styles = sampleTag\`Hello, world!\`;`,
+    });
+
+    expect(result.code).toBe(
+      'export const styles = "Hello, world!";\nexport const __wywPreval = {};'
+    );
   });
 });
@@ -1,44 +1,33 @@
 #![deny(clippy::all)]
 
-use std::collections::HashMap;
-use wyw_processor::Processor;
+use oxc::span::GetSpan;
+use wyw_processor::diagnostics::{Diagnostic, Diagnostics};
+use wyw_processor::static_semantics::StaticSemantics;
+use wyw_processor::{Processor, ProcessorOutput, ProcessorParams};
 
-#[macro_use]
-extern crate napi_derive;
+wyw_macros::define_processors!(SampleTagProcessor);
 
-#[napi(string_enum)]
-#[derive(Eq, Hash, PartialEq)]
-pub enum TransformTargetProcessors {
-  SampleTag,
-}
-
-#[napi(object)]
-pub struct TransformTarget {
-  pub specifier: String,
-  pub source: String,
-  pub processor: TransformTargetProcessors,
-}
-
-#[napi(object)]
-pub struct TransformOptions {
-  pub targets: Vec<TransformTarget>,
-}
-
-// TODO: this should be generated by a macro
-
-#[napi]
-pub fn transform(filename: String, source_code: String, options: TransformOptions) -> String {
-  let processors = HashMap::from([(TransformTargetProcessors::SampleTag, SampleTagProcessor {})]);
-
-  // TODO this is a stub implementation, will be moved to wyw-in-js-transform
-  processors
-    .get(&TransformTargetProcessors::SampleTag)
-    .unwrap()
-    .transform()
+fn to_string_literal(value: &str) -> String {
+  let mut result = String::with_capacity(value.len() + 2);
+  result.push('"');
+  for ch in value.chars() {
+    match ch {
+      '"' => result.push_str("\\\""),
+      '\\' => result.push_str("\\\\"),
+      '\n' => result.push_str("\\n"),
+      '\r' => result.push_str("\\r"),
+      '\t' => result.push_str("\\t"),
+      '\u{2028}' => result.push_str("\\u2028"),
+      '\u{2029}' => result.push_str("\\u2029"),
+      ch => result.push(ch),
+    }
+  }
+  result.push('"');
+  result
 }
 
-// TODO: this is an actual impl, will stay in this crate
-
+// Replaces `sampleTag` templates with their text
+#[derive(Default)]
 struct SampleTagProcessor {}
 
 impl Processor for SampleTagProcessor {
@@ -46,7 +35,41 @@ impl Processor for SampleTagProcessor {
     "sample-tag-processor"
   }
 
-  fn transform(&self) -> String {
-    "Hello, World!".to_string()
+  fn transform(
+    &self,
+    params: &ProcessorParams,
+    diagnostics: &mut Diagnostics,
+  ) -> Option<ProcessorOutput> {
+    let quasi = &params.node.quasi;
+    if let Some(expression) = quasi.expressions.first() {
+      diagnostics.push(
+        Diagnostic::error(
+          "sample-tag/unsupported-interpolation",
+          "Interpolations are not supported by the sample tag",
+          expression.span(),
+        )
+        .with_secondary(params.node.tag.span(), "the tag is used here"),
+      );
+
+      return None;
+    }
+
+    let text = quasi
+      .quasis
+      .iter()
+      .filter_map(|quasi| quasi.value.cooked.as_ref())
+      .map(|cooked| cooked.as_str())
+      .collect::<String>();
+
+    Some(ProcessorOutput {
+      replacement: to_string_literal(&text),
+      ..Default::default()
+    })
+  }
+
+  fn static_semantics(&self) -> StaticSemantics {
+    StaticSemantics::Pure {
+      runtime_callbacks: false,
+    }
   }
 }
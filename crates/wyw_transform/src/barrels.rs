@@ -0,0 +1,1479 @@
+use crate::cache::{content_hash, BarrelManifestCache, CachedBarrelManifest};
+use crate::edits::SourceEdits;
+use crate::exports_and_imports::{
+  binding_identifiers, collect_exports_and_imports, reference_symbol,
+};
+use crate::parse_error;
+use crate::static_values::{quote, ModuleLoader};
+use oxc::allocator::Allocator;
+use oxc::ast::ast::{
+  Declaration, ExportAllDeclaration, ExportNamedDeclaration, ImportDeclaration,
+  ImportDeclarationSpecifier, ModuleExportName, Program, Statement,
+};
+use oxc::parser::Parser;
+use oxc::span::{GetSpan, SourceType, Span};
+use oxc_semantic::{SemanticBuilder, SymbolId, SymbolTable};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use wyw_processor::error::WywResult;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BarrelBinding {
+  Named { imported: String, source: String },
+  Namespace { source: String },
+}
+
+impl BarrelBinding {
+  pub fn source(&self) -> &str {
+    match self {
+      Self::Named { source, .. } | Self::Namespace { source } => source,
+    }
+  }
+}
+
+// Re-exports of a barrel before their sources are resolved
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawBarrelManifest {
+  // `false` if some of the exports are not re-exports
+  pub complete: bool,
+  pub explicit_exports: Vec<String>,
+  // Sources of `export * from …`
+  pub export_all: Vec<String>,
+  pub reexports: Vec<(String, BarrelBinding)>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BarrelSkipReason {
+  // Packages are not analyzed
+  External,
+  // The module has code besides imports and re-exports
+  Impure,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BarrelBlockedReason {
+  // Several `export *` provide different bindings with the same name
+  Ambiguous,
+  Cycle,
+  // A namespace of a barrel would pull in all of its sources anyway
+  NamespaceBarrel,
+  // The export may come from a module that cannot be analyzed
+  UnknownStar,
+  Unresolved,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BarrelExport {
+  Resolved(BarrelBinding),
+  Blocked(BarrelBlockedReason),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BarrelManifest {
+  pub complete: bool,
+  // Exports in the order of definition, sources are resolved filenames
+  pub exports: Vec<(String, BarrelExport)>,
+}
+
+impl BarrelManifest {
+  pub fn get(&self, exported: &str) -> Option<&BarrelExport> {
+    self
+      .exports
+      .iter()
+      .find(|(name, _)| name == exported)
+      .map(|(_, export)| export)
+  }
+
+  fn set(&mut self, exported: String, export: BarrelExport) {
+    match self.exports.iter_mut().find(|(name, _)| *name == exported) {
+      Some(existing) => existing.1 = export,
+      None => self.exports.push((exported, export)),
+    }
+  }
+
+  // A binding from `export *` doesn't override explicit exports and conflicts with other stars
+  fn add_star_binding(
+    &mut self,
+    exported: String,
+    binding: BarrelBinding,
+    explicit: &HashSet<String>,
+  ) {
+    if explicit.contains(&exported) {
+      return;
+    }
+
+    match self.get(&exported) {
+      None => self.set(exported, BarrelExport::Resolved(binding)),
+      Some(BarrelExport::Resolved(existing)) if *existing != binding => self.set(
+        exported,
+        BarrelExport::Blocked(BarrelBlockedReason::Ambiguous),
+      ),
+      Some(_) => {}
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BarrelManifestEntry {
+  Barrel(BarrelManifest),
+  Ineligible(BarrelSkipReason),
+}
+
+// A module that was loaded while a manifest was built
+#[derive(Clone, Debug, PartialEq)]
+pub struct BarrelDependency {
+  pub importer: String,
+  pub source: String,
+  // The resolved filename and the hash of the content, `None` if the module was not loaded
+  pub loaded: Option<(String, u64)>,
+}
+
+// What a manifest depends on besides the content of the barrel
+#[derive(Default)]
+struct BuildState {
+  dependencies: Vec<BarrelDependency>,
+  // Barrels of the current stack that were skipped as cycles. The result depends on
+  // the order of the calls then, so such manifests are not cached.
+  cycles: HashSet<String>,
+}
+
+fn add_unique(items: &mut Vec<String>, item: String) {
+  if !items.contains(&item) {
+    items.push(item);
+  }
+}
+
+fn is_valid_identifier(name: &str) -> bool {
+  let mut chars = name.chars();
+  chars
+    .next()
+    .is_some_and(|ch| ch == '$' || ch == '_' || ch.is_ascii_alphabetic())
+    && chars.all(|ch| ch == '$' || ch == '_' || ch.is_ascii_alphanumeric())
+}
+
+fn imported_name_code(name: &str) -> String {
+  if name == "default" || is_valid_identifier(name) {
+    name.to_string()
+  } else {
+    quote(name)
+  }
+}
+
+fn exported_name_code(name: &str) -> String {
+  if is_valid_identifier(name) {
+    name.to_string()
+  } else {
+    quote(name)
+  }
+}
+
+fn is_external(filename: &str) -> bool {
+  filename.contains("/node_modules/") || filename.contains("\\node_modules\\")
+}
+
+fn parse<'a>(
+  allocator: &'a Allocator,
+  filename: &str,
+  source_text: &'a str,
+) -> WywResult<(Program<'a>, SymbolTable)> {
+  let source_type = SourceType::from_path(filename)
+    .unwrap_or_default()
+    .with_module(true);
+  let ret = Parser::new(allocator, source_text, source_type).parse();
+  if let Some(error) = ret.errors.first() {
+    return Err(parse_error(filename, error));
+  }
+
+  let program = ret.program;
+  let (symbols, _) = SemanticBuilder::new()
+    .build(&program)
+    .semantic
+    .into_symbol_table_and_scope_tree();
+
+  Ok((program, symbols))
+}
+
+// `None` for enums and namespaces, which have runtime code
+fn declaration_names(declaration: &Declaration) -> Option<Vec<String>> {
+  let names = match declaration {
+    Declaration::VariableDeclaration(declaration) => {
+      let mut idents = vec![];
+      for declarator in &declaration.declarations {
+        binding_identifiers(&declarator.id, &mut idents);
+      }
+      idents.iter().map(|ident| ident.name.to_string()).collect()
+    }
+    Declaration::FunctionDeclaration(function) => {
+      function.id.iter().map(|id| id.name.to_string()).collect()
+    }
+    Declaration::ClassDeclaration(class) => class.id.iter().map(|id| id.name.to_string()).collect(),
+    Declaration::TSEnumDeclaration(_) | Declaration::TSModuleDeclaration(_) => return None,
+    _ => vec![],
+  };
+
+  Some(names)
+}
+
+// The same as `analyzeOxcBarrelFile`, `None` if the module is not a barrel
+fn analyze_program(program: &Program, symbols: &SymbolTable) -> Option<RawBarrelManifest> {
+  let mut manifest = RawBarrelManifest {
+    complete: true,
+    explicit_exports: vec![],
+    export_all: vec![],
+    reexports: vec![],
+  };
+  let mut imports = HashMap::<SymbolId, BarrelBinding>::new();
+  // Imported bindings that are exported with `export { … }`
+  let mut passthrough = Vec::<(SymbolId, Vec<String>)>::new();
+
+  for statement in &program.body {
+    match statement {
+      Statement::ImportDeclaration(import) => {
+        if import.import_kind.is_type() {
+          continue;
+        }
+
+        // Side-effect imports make the barrel impure
+        let specifiers = import
+          .specifiers
+          .as_ref()
+          .filter(|items| !items.is_empty())?;
+        let source = import.source.value.to_string();
+        for specifier in specifiers {
+          let (local, binding) = match specifier {
+            ImportDeclarationSpecifier::ImportSpecifier(specifier) => {
+              if specifier.import_kind.is_type() {
+                continue;
+              }
+
+              let imported = specifier.imported.name().to_string();
+              (
+                &specifier.local,
+                BarrelBinding::Named {
+                  imported,
+                  source: source.clone(),
+                },
+              )
+            }
+            ImportDeclarationSpecifier::ImportDefaultSpecifier(specifier) => (
+              &specifier.local,
+              BarrelBinding::Named {
+                imported: "default".to_string(),
+                source: source.clone(),
+              },
+            ),
+            ImportDeclarationSpecifier::ImportNamespaceSpecifier(specifier) => (
+              &specifier.local,
+              BarrelBinding::Namespace {
+                source: source.clone(),
+              },
+            ),
+          };
+
+          if let Some(symbol_id) = local.symbol_id.get() {
+            imports.insert(symbol_id, binding);
+          }
+        }
+      }
+      Statement::ExportNamedDeclaration(export) => {
+        if export.export_kind.is_type() {
+          continue;
+        }
+
+        if let Some(source) = &export.source {
+          if export.specifiers.is_empty() {
+            return None;
+          }
+
+          for specifier in export
+            .specifiers
+            .iter()
+            .filter(|s| !s.export_kind.is_type())
+          {
+            let exported = specifier.exported.name().to_string();
+            add_unique(&mut manifest.explicit_exports, exported.clone());
+            manifest.reexports.push((
+              exported,
+              BarrelBinding::Named {
+                imported: specifier.local.name().to_string(),
+                source: source.value.to_string(),
+              },
+            ));
+          }
+          continue;
+        }
+
+        if let Some(declaration) = &export.declaration {
+          let names = declaration_names(declaration)?;
+          manifest.complete &= names.is_empty();
+          for name in names {
+            add_unique(&mut manifest.explicit_exports, name);
+          }
+          continue;
+        }
+
+        manifest.complete &= !export.specifiers.is_empty();
+        for specifier in export
+          .specifiers
+          .iter()
+          .filter(|s| !s.export_kind.is_type())
+        {
+          let exported = specifier.exported.name().to_string();
+          add_unique(&mut manifest.explicit_exports, exported.clone());
+
+          let symbol_id = match &specifier.local {
+            ModuleExportName::IdentifierReference(local) => reference_symbol(local, symbols),
+            _ => None,
+          };
+          match symbol_id.filter(|id| imports.contains_key(id)) {
+            Some(symbol_id) => match passthrough.iter_mut().find(|(id, _)| *id == symbol_id) {
+              Some((_, names)) => names.push(exported),
+              None => passthrough.push((symbol_id, vec![exported])),
+            },
+            None => manifest.complete = false,
+          }
+        }
+      }
+      Statement::ExportAllDeclaration(export) => {
+        if export.export_kind.is_type() {
+          continue;
+        }
+
+        let source = export.source.value.to_string();
+        match &export.exported {
+          Some(exported) => {
+            let exported = exported.name().to_string();
+            add_unique(&mut manifest.explicit_exports, exported.clone());
+            manifest
+              .reexports
+              .push((exported, BarrelBinding::Namespace { source }));
+          }
+          None => manifest.export_all.push(source),
+        }
+      }
+      Statement::EmptyStatement(_)
+      | Statement::TSInterfaceDeclaration(_)
+      | Statement::TSTypeAliasDeclaration(_) => {}
+      Statement::FunctionDeclaration(function) if function.declare => {}
+      _ => return None,
+    }
+  }
+
+  // An imported binding is a pure re-export only if it's not used anywhere else
+  for (symbol_id, exported_names) in passthrough {
+    if symbols.get_resolved_reference_ids(symbol_id).len() != exported_names.len() {
+      manifest.complete = false;
+      continue;
+    }
+
+    for exported in exported_names {
+      manifest
+        .reexports
+        .push((exported, imports[&symbol_id].clone()));
+    }
+  }
+
+  if manifest.reexports.is_empty() && manifest.export_all.is_empty() {
+    return None;
+  }
+
+  Some(manifest)
+}
+
+pub fn analyze_barrel_file(
+  filename: &str,
+  source_text: &str,
+) -> WywResult<Option<RawBarrelManifest>> {
+  let allocator = Allocator::default();
+  let (program, symbols) = parse(&allocator, filename, source_text)?;
+  Ok(analyze_program(&program, &symbols))
+}
+
+#[derive(Debug, Default)]
+pub struct BarrelRewriteOutput {
+  pub code: String,
+  // The same as `collectOptimizedImports`: used names by the source, including the generated ones
+  pub imports: Vec<(String, Vec<String>)>,
+  // Sources that are not imported anymore
+  pub fully_rewritten_sources: Vec<String>,
+  // Sources that are still imported for some of the specifiers
+  pub partial_fallback_sources: Vec<String>,
+  // Resolved filenames that are imported directly now
+  pub generated_sources: Vec<String>,
+  pub optimized_count: usize,
+  // Sources with specifiers that could not be rewritten and the reasons
+  pub skipped: Vec<(String, BarrelBlockedReason)>,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum RewriteMode {
+  Full,
+  Partial,
+}
+
+struct StatementRewrite {
+  mode: RewriteMode,
+  statements: Vec<String>,
+  generated_sources: Vec<String>,
+}
+
+enum RewrittenSpecifier {
+  Default { local: String },
+  Named { imported: String, local: String },
+  NamespaceExport { exported: String },
+  NamedExport { imported: String, exported: String },
+}
+
+impl RewrittenSpecifier {
+  fn code(&self) -> String {
+    match self {
+      Self::Default { local } => local.clone(),
+      Self::Named { imported, local } => {
+        let imported = imported_name_code(imported);
+        if imported == *local {
+          imported
+        } else {
+          format!("{imported} as {local}")
+        }
+      }
+      Self::NamespaceExport { exported } => format!("* as {exported}"),
+      Self::NamedExport { imported, exported } => {
+        let imported = imported_name_code(imported);
+        let exported = exported_name_code(exported);
+        if imported == exported {
+          imported
+        } else {
+          format!("{imported} as {exported}")
+        }
+      }
+    }
+  }
+}
+
+// Specifiers grouped by the source in the order of the first usage
+fn group_by_source(
+  specifiers: Vec<(String, RewrittenSpecifier)>,
+) -> Vec<(String, Vec<RewrittenSpecifier>)> {
+  let mut groups = Vec::<(String, Vec<RewrittenSpecifier>)>::new();
+  for (source, specifier) in specifiers {
+    match groups.iter_mut().find(|(existing, _)| *existing == source) {
+      Some((_, group)) => group.push(specifier),
+      None => groups.push((source, vec![specifier])),
+    }
+  }
+
+  groups
+}
+
+fn import_statements(specifiers: Vec<(String, RewrittenSpecifier)>) -> Vec<String> {
+  let mut statements = vec![];
+  for (source, group) in group_by_source(specifiers) {
+    let (defaults, named): (Vec<_>, Vec<_>) = group
+      .iter()
+      .partition(|specifier| matches!(specifier, RewrittenSpecifier::Default { .. }));
+    let named = named
+      .iter()
+      .map(|specifier| specifier.code())
+      .collect::<Vec<_>>();
+    let source = quote(&source);
+
+    let Some((first, rest)) = defaults.split_first() else {
+      statements.push(format!("import {{ {} }} from {source};", named.join(", ")));
+      continue;
+    };
+
+    if named.is_empty() {
+      statements.push(format!("import {} from {source};", first.code()));
+    } else {
+      statements.push(format!(
+        "import {}, {{ {} }} from {source};",
+        first.code(),
+        named.join(", ")
+      ));
+    }
+
+    for specifier in rest {
+      statements.push(format!("import {} from {source};", specifier.code()));
+    }
+  }
+
+  statements
+}
+
+fn export_statements(specifiers: Vec<(String, RewrittenSpecifier)>) -> Vec<String> {
+  let (namespaces, named): (Vec<_>, Vec<_>) = specifiers
+    .into_iter()
+    .partition(|(_, specifier)| matches!(specifier, RewrittenSpecifier::NamespaceExport { .. }));
+
+  let mut statements = namespaces
+    .into_iter()
+    .map(|(source, specifier)| format!("export {} from {};", specifier.code(), quote(&source)))
+    .collect::<Vec<_>>();
+  for (source, group) in group_by_source(named) {
+    let specifiers = group
+      .iter()
+      .map(RewrittenSpecifier::code)
+      .collect::<Vec<_>>();
+    statements.push(format!(
+      "export {{ {} }} from {};",
+      specifiers.join(", "),
+      quote(&source)
+    ));
+  }
+
+  statements
+}
+
+fn source_slice(source_text: &str, span: Span) -> &str {
+  &source_text[span.start as usize..span.end as usize]
+}
+
+// The original specifiers that could not be rewritten
+fn import_fallback(
+  source_text: &str,
+  import: &ImportDeclaration,
+  fallback: &[&ImportDeclarationSpecifier],
+) -> Option<String> {
+  if fallback.is_empty() {
+    return None;
+  }
+
+  let source = quote(&import.source.value);
+  let find = |f: fn(&ImportDeclarationSpecifier) -> bool| {
+    fallback
+      .iter()
+      .find(|specifier| f(specifier))
+      .map(|specifier| source_slice(source_text, specifier.span()))
+  };
+  let default = find(|s| matches!(s, ImportDeclarationSpecifier::ImportDefaultSpecifier(_)));
+  let namespace = find(|s| matches!(s, ImportDeclarationSpecifier::ImportNamespaceSpecifier(_)));
+  let named = fallback
+    .iter()
+    .filter(|specifier| matches!(specifier, ImportDeclarationSpecifier::ImportSpecifier(_)))
+    .map(|specifier| source_slice(source_text, specifier.span()))
+    .collect::<Vec<_>>();
+
+  let statement = match (default, namespace) {
+    (Some(default), Some(namespace)) => format!("import {default}, {namespace} from {source};"),
+    (Some(default), None) if !named.is_empty() => {
+      format!(
+        "import {default}, {{ {} }} from {source};",
+        named.join(", ")
+      )
+    }
+    (Some(default), None) => format!("import {default} from {source};"),
+    (None, Some(namespace)) => format!("import {namespace} from {source};"),
+    (None, None) => format!("import {{ {} }} from {source};", named.join(", ")),
+  };
+
+  Some(statement)
+}
+
+// The same as `collectOptimizedImports`
+fn optimized_imports(program: &Program) -> Vec<(String, Vec<String>)> {
+  let mut imports = Vec::<(String, Vec<String>)>::new();
+  let mut add = |source: &str, imported: &str| {
+    let idx = match imports.iter().position(|(existing, _)| existing == source) {
+      Some(idx) => idx,
+      None => {
+        imports.push((source.to_string(), vec![]));
+        imports.len() - 1
+      }
+    };
+    add_unique(&mut imports[idx].1, imported.to_string());
+  };
+
+  for statement in &program.body {
+    match statement {
+      Statement::ImportDeclaration(import) if !import.import_kind.is_type() => {
+        let source = import.source.value.as_str();
+        let specifiers = import.specifiers.as_deref().map_or(&[][..], |items| items);
+        if specifiers.is_empty() {
+          add(source, "side-effect");
+        }
+
+        for specifier in specifiers {
+          match specifier {
+            ImportDeclarationSpecifier::ImportSpecifier(specifier) => {
+              if !specifier.import_kind.is_type() {
+                add(source, &specifier.imported.name());
+              }
+            }
+            ImportDeclarationSpecifier::ImportDefaultSpecifier(_) => add(source, "default"),
+            ImportDeclarationSpecifier::ImportNamespaceSpecifier(_) => add(source, "*"),
+          }
+        }
+      }
+      Statement::ExportNamedDeclaration(export) if !export.export_kind.is_type() => {
+        if let Some(source) = &export.source {
+          for specifier in export
+            .specifiers
+            .iter()
+            .filter(|s| !s.export_kind.is_type())
+          {
+            add(&source.value, &specifier.local.name());
+          }
+        }
+      }
+      Statement::ExportAllDeclaration(export) if !export.export_kind.is_type() => {
+        add(&export.source.value, "*");
+      }
+      _ => {}
+    }
+  }
+
+  imports
+}
+
+// Builds manifests of barrels and points imports from them to the modules that define the bindings
+pub struct BarrelResolver<'l> {
+  loader: &'l dyn ModuleLoader,
+  cache: &'l BarrelManifestCache,
+}
+
+impl<'l> BarrelResolver<'l> {
+  pub fn new(loader: &'l dyn ModuleLoader, cache: &'l BarrelManifestCache) -> Self {
+    Self { loader, cache }
+  }
+
+  // Unresolved modules are treated the same as unresolved exports
+  fn load(&self, importer: &str, source: &str, state: &mut BuildState) -> Option<(String, String)> {
+    let loaded = self.loader.load(importer, source).ok();
+    state.dependencies.push(BarrelDependency {
+      importer: importer.to_string(),
+      source: source.to_string(),
+      loaded: loaded
+        .as_ref()
+        .map(|(target, code)| (target.clone(), content_hash(code))),
+    });
+
+    loaded
+  }
+
+  // None of the modules that the cached manifest was built from has changed
+  fn is_fresh(&self, cached: &CachedBarrelManifest) -> bool {
+    cached.dependencies.iter().all(|dependency| {
+      let loaded = self
+        .loader
+        .load(&dependency.importer, &dependency.source)
+        .ok();
+      loaded.map(|(target, code)| (target, content_hash(&code))) == dependency.loaded
+    })
+  }
+
+  pub fn manifest(&self, filename: &str, source_text: &str) -> Arc<BarrelManifestEntry> {
+    self.manifest_in(
+      filename,
+      source_text,
+      &HashSet::new(),
+      &mut BuildState::default(),
+    )
+  }
+
+  // `stack` contains barrels whose manifests are being built
+  fn manifest_in(
+    &self,
+    filename: &str,
+    source_text: &str,
+    stack: &HashSet<String>,
+    state: &mut BuildState,
+  ) -> Arc<BarrelManifestEntry> {
+    if let Some(cached) = self.cache.get(filename, source_text) {
+      if self.is_fresh(&cached) {
+        state.dependencies.extend(cached.dependencies);
+        return cached.entry;
+      }
+    }
+
+    let mut own = BuildState::default();
+    let entry = Arc::new(self.build(filename, source_text, stack, &mut own));
+    // A cycle back to this barrel is the same whoever asks for the manifest
+    own.cycles.remove(filename);
+    if own.cycles.is_empty() {
+      self.cache.insert(
+        filename,
+        source_text,
+        entry.clone(),
+        own.dependencies.clone(),
+      );
+    }
+
+    state.dependencies.extend(own.dependencies);
+    state.cycles.extend(own.cycles);
+    entry
+  }
+
+  fn build(
+    &self,
+    filename: &str,
+    source_text: &str,
+    stack: &HashSet<String>,
+    state: &mut BuildState,
+  ) -> BarrelManifestEntry {
+    if is_external(filename) {
+      return BarrelManifestEntry::Ineligible(BarrelSkipReason::External);
+    }
+
+    let Ok(Some(raw)) = analyze_barrel_file(filename, source_text) else {
+      return BarrelManifestEntry::Ineligible(BarrelSkipReason::Impure);
+    };
+
+    let mut manifest = BarrelManifest {
+      complete: raw.complete,
+      exports: vec![],
+    };
+    let mut explicit = raw.explicit_exports.into_iter().collect::<HashSet<_>>();
+    let mut stack = stack.clone();
+    stack.insert(filename.to_string());
+
+    for (exported, reexport) in raw.reexports {
+      explicit.insert(exported.clone());
+      let Some((target, code)) = self.load(filename, reexport.source(), state) else {
+        manifest.complete = false;
+        manifest.set(
+          exported,
+          BarrelExport::Blocked(BarrelBlockedReason::Unresolved),
+        );
+        continue;
+      };
+
+      let export = match reexport {
+        BarrelBinding::Named { imported, .. } => {
+          self.named_binding(&target, &code, &imported, &stack, state)
+        }
+        BarrelBinding::Namespace { .. } => self.namespace_binding(&target, &code, &stack, state),
+      };
+      if matches!(export, BarrelExport::Blocked(_)) {
+        manifest.complete = false;
+      }
+      manifest.set(exported, export);
+    }
+
+    for source in raw.export_all {
+      let candidates = self
+        .load(filename, &source, state)
+        .and_then(|(target, code)| self.star_candidates(&target, &code, &stack, state));
+      let Some(candidates) = candidates else {
+        manifest.complete = false;
+        continue;
+      };
+
+      for (exported, binding) in candidates {
+        manifest.add_star_binding(exported, binding, &explicit);
+      }
+    }
+
+    BarrelManifestEntry::Barrel(manifest)
+  }
+
+  fn named_binding(
+    &self,
+    target: &str,
+    source_text: &str,
+    imported: &str,
+    stack: &HashSet<String>,
+    state: &mut BuildState,
+  ) -> BarrelExport {
+    if stack.contains(target) {
+      state.cycles.insert(target.to_string());
+      return BarrelExport::Blocked(BarrelBlockedReason::Cycle);
+    }
+
+    match self.manifest_in(target, source_text, stack, state).as_ref() {
+      BarrelManifestEntry::Barrel(manifest) => match manifest.get(imported) {
+        Some(export) => export.clone(),
+        None if manifest.complete => BarrelExport::Blocked(BarrelBlockedReason::Unresolved),
+        None => BarrelExport::Blocked(BarrelBlockedReason::UnknownStar),
+      },
+      BarrelManifestEntry::Ineligible(_) => BarrelExport::Resolved(BarrelBinding::Named {
+        imported: imported.to_string(),
+        source: target.to_string(),
+      }),
+    }
+  }
+
+  fn namespace_binding(
+    &self,
+    target: &str,
+    source_text: &str,
+    stack: &HashSet<String>,
+    state: &mut BuildState,
+  ) -> BarrelExport {
+    if stack.contains(target) {
+      state.cycles.insert(target.to_string());
+      return BarrelExport::Blocked(BarrelBlockedReason::Cycle);
+    }
+
+    match self.manifest_in(target, source_text, stack, state).as_ref() {
+      BarrelManifestEntry::Barrel(_) => BarrelExport::Blocked(BarrelBlockedReason::NamespaceBarrel),
+      BarrelManifestEntry::Ineligible(_) => BarrelExport::Resolved(BarrelBinding::Namespace {
+        source: target.to_string(),
+      }),
+    }
+  }
+
+  // Bindings provided by `export * from target`, `None` if they are not known
+  fn star_candidates(
+    &self,
+    target: &str,
+    source_text: &str,
+    stack: &HashSet<String>,
+    state: &mut BuildState,
+  ) -> Option<Vec<(String, BarrelBinding)>> {
+    if stack.contains(target) {
+      state.cycles.insert(target.to_string());
+      return None;
+    }
+
+    match self.manifest_in(target, source_text, stack, state).as_ref() {
+      BarrelManifestEntry::Barrel(manifest) if manifest.complete => Some(
+        manifest
+          .exports
+          .iter()
+          .filter_map(|(exported, export)| match export {
+            BarrelExport::Resolved(binding) if exported != "default" => {
+              Some((exported.clone(), binding.clone()))
+            }
+            _ => None,
+          })
+          .collect(),
+      ),
+      BarrelManifestEntry::Ineligible(BarrelSkipReason::Impure) => Some(
+        self
+          .file_exports(target, source_text, stack, state)
+          .into_iter()
+          .filter(|exported| exported != "default")
+          .map(|exported| {
+            let binding = BarrelBinding::Named {
+              imported: exported.clone(),
+              source: target.to_string(),
+            };
+            (exported, binding)
+          })
+          .collect(),
+      ),
+      _ => None,
+    }
+  }
+
+  // Export names of a module, including the ones from `export * from …`
+  fn file_exports(
+    &self,
+    filename: &str,
+    source_text: &str,
+    stack: &HashSet<String>,
+    state: &mut BuildState,
+  ) -> Vec<String> {
+    let Ok(collected) = collect_exports_and_imports(filename, source_text, None) else {
+      return vec![];
+    };
+
+    let mut names = collected.exports.keys().cloned().collect::<Vec<_>>();
+    let mut stack = stack.clone();
+    stack.insert(filename.to_string());
+    for reexport in &collected.reexports {
+      if reexport.exported != "*" {
+        add_unique(&mut names, reexport.exported.clone());
+        continue;
+      }
+
+      let Some((target, code)) = self.load(filename, &reexport.source, state) else {
+        continue;
+      };
+      if stack.contains(&target) {
+        state.cycles.insert(target);
+        continue;
+      }
+
+      for name in self.file_exports(&target, &code, &stack, state) {
+        if name != "default" {
+          add_unique(&mut names, name);
+        }
+      }
+    }
+
+    // Cycles back to this module don't depend on the callers
+    state.cycles.remove(filename);
+    names
+  }
+
+  fn barrel_manifest(&self, importer: &str, source: &str) -> Option<BarrelManifest> {
+    let (target, code) = self.loader.load(importer, source).ok()?;
+    match self.manifest(&target, &code).as_ref() {
+      BarrelManifestEntry::Barrel(manifest) => Some(manifest.clone()),
+      BarrelManifestEntry::Ineligible(_) => None,
+    }
+  }
+
+  fn rewrite_import(
+    &self,
+    filename: &str,
+    source_text: &str,
+    import: &ImportDeclaration,
+    skipped: &mut Vec<(String, BarrelBlockedReason)>,
+  ) -> Option<StatementRewrite> {
+    let specifiers = import
+      .specifiers
+      .as_ref()
+      .filter(|items| !items.is_empty())?;
+    if import.import_kind.is_type() {
+      return None;
+    }
+
+    let manifest = self.barrel_manifest(filename, &import.source.value)?;
+    let mut optimized = vec![];
+    let mut fallback = vec![];
+    for specifier in specifiers {
+      let (imported, local) = match specifier {
+        ImportDeclarationSpecifier::ImportNamespaceSpecifier(_) => {
+          fallback.push(specifier);
+          continue;
+        }
+        ImportDeclarationSpecifier::ImportDefaultSpecifier(specifier) => {
+          ("default".to_string(), specifier.local.name.to_string())
+        }
+        ImportDeclarationSpecifier::ImportSpecifier(specifier) => (
+          specifier.imported.name().to_string(),
+          specifier.local.name.to_string(),
+        ),
+      };
+
+      match manifest.get(&imported) {
+        Some(BarrelExport::Resolved(BarrelBinding::Named {
+          imported: binding,
+          source,
+        })) => {
+          let rewritten = match specifier {
+            ImportDeclarationSpecifier::ImportDefaultSpecifier(_) if binding == "default" => {
+              RewrittenSpecifier::Default { local }
+            }
+            ImportDeclarationSpecifier::ImportDefaultSpecifier(_) => {
+              fallback.push(specifier);
+              continue;
+            }
+            _ => RewrittenSpecifier::Named {
+              imported: binding.clone(),
+              local,
+            },
+          };
+          optimized.push((source.clone(), rewritten));
+        }
+        Some(BarrelExport::Blocked(reason)) => {
+          skipped.push((import.source.value.to_string(), *reason));
+          fallback.push(specifier);
+        }
+        _ => fallback.push(specifier),
+      }
+    }
+
+    if optimized.is_empty() {
+      return None;
+    }
+
+    let generated_sources = unique_sources(&optimized);
+    let fallback = import_fallback(source_text, import, &fallback);
+    let mode = if fallback.is_some() {
+      RewriteMode::Partial
+    } else {
+      RewriteMode::Full
+    };
+
+    Some(StatementRewrite {
+      mode,
+      statements: fallback
+        .into_iter()
+        .chain(import_statements(optimized))
+        .collect(),
+      generated_sources,
+    })
+  }
+
+  fn rewrite_export(
+    &self,
+    filename: &str,
+    source_text: &str,
+    export: &ExportNamedDeclaration,
+    skipped: &mut Vec<(String, BarrelBlockedReason)>,
+  ) -> Option<StatementRewrite> {
+    let source = export.source.as_ref()?;
+    if export.export_kind.is_type() {
+      return None;
+    }
+
+    let manifest = self.barrel_manifest(filename, &source.value)?;
+    let mut optimized = vec![];
+    let mut fallback = vec![];
+    for specifier in &export.specifiers {
+      let imported = specifier.local.name();
+      let exported = specifier.exported.name().to_string();
+      match manifest.get(&imported) {
+        Some(BarrelExport::Resolved(BarrelBinding::Named {
+          imported: binding,
+          source,
+        }))
+          if binding == "default" || is_valid_identifier(binding) =>
+        {
+          let rewritten = RewrittenSpecifier::NamedExport {
+            imported: binding.clone(),
+            exported,
+          };
+          optimized.push((source.clone(), rewritten));
+        }
+        Some(BarrelExport::Resolved(BarrelBinding::Namespace { source }))
+          if is_valid_identifier(&exported) =>
+        {
+          optimized.push((
+            source.clone(),
+            RewrittenSpecifier::NamespaceExport { exported },
+          ));
+        }
+        Some(BarrelExport::Blocked(reason)) => {
+          skipped.push((source.value.to_string(), *reason));
+          fallback.push(source_slice(source_text, specifier.span));
+        }
+        _ => fallback.push(source_slice(source_text, specifier.span)),
+      }
+    }
+
+    if optimized.is_empty() {
+      return None;
+    }
+
+    let generated_sources = unique_sources(&optimized);
+    let fallback = (!fallback.is_empty()).then(|| {
+      format!(
+        "export {{ {} }} from {};",
+        fallback.join(", "),
+        quote(&source.value)
+      )
+    });
+    let mode = if fallback.is_some() {
+      RewriteMode::Partial
+    } else {
+      RewriteMode::Full
+    };
+
+    Some(StatementRewrite {
+      mode,
+      statements: fallback
+        .into_iter()
+        .chain(export_statements(optimized))
+        .collect(),
+      generated_sources,
+    })
+  }
+
+  fn rewrite_export_all(
+    &self,
+    filename: &str,
+    export: &ExportAllDeclaration,
+    skipped: &mut Vec<(String, BarrelBlockedReason)>,
+  ) -> Option<StatementRewrite> {
+    if export.exported.is_some() || export.export_kind.is_type() {
+      return None;
+    }
+
+    let source = export.source.value.as_str();
+    let manifest = self
+      .barrel_manifest(filename, source)
+      .filter(|manifest| manifest.complete)?;
+
+    let mut optimized = vec![];
+    let mut rewritable = true;
+    for (exported, export) in &manifest.exports {
+      let BarrelExport::Resolved(binding) = export else {
+        continue;
+      };
+      if exported == "default" {
+        continue;
+      }
+
+      match binding {
+        BarrelBinding::Namespace { source: target } if is_valid_identifier(exported) => {
+          let rewritten = RewrittenSpecifier::NamespaceExport {
+            exported: exported.clone(),
+          };
+          optimized.push((target.clone(), rewritten));
+        }
+        BarrelBinding::Namespace { .. } => {
+          skipped.push((source.to_string(), BarrelBlockedReason::NamespaceBarrel));
+          rewritable = false;
+        }
+        BarrelBinding::Named {
+          imported,
+          source: target,
+        } if imported == "default" || is_valid_identifier(imported) => {
+          let rewritten = RewrittenSpecifier::NamedExport {
+            imported: imported.clone(),
+            exported: exported.clone(),
+          };
+          optimized.push((target.clone(), rewritten));
+        }
+        BarrelBinding::Named { .. } => {
+          skipped.push((source.to_string(), BarrelBlockedReason::UnknownStar));
+          rewritable = false;
+        }
+      }
+    }
+
+    if !rewritable {
+      return None;
+    }
+
+    Some(StatementRewrite {
+      mode: RewriteMode::Full,
+      generated_sources: unique_sources(&optimized),
+      statements: export_statements(optimized),
+    })
+  }
+
+  // The same as `rewriteOptimizedOxcBarrelImports`
+  pub fn rewrite_imports(
+    &self,
+    filename: &str,
+    source_text: &str,
+  ) -> WywResult<BarrelRewriteOutput> {
+    let allocator = Allocator::default();
+    let (program, _) = parse(&allocator, filename, source_text)?;
+
+    let mut output = BarrelRewriteOutput::default();
+    let mut edits = SourceEdits::new(source_text);
+    let mut modes = Vec::<(String, RewriteMode)>::new();
+    for statement in &program.body {
+      let (source, rewritten) = match statement {
+        Statement::ImportDeclaration(import) => (
+          &import.source.value,
+          self.rewrite_import(filename, source_text, import, &mut output.skipped),
+        ),
+        Statement::ExportNamedDeclaration(export) => match &export.source {
+          Some(source) => (
+            &source.value,
+            self.rewrite_export(filename, source_text, export, &mut output.skipped),
+          ),
+          None => continue,
+        },
+        Statement::ExportAllDeclaration(export) => (
+          &export.source.value,
+          self.rewrite_export_all(filename, export, &mut output.skipped),
+        ),
+        _ => continue,
+      };
+      let Some(rewritten) = rewritten else {
+        continue;
+      };
+
+      let code = rewritten.statements.join("\n");
+      if code != source_slice(source_text, statement.span()) {
+        output.optimized_count += 1;
+        edits.overwrite(statement.span(), code);
+      }
+
+      match modes
+        .iter_mut()
+        .find(|(existing, _)| existing == source.as_str())
+      {
+        Some((_, mode)) if rewritten.mode == RewriteMode::Partial => *mode = RewriteMode::Partial,
+        Some(_) => {}
+        None => modes.push((source.to_string(), rewritten.mode)),
+      }
+      for generated in rewritten.generated_sources {
+        add_unique(&mut output.generated_sources, generated);
+      }
+    }
+
+    for (source, mode) in modes {
+      match mode {
+        RewriteMode::Full => output.fully_rewritten_sources.push(source),
+        RewriteMode::Partial => output.partial_fallback_sources.push(source),
+      }
+    }
+
+    output.code = edits.to_code();
+    let allocator = Allocator::default();
+    let (program, _) = parse(&allocator, filename, &output.code)?;
+    output.imports = optimized_imports(&program);
+
+    Ok(output)
+  }
+}
+
+fn unique_sources(specifiers: &[(String, RewrittenSpecifier)]) -> Vec<String> {
+  let mut sources = vec![];
+  for (source, _) in specifiers {
+    add_unique(&mut sources, source.clone());
+  }
+
+  sources
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::resolver::ModuleResolver;
+  use std::path::{Component, Path, PathBuf};
+  use wyw_processor::error::WywError;
+
+  struct MemoryLoader(HashMap<&'static str, &'static str>);
+
+  impl ModuleResolver for MemoryLoader {
+    fn resolve(&self, importer: &str, source: &str) -> WywResult<String> {
+      self.load(importer, source).map(|(filename, _)| filename)
+    }
+  }
+
+  impl ModuleLoader for MemoryLoader {
+    fn load(&self, importer: &str, source: &str) -> WywResult<(String, String)> {
+      let mut path = PathBuf::new();
+      for component in Path::new(importer)
+        .parent()
+        .unwrap()
+        .join(source)
+        .components()
+      {
+        match component {
+          Component::ParentDir => {
+            path.pop();
+          }
+          Component::CurDir => {}
+          component => path.push(component),
+        }
+      }
+
+      let path = path.display().to_string();
+      [format!("{path}.ts"), format!("{path}/index.ts")]
+        .into_iter()
+        .find_map(|filename| {
+          let code = self.0.get(filename.as_str())?;
+          Some((filename, code.to_string()))
+        })
+        .ok_or_else(|| {
+          WywError::new(
+            "wyw/resolve-error",
+            format!("Cannot resolve module {source}"),
+          )
+        })
+    }
+  }
+
+  const MODULES: [(&str, &str); 6] = [
+    (
+      "/components/index.ts",
+      r#"
+      import type { Theme } from "./theme";
+      import { Link as BaseLink } from "./Link";
+      export { Button } from "./Button";
+      export * from "./icons";
+      export * as utils from "./utils";
+      export { BaseLink as Link };
+      export type { Theme };
+      "#,
+    ),
+    ("/components/Button.ts", "export const Button = () => null;"),
+    (
+      "/components/Link.ts",
+      "export default 1; export const Link = () => null;",
+    ),
+    (
+      "/components/icons/index.ts",
+      "export * from './Star'; export { Moon } from './Moon';",
+    ),
+    (
+      "/components/icons/Star.ts",
+      "export * from './Moon'; export const Star = 1; export default Star;",
+    ),
+    ("/components/icons/Moon.ts", "export const Moon = 2;"),
+  ];
+
+  fn resolver_output(source_text: &str) -> BarrelRewriteOutput {
+    let loader = MemoryLoader(MODULES.into_iter().collect());
+    let cache = BarrelManifestCache::new();
+    let resolver = BarrelResolver::new(&loader, &cache);
+    resolver.rewrite_imports("/entry.ts", source_text).unwrap()
+  }
+
+  fn analyze(source_text: &str) -> Option<RawBarrelManifest> {
+    analyze_barrel_file("/index.ts", source_text).unwrap()
+  }
+
+  #[test]
+  fn test_analyze() {
+    let manifest = analyze(MODULES[0].1).unwrap();
+    assert!(manifest.complete);
+    assert_eq!(manifest.explicit_exports, ["Button", "utils", "Link"]);
+    assert_eq!(manifest.export_all, ["./icons"]);
+    assert_eq!(
+      manifest
+        .reexports
+        .iter()
+        .map(|(exported, binding)| format!("{exported}: {binding:?}"))
+        .collect::<Vec<_>>(),
+      [
+        r#"Button: Named { imported: "Button", source: "./Button" }"#,
+        r#"utils: Namespace { source: "./utils" }"#,
+        r#"Link: Named { imported: "Link", source: "./Link" }"#,
+      ]
+    );
+
+    // The imported binding is used by something else
+    let manifest =
+      analyze("import { a } from './a'; export { a }; export const b = a; export * from './c';")
+        .unwrap();
+    assert!(!manifest.complete);
+    assert!(manifest.reexports.is_empty());
+
+    assert_eq!(analyze("import './styles'; export * from './a';"), None);
+    assert_eq!(analyze("export * from './a'; console.log(1);"), None);
+    assert_eq!(analyze("export default 1;"), None);
+  }
+
+  #[test]
+  fn test_manifest() {
+    let loader = MemoryLoader(MODULES.into_iter().collect());
+    let cache = BarrelManifestCache::new();
+    let resolver = BarrelResolver::new(&loader, &cache);
+    let entry = resolver.manifest(MODULES[0].0, MODULES[0].1);
+    let BarrelManifestEntry::Barrel(manifest) = entry.as_ref() else {
+      panic!("Expected a barrel");
+    };
+
+    assert!(!manifest.complete);
+    let named = |imported: &str, source: &str| {
+      BarrelExport::Resolved(BarrelBinding::Named {
+        imported: imported.to_string(),
+        source: source.to_string(),
+      })
+    };
+    assert_eq!(
+      manifest.exports,
+      [
+        (
+          "Button".to_string(),
+          named("Button", "/components/Button.ts")
+        ),
+        (
+          "utils".to_string(),
+          BarrelExport::Blocked(BarrelBlockedReason::Unresolved)
+        ),
+        ("Link".to_string(), named("Link", "/components/Link.ts")),
+        (
+          "Moon".to_string(),
+          named("Moon", "/components/icons/Moon.ts")
+        ),
+        (
+          "Star".to_string(),
+          named("Star", "/components/icons/Star.ts")
+        ),
+      ]
+    );
+
+    // Manifests of the nested barrels are cached as well
+    assert_eq!(cache.len(), 6);
+  }
+
+  #[test]
+  fn test_rewrite() {
+    let output = resolver_output(
+      r#"import Link, { Button, Star as S, utils, Missing } from "./components";
+export { Moon, Link as L } from "./components";
+export * from "./components/icons";
+"#,
+    );
+
+    assert_eq!(
+      output.code,
+      r#"import Link, { utils, Missing } from "./components";
+import { Button } from "/components/Button.ts";
+import { Star as S } from "/components/icons/Star.ts";
+export { Moon } from "/components/icons/Moon.ts";
+export { Link as L } from "/components/Link.ts";
+export { Moon } from "/components/icons/Moon.ts";
+export { Star } from "/components/icons/Star.ts";
+"#
+    );
+    assert_eq!(output.optimized_count, 3);
+    assert_eq!(output.fully_rewritten_sources, ["./components/icons"]);
+    assert_eq!(output.partial_fallback_sources, ["./components"]);
+    assert_eq!(
+      output.skipped,
+      [("./components".to_string(), BarrelBlockedReason::Unresolved)]
+    );
+    assert_eq!(
+      output.imports[0],
+      (
+        "./components".to_string(),
+        vec![
+          "default".to_string(),
+          "utils".to_string(),
+          "Missing".to_string()
+        ]
+      )
+    );
+  }
+
+  #[test]
+  fn test_cached_manifest_dependencies() {
+    let cache = BarrelManifestCache::new();
+    let (filename, source_text) = MODULES[3];
+    let exports = |loader: &MemoryLoader| {
+      let resolver = BarrelResolver::new(loader, &cache);
+      match resolver.manifest(filename, source_text).as_ref() {
+        BarrelManifestEntry::Barrel(manifest) => manifest
+          .exports
+          .iter()
+          .map(|(exported, _)| exported.clone())
+          .collect::<Vec<_>>(),
+        BarrelManifestEntry::Ineligible(reason) => panic!("Expected a barrel, got {reason:?}"),
+      }
+    };
+
+    let loader = MemoryLoader(MODULES.into_iter().collect());
+    assert_eq!(exports(&loader), ["Moon", "Star"]);
+
+    // `Moon.ts` is not the barrel itself, but the manifest of `icons` depends on it
+    let mut modules = MODULES.into_iter().collect::<HashMap<_, _>>();
+    modules.insert(
+      "/components/icons/Moon.ts",
+      "export const Moon = 2; export const Sun = 3;",
+    );
+    assert_eq!(exports(&MemoryLoader(modules)), ["Moon", "Star", "Sun"]);
+  }
+
+  #[test]
+  fn test_cycles() {
+    let modules = [
+      (
+        "/a/index.ts",
+        "export { y } from '../b'; export { x } from './x';",
+      ),
+      ("/a/x.ts", "export const x = 1;"),
+      (
+        "/b/index.ts",
+        "export { x } from '../a'; export { y } from './y';",
+      ),
+      ("/b/y.ts", "export const y = 2;"),
+    ];
+    let manifests = |order: [usize; 2]| {
+      let loader = MemoryLoader(modules.into_iter().collect());
+      let cache = BarrelManifestCache::new();
+      let resolver = BarrelResolver::new(&loader, &cache);
+      order.map(|idx| resolver.manifest(modules[idx].0, modules[idx].1))
+    };
+
+    // The manifest of `b` that is built in the middle of the cycle is not cached
+    let [a, b] = manifests([0, 2]);
+    let [b_first, a_second] = manifests([2, 0]);
+    assert_eq!(a, a_second);
+    assert_eq!(b, b_first);
+
+    let BarrelManifestEntry::Barrel(manifest) = b.as_ref() else {
+      panic!("Expected a barrel");
+    };
+    assert_eq!(
+      manifest.get("x"),
+      Some(&BarrelExport::Resolved(BarrelBinding::Named {
+        imported: "x".to_string(),
+        source: "/a/x.ts".to_string(),
+      }))
+    );
+  }
+}
@@ -0,0 +1,216 @@
+use crate::barrels::{BarrelDependency, BarrelManifestEntry};
+use crate::exports_and_imports::{CollectedImport, CollectedState};
+use crate::TransformOutput;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, RwLock};
+use wyw_processor::registry::ImportBinding;
+
+pub fn content_hash(source_text: &str) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  source_text.hash(&mut hasher);
+  hasher.finish()
+}
+
+// Every edit of a watched file adds an entry, so the cache starts over when it's full
+const MODULE_CACHE_CAPACITY: usize = 4096;
+
+// What is known about a module with a particular content
+#[derive(Debug, Default)]
+struct ModuleEntry {
+  // Imports that can bring processors into the module
+  imports: Option<Arc<Vec<ImportBinding>>>,
+  // Transform results by filename, the same content can be transformed differently in another file
+  outputs: HashMap<String, TransformOutput>,
+  exports_and_imports: Option<(bool, Arc<CollectedState>)>,
+  // Whether the parser found ES module syntax in the content
+  detected_es_module: Option<bool>,
+  processor_imports: Option<Arc<Vec<CollectedImport>>>,
+}
+
+// Analysis and transform results keyed by the hash of the module content.
+// It can be shared between threads and between files with the same content, but not between
+// different sets of processors. Entries are private, callers get copies that they can change.
+#[derive(Debug, Default)]
+pub struct ModuleCache {
+  entries: RwLock<HashMap<u64, ModuleEntry>>,
+}
+
+impl ModuleCache {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn read<T>(&self, source_text: &str, get: impl FnOnce(&ModuleEntry) -> Option<T>) -> Option<T> {
+    let entries = self.entries.read().unwrap();
+    get(entries.get(&content_hash(source_text))?)
+  }
+
+  fn write(&self, source_text: &str, update: impl FnOnce(&mut ModuleEntry)) {
+    let hash = content_hash(source_text);
+    let mut entries = self.entries.write().unwrap();
+    if entries.len() >= MODULE_CACHE_CAPACITY && !entries.contains_key(&hash) {
+      entries.clear();
+    }
+
+    update(entries.entry(hash).or_default());
+  }
+
+  pub fn get_imports(&self, source_text: &str) -> Option<Vec<ImportBinding>> {
+    self.read(source_text, |entry| {
+      entry
+        .imports
+        .as_ref()
+        .map(|imports| imports.as_ref().clone())
+    })
+  }
+
+  pub fn insert_imports(&self, source_text: &str, imports: Vec<ImportBinding>) {
+    self.write(source_text, |entry| entry.imports = Some(Arc::new(imports)));
+  }
+
+  pub fn get_output(&self, source_text: &str, filename: &str) -> Option<TransformOutput> {
+    self.read(source_text, |entry| entry.outputs.get(filename).cloned())
+  }
+
+  pub fn insert_output(&self, source_text: &str, filename: &str, output: TransformOutput) {
+    self.write(source_text, |entry| {
+      entry.outputs.insert(filename.to_string(), output);
+    });
+  }
+
+  pub fn get_exports_and_imports(
+    &self,
+    source_text: &str,
+    is_es_module: bool,
+  ) -> Option<CollectedState> {
+    self.read(source_text, |entry| match &entry.exports_and_imports {
+      Some((cached_is_es_module, state)) if *cached_is_es_module == is_es_module => {
+        Some(state.as_ref().clone())
+      }
+      _ => None,
+    })
+  }
+
+  pub fn insert_exports_and_imports(
+    &self,
+    source_text: &str,
+    is_es_module: bool,
+    state: CollectedState,
+  ) {
+    self.write(source_text, |entry| {
+      entry.exports_and_imports = Some((is_es_module, Arc::new(state)));
+    });
+  }
+
+  // Exports and imports of the content in the module mode that the parser detected for it
+  pub fn get_detected_exports_and_imports(&self, source_text: &str) -> Option<CollectedState> {
+    self.read(source_text, |entry| match &entry.exports_and_imports {
+      Some((is_es_module, state)) if entry.detected_es_module == Some(*is_es_module) => {
+        Some(state.as_ref().clone())
+      }
+      _ => None,
+    })
+  }
+
+  pub fn insert_detected_es_module(&self, source_text: &str, is_es_module: bool) {
+    self.write(source_text, |entry| {
+      entry.detected_es_module = Some(is_es_module);
+    });
+  }
+
+  pub fn get_processor_imports(&self, source_text: &str) -> Option<Vec<CollectedImport>> {
+    self.read(source_text, |entry| {
+      let imports = entry.processor_imports.as_ref()?;
+      Some(imports.as_ref().clone())
+    })
+  }
+
+  pub fn insert_processor_imports(&self, source_text: &str, imports: Vec<CollectedImport>) {
+    self.write(source_text, |entry| {
+      entry.processor_imports = Some(Arc::new(imports));
+    });
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.read().unwrap().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn clear(&self) {
+    self.entries.write().unwrap().clear();
+  }
+}
+
+// Barrel manifests keyed by the resolved filename and the hash of the barrel content.
+// Every entry remembers the modules that were read to build it, so `BarrelResolver`
+// can check that none of them changed before it uses the entry.
+#[derive(Debug, Default)]
+pub struct BarrelManifestCache {
+  entries: RwLock<HashMap<(String, u64), CachedBarrelManifest>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CachedBarrelManifest {
+  pub entry: Arc<BarrelManifestEntry>,
+  pub dependencies: Vec<BarrelDependency>,
+}
+
+impl BarrelManifestCache {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn get(&self, filename: &str, source_text: &str) -> Option<CachedBarrelManifest> {
+    let key = (filename.to_string(), content_hash(source_text));
+    self.entries.read().unwrap().get(&key).cloned()
+  }
+
+  pub fn insert(
+    &self,
+    filename: &str,
+    source_text: &str,
+    entry: Arc<BarrelManifestEntry>,
+    dependencies: Vec<BarrelDependency>,
+  ) {
+    let key = (filename.to_string(), content_hash(source_text));
+    let cached = CachedBarrelManifest {
+      entry,
+      dependencies,
+    };
+    self.entries.write().unwrap().insert(key, cached);
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.read().unwrap().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn clear(&self) {
+    self.entries.write().unwrap().clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_module_cache_capacity() {
+    let cache = ModuleCache::new();
+    for idx in 0..MODULE_CACHE_CAPACITY + 10 {
+      cache.insert_imports(&format!("export const a = {idx};"), vec![]);
+    }
+
+    assert!(cache.len() <= MODULE_CACHE_CAPACITY);
+    assert!(cache
+      .get_imports(&format!("export const a = {};", MODULE_CACHE_CAPACITY + 9))
+      .is_some());
+  }
+}
@@ -0,0 +1,82 @@
+use oxc::ast::ast::{Expression, IdentifierReference, TaggedTemplateExpression};
+use oxc_semantic::SymbolId;
+use std::collections::HashSet;
+use wyw_processor::registry::{DefinedTag, DefinedTags};
+use wyw_traverse::{EnterAction, TraverseCtx, TraverseHooks};
+
+pub struct CallSite<'a> {
+  pub node: &'a TaggedTemplateExpression<'a>,
+  pub tag: DefinedTag,
+  // The import binding that the tag refers to
+  pub symbol_id: SymbolId,
+}
+
+// Returns the root identifier of a tag and its qualified name, e.g. `styled.div` or `ui.css`
+fn qualified_tag<'a>(
+  expression: &'a Expression<'a>,
+) -> Option<(&'a IdentifierReference<'a>, String)> {
+  match expression {
+    Expression::Identifier(ident) => Some((ident.as_ref(), ident.name.to_string())),
+    Expression::StaticMemberExpression(member) => qualified_tag(&member.object)
+      .map(|(root, object)| (root, format!("{object}.{}", member.property.name))),
+    Expression::ComputedMemberExpression(member) => match &member.expression {
+      Expression::StringLiteral(property) => qualified_tag(&member.object)
+        .map(|(root, object)| (root, format!("{object}.{}", property.value))),
+      _ => qualified_tag(&member.object),
+    },
+    // `styled(Component)`
+    Expression::CallExpression(call) => qualified_tag(&call.callee),
+    Expression::ParenthesizedExpression(parenthesized) => qualified_tag(&parenthesized.expression),
+    _ => None,
+  }
+}
+
+pub struct CallSiteCollector<'a, 'r> {
+  tags: &'r DefinedTags<'r>,
+  import_symbols: HashSet<SymbolId>,
+  pub call_sites: Vec<CallSite<'a>>,
+}
+
+impl<'r> CallSiteCollector<'_, 'r> {
+  pub fn new(
+    tags: &'r DefinedTags<'r>,
+    import_symbols: impl IntoIterator<Item = SymbolId>,
+  ) -> Self {
+    Self {
+      tags,
+      import_symbols: import_symbols.into_iter().collect(),
+      call_sites: vec![],
+    }
+  }
+}
+
+impl<'a> TraverseHooks<'a> for CallSiteCollector<'a, '_> {
+  fn enter_tagged_template_expression(
+    &mut self,
+    node: &'a TaggedTemplateExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let Some((root, qualified_name)) = qualified_tag(&node.tag) else {
+      return EnterAction::Continue;
+    };
+
+    // The tag should refer to the imported binding, not to a local one with the same name
+    let symbol_id = root
+      .reference_id
+      .get()
+      .and_then(|reference_id| ctx.symbols().get_reference(reference_id).symbol_id());
+    let Some(symbol_id) = symbol_id.filter(|id| self.import_symbols.contains(id)) else {
+      return EnterAction::Continue;
+    };
+
+    if let Some((tag, _)) = self.tags.resolve(&qualified_name) {
+      self.call_sites.push(CallSite {
+        node,
+        tag,
+        symbol_id,
+      });
+    }
+
+    EnterAction::Continue
+  }
+}
@@ -0,0 +1,140 @@
+use crate::parse_error;
+use oxc::allocator::Allocator;
+use oxc::ast::ast::Program;
+use oxc::codegen::{CodeGenerator, CodegenOptions};
+use oxc::parser::Parser;
+use oxc::sourcemap::SourceMap;
+use oxc::span::SourceType;
+use oxc::transformer::{TransformOptions, Transformer};
+use oxc_semantic::SemanticBuilder;
+use std::path::{Path, PathBuf};
+use wyw_processor::error::{WywError, WywResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct PrintOptions {
+  // Removes whitespace. oxc drops all comments in this mode, annotations included.
+  pub minify: bool,
+  pub source_map: bool,
+}
+
+#[derive(Debug)]
+pub struct PrintOutput {
+  pub code: String,
+  pub source_map: Option<SourceMap>,
+}
+
+// Prints a (possibly mutated) program. Annotations such as `/*#__PURE__*/`,
+// JSDoc comments such as `/** @wyw */` and legal comments are kept, other comments are dropped by oxc.
+pub fn print(filename: &str, program: &Program, options: &PrintOptions) -> PrintOutput {
+  let ret = CodeGenerator::new()
+    .with_options(CodegenOptions {
+      minify: options.minify,
+      source_map_path: options.source_map.then(|| PathBuf::from(filename)),
+      ..CodegenOptions::default()
+    })
+    .build(program);
+
+  PrintOutput {
+    code: ret.code,
+    source_map: ret.map,
+  }
+}
+
+// The same as `stripTypesAndJsxWithOxc`: removes TypeScript syntax and lowers JSX
+// with the automatic runtime. Newer syntax is left as is.
+pub fn strip_types_and_jsx(
+  filename: &str,
+  source_text: &str,
+  options: &PrintOptions,
+) -> WywResult<PrintOutput> {
+  let allocator = Allocator::default();
+  let source_type = SourceType::from_path(filename).unwrap_or_default();
+  let ret = Parser::new(&allocator, source_text, source_type).parse();
+  if let Some(error) = ret.errors.first() {
+    return Err(parse_error(filename, error));
+  }
+
+  let mut program = ret.program;
+  let (symbols, scopes) = SemanticBuilder::new()
+    .build(&program)
+    .semantic
+    .into_symbol_table_and_scope_tree();
+
+  let ret = Transformer::new(
+    &allocator,
+    Path::new(filename),
+    &TransformOptions::default(),
+  )
+  .build_with_symbols_and_scopes(symbols, scopes, &mut program);
+  if let Some(error) = ret.errors.first() {
+    return Err(WywError::new("wyw/transform-error", &error.message).with_filename(filename));
+  }
+
+  Ok(print(filename, &program, options))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse_and_print(source_text: &str, options: &PrintOptions) -> PrintOutput {
+    let allocator = Allocator::default();
+    let program = Parser::new(&allocator, source_text, SourceType::mjs())
+      .parse()
+      .program;
+    print("index.js", &program, options)
+  }
+
+  #[test]
+  fn test_print() {
+    let source_text = "/** @wyw */\nconst a = /*#__PURE__*/ styled(\"div\");\n";
+    assert_eq!(
+      parse_and_print(source_text, &PrintOptions::default()).code,
+      source_text
+    );
+
+    let output = parse_and_print(
+      "const a = 1 + 2;\n",
+      &PrintOptions {
+        minify: true,
+        source_map: true,
+      },
+    );
+    assert_eq!(output.code, "const a=1+2;");
+    assert_eq!(output.source_map.unwrap().get_source(0), Some("index.js"));
+  }
+
+  #[test]
+  fn test_strip_types_and_jsx() {
+    let output = strip_types_and_jsx(
+      "source.ts",
+      "export enum Flags {\n  Dev = 1,\n}\nexport const mode: number = Flags.Dev;\n",
+      &PrintOptions {
+        source_map: true,
+        ..PrintOptions::default()
+      },
+    )
+    .unwrap();
+    assert!(output.code.contains("export let Flags"));
+    assert!(output.code.contains("export const mode = Flags.Dev"));
+    assert!(!output.code.contains(": number"));
+    assert_eq!(output.source_map.unwrap().get_source(0), Some("source.ts"));
+
+    let output = strip_types_and_jsx(
+      "source.tsx",
+      "export const view = <div data-id=\"x\" />;",
+      &PrintOptions::default(),
+    )
+    .unwrap();
+    assert!(output.code.contains("react/jsx-runtime"));
+    assert!(output.code.contains("_jsx(\"div\""));
+
+    let output = strip_types_and_jsx(
+      "source.ts",
+      "class Cache {\n  #value = 1;\n  get() {\n    return this.#value;\n  }\n}\n",
+      &PrintOptions::default(),
+    )
+    .unwrap();
+    assert!(output.code.contains("#value"));
+  }
+}
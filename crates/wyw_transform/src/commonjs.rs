@@ -0,0 +1,650 @@
+use crate::codegen::{strip_types_and_jsx, PrintOptions, PrintOutput};
+use crate::edits::{compose, SourceEdits};
+use crate::parse_error;
+use oxc::allocator::Allocator;
+use oxc::ast::ast::{
+  BindingPattern, BindingPatternKind, Declaration, ExportAllDeclaration, ExportDefaultDeclaration,
+  ExportDefaultDeclarationKind, ExportNamedDeclaration, ImportDeclaration,
+  ImportDeclarationSpecifier, Statement, VariableDeclaration,
+};
+use oxc::parser::Parser;
+use oxc::span::{GetSpan, SourceType, Span};
+use wyw_processor::error::WywResult;
+
+fn string_literal(value: &str) -> String {
+  let mut result = String::with_capacity(value.len() + 2);
+  result.push('"');
+  for ch in value.chars() {
+    match ch {
+      '"' => result.push_str("\\\""),
+      '\\' => result.push_str("\\\\"),
+      '\n' => result.push_str("\\n"),
+      '\r' => result.push_str("\\r"),
+      '\t' => result.push_str("\\t"),
+      ch if (ch as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", ch as u32)),
+      ch => result.push(ch),
+    }
+  }
+
+  result.push('"');
+  result
+}
+
+fn property_access(name: &str) -> String {
+  let mut chars = name.chars();
+  let is_identifier = chars
+    .next()
+    .is_some_and(|ch| ch == '$' || ch == '_' || ch.is_ascii_alphabetic())
+    && chars.all(|ch| ch == '$' || ch == '_' || ch.is_ascii_alphanumeric());
+
+  if is_identifier {
+    format!(".{name}")
+  } else {
+    format!("[{}]", string_literal(name))
+  }
+}
+
+fn export_assignment(exported: &str, local: &str) -> String {
+  format!("exports{} = {local};", property_access(exported))
+}
+
+fn export_getter(exported: &str, value: &str) -> String {
+  [
+    format!(
+      "Object.defineProperty(exports, {}, {{",
+      string_literal(exported)
+    ),
+    "  enumerable: true,".to_string(),
+    "  get: function () {".to_string(),
+    format!("    return {value};"),
+    "  }".to_string(),
+    "});".to_string(),
+  ]
+  .join("\n")
+}
+
+fn default_interop(value: &str) -> String {
+  format!(
+    "{value} && {value}.__esModule ? {value}.default : (\"default\" in Object({value}) ? {value}.default : {value})"
+  )
+}
+
+fn binding_names(pattern: &BindingPattern, names: &mut Vec<String>) {
+  match &pattern.kind {
+    BindingPatternKind::BindingIdentifier(identifier) => names.push(identifier.name.to_string()),
+    BindingPatternKind::AssignmentPattern(assignment) => binding_names(&assignment.left, names),
+    BindingPatternKind::ObjectPattern(object) => {
+      for property in &object.properties {
+        binding_names(&property.value, names);
+      }
+
+      if let Some(rest) = &object.rest {
+        binding_names(&rest.argument, names);
+      }
+    }
+    BindingPatternKind::ArrayPattern(array) => {
+      for element in array.elements.iter().flatten() {
+        binding_names(element, names);
+      }
+
+      if let Some(rest) = &array.rest {
+        binding_names(&rest.argument, names);
+      }
+    }
+  }
+}
+
+fn variable_bindings(declaration: &VariableDeclaration) -> Vec<String> {
+  let mut names = vec![];
+  for declarator in &declaration.declarations {
+    binding_names(&declarator.id, &mut names);
+  }
+
+  names
+}
+
+fn declaration_bindings(declaration: &Declaration) -> Vec<String> {
+  match declaration {
+    Declaration::VariableDeclaration(declaration) => variable_bindings(declaration),
+    Declaration::FunctionDeclaration(function) => {
+      function.id.iter().map(|id| id.name.to_string()).collect()
+    }
+    Declaration::ClassDeclaration(class) => class.id.iter().map(|id| id.name.to_string()).collect(),
+    _ => vec![],
+  }
+}
+
+fn emit_import(declaration: &ImportDeclaration, index: usize) -> String {
+  let source = string_literal(&declaration.source.value);
+  let specifiers = declaration
+    .specifiers
+    .as_deref()
+    .map_or(&[][..], |s| &s[..]);
+  if specifiers.is_empty() {
+    return format!("require({source});");
+  }
+
+  let temp = format!("__wyw_import_{index}");
+  let mut lines = vec![format!("const {temp} = require({source});")];
+  for specifier in specifiers {
+    let value = match specifier {
+      ImportDeclarationSpecifier::ImportNamespaceSpecifier(_) => temp.clone(),
+      ImportDeclarationSpecifier::ImportDefaultSpecifier(_) => default_interop(&temp),
+      ImportDeclarationSpecifier::ImportSpecifier(specifier) => match specifier.imported.name() {
+        name if name == "default" => default_interop(&temp),
+        name => format!("{temp}{}", property_access(&name)),
+      },
+    };
+
+    lines.push(format!("const {} = {value};", specifier.local().name));
+  }
+
+  lines.join("\n")
+}
+
+// `export const a = 1, b = 2` becomes `const a = exports.a = 1;\nconst b = exports.b = 2;`.
+// `let` and `var` bindings can be reassigned later, so they are exported through getters.
+fn emit_variable_export(code: &str, declaration: &VariableDeclaration) -> String {
+  let kind = declaration.kind.as_str();
+  let all_simple = declaration
+    .declarations
+    .iter()
+    .all(|declarator| declarator.id.kind.is_binding_identifier());
+
+  if !declaration.kind.is_const() {
+    let mut lines = vec![declaration.span.source_text(code).to_string()];
+    lines.extend(
+      variable_bindings(declaration)
+        .iter()
+        .map(|binding| export_getter(binding, binding)),
+    );
+    return lines.join("\n");
+  }
+
+  if !all_simple {
+    let mut lines = vec![declaration.span.source_text(code).to_string()];
+    lines.extend(
+      variable_bindings(declaration)
+        .iter()
+        .map(|binding| export_assignment(binding, binding)),
+    );
+    return lines.join("\n");
+  }
+
+  declaration
+    .declarations
+    .iter()
+    .map(|declarator| {
+      let binding = declarator
+        .id
+        .get_identifier()
+        .map_or("", |name| name.as_str());
+      let init = declarator
+        .init
+        .as_ref()
+        .map_or("undefined", |init| init.span().source_text(code));
+      format!(
+        "{kind} {binding} = exports{} = {init};",
+        property_access(binding)
+      )
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+fn emit_named_export(code: &str, declaration: &ExportNamedDeclaration, index: usize) -> String {
+  if let Some(source) = &declaration.source {
+    let temp = format!("__wyw_reexport_{index}");
+    let mut lines = vec![format!(
+      "const {temp} = require({});",
+      string_literal(&source.value)
+    )];
+    for specifier in &declaration.specifiers {
+      let local = specifier.local.name();
+      let value = if local == "default" {
+        default_interop(&temp)
+      } else {
+        format!("{temp}{}", property_access(&local))
+      };
+
+      lines.push(export_getter(&specifier.exported.name(), &value));
+    }
+
+    return lines.join("\n");
+  }
+
+  if let Some(inner) = &declaration.declaration {
+    if let Declaration::VariableDeclaration(variable) = inner {
+      return emit_variable_export(code, variable);
+    }
+
+    // Function exports are assigned in the preamble, see `hoisted_exports`
+    if let Declaration::FunctionDeclaration(_) = inner {
+      return inner.span().source_text(code).to_string();
+    }
+
+    let mut lines = vec![inner.span().source_text(code).to_string()];
+    lines.extend(
+      declaration_bindings(inner)
+        .iter()
+        .map(|binding| export_assignment(binding, binding)),
+    );
+    return lines.join("\n");
+  }
+
+  // Getters keep the bindings live and allow exporting of bindings declared later
+  declaration
+    .specifiers
+    .iter()
+    .map(|specifier| export_getter(&specifier.exported.name(), &specifier.local.name()))
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+fn emit_default_export(code: &str, declaration: &ExportDefaultDeclaration) -> String {
+  let inner = &declaration.declaration;
+  let inner_code = inner.span().source_text(code);
+  let name = match inner {
+    ExportDefaultDeclarationKind::FunctionDeclaration(function) => function.id.as_ref(),
+    ExportDefaultDeclarationKind::ClassDeclaration(class) => class.id.as_ref(),
+    _ => return format!("exports.default = {inner_code};"),
+  };
+
+  match name {
+    Some(_) if matches!(inner, ExportDefaultDeclarationKind::FunctionDeclaration(_)) => {
+      inner_code.to_string()
+    }
+    Some(name) => format!("{inner_code}\nexports.default = {};", name.name),
+    None => format!("const _default = {inner_code};\nexports.default = _default;"),
+  }
+}
+
+fn emit_export_all(declaration: &ExportAllDeclaration, index: usize) -> String {
+  let source = string_literal(&declaration.source.value);
+  if let Some(exported) = &declaration.exported {
+    return format!(
+      "exports{} = require({source});",
+      property_access(&exported.name())
+    );
+  }
+
+  let temp = format!("__wyw_reexport_all_{index}");
+  [
+    format!("const {temp} = require({source});"),
+    format!("Object.keys({temp}).forEach((key) => {{"),
+    format!("  if (key !== \"default\" && key !== \"__esModule\") exports[key] = {temp}[key];"),
+    "});".to_string(),
+  ]
+  .join("\n")
+}
+
+fn export_names(statement: &Statement) -> Vec<String> {
+  match statement {
+    Statement::ExportNamedDeclaration(declaration) => match &declaration.declaration {
+      Some(inner) => declaration_bindings(inner),
+      None => declaration
+        .specifiers
+        .iter()
+        .map(|specifier| specifier.exported.name().to_string())
+        .collect(),
+    },
+    Statement::ExportDefaultDeclaration(_) => vec!["default".to_string()],
+    _ => vec![],
+  }
+}
+
+// Exported function declarations as (exported, local) pairs. Functions are hoisted,
+// so the exports are assigned before any other code runs, like the declarations themselves.
+fn hoisted_exports(statement: &Statement) -> Vec<(String, String)> {
+  match statement {
+    Statement::ExportNamedDeclaration(declaration) => match &declaration.declaration {
+      Some(Declaration::FunctionDeclaration(function)) => function
+        .id
+        .iter()
+        .map(|id| (id.name.to_string(), id.name.to_string()))
+        .collect(),
+      _ => vec![],
+    },
+    Statement::ExportDefaultDeclaration(declaration) => match &declaration.declaration {
+      ExportDefaultDeclarationKind::FunctionDeclaration(function) => function
+        .id
+        .iter()
+        .map(|id| ("default".to_string(), id.name.to_string()))
+        .collect(),
+      _ => vec![],
+    },
+    _ => vec![],
+  }
+}
+
+fn strip_leading_blank_lines(code: &str) -> &str {
+  let mut rest = code;
+  while let Some((line, next)) = rest.split_once('\n') {
+    if !line.trim_matches([' ', '\t']).is_empty() {
+      break;
+    }
+
+    rest = next;
+  }
+
+  rest
+}
+
+// The same as `emitOxcCommonJS`: strips types and JSX if the code is not plain JS
+// and turns ES module syntax into CommonJS. The source map points to `source_text`.
+pub fn emit_common_js(filename: &str, source_text: &str) -> WywResult<PrintOutput> {
+  let stripped;
+  let mut input_map = None;
+  let allocator = Allocator::default();
+  let ret = Parser::new(&allocator, source_text, SourceType::mjs()).parse();
+  let (code, program) = if ret.errors.is_empty() {
+    (source_text, ret.program)
+  } else {
+    let options = PrintOptions {
+      source_map: true,
+      ..PrintOptions::default()
+    };
+    let output = strip_types_and_jsx(filename, source_text, &options)?;
+    stripped = output.code;
+    input_map = output.source_map;
+    let ret = Parser::new(&allocator, &stripped, SourceType::mjs()).parse();
+    if let Some(error) = ret.errors.first() {
+      return Err(parse_error(filename, error));
+    }
+
+    (stripped.as_str(), ret.program)
+  };
+
+  let mut edits = SourceEdits::new(code);
+  let mut is_es_module = false;
+  let mut predeclared = Vec::<String>::new();
+  let mut hoisted = Vec::<(String, String)>::new();
+  for (index, statement) in program.body.iter().enumerate() {
+    let value = match statement {
+      Statement::ImportDeclaration(declaration) => emit_import(declaration, index),
+      Statement::ExportNamedDeclaration(declaration) => emit_named_export(code, declaration, index),
+      Statement::ExportDefaultDeclaration(declaration) => emit_default_export(code, declaration),
+      Statement::ExportAllDeclaration(declaration) => emit_export_all(declaration, index),
+      // Splits declarations so that each binding can be exported separately
+      Statement::VariableDeclaration(declaration) if declaration.declarations.len() > 1 => {
+        declaration
+          .declarations
+          .iter()
+          .map(|declarator| {
+            format!(
+              "{} {};",
+              declaration.kind.as_str(),
+              declarator.span.source_text(code)
+            )
+          })
+          .collect::<Vec<_>>()
+          .join("\n")
+      }
+      _ => continue,
+    };
+
+    if !matches!(
+      statement,
+      Statement::ImportDeclaration(_) | Statement::VariableDeclaration(_)
+    ) {
+      is_es_module = true;
+    }
+
+    let statement_hoisted = hoisted_exports(statement);
+    for name in export_names(statement) {
+      let is_hoisted = statement_hoisted
+        .iter()
+        .any(|(exported, _)| exported == &name);
+      if !is_hoisted && !predeclared.contains(&name) {
+        predeclared.push(name);
+      }
+    }
+
+    hoisted.extend(statement_hoisted);
+
+    edits.overwrite(statement.span(), value);
+  }
+
+  let mut preamble = String::from("\"use strict\";\n");
+  if is_es_module {
+    preamble.push_str("\nObject.defineProperty(exports, \"__esModule\", {\n  value: true\n});\n");
+    for name in &predeclared {
+      preamble.push_str(&format!("exports{} = void 0;\n", property_access(name)));
+    }
+
+    for (exported, local) in &hoisted {
+      preamble.push_str(&format!("{}\n", export_assignment(exported, local)));
+    }
+  }
+
+  edits.insert(0, preamble);
+  let blank_lines = code.len() - strip_leading_blank_lines(code).len();
+  if blank_lines > 0 {
+    edits.remove(Span::new(0, blank_lines as u32));
+  }
+
+  let (code, source_map) = edits.to_code_and_map(filename);
+  let source_map = match input_map {
+    Some(input_map) => compose(&source_map, &input_map),
+    None => source_map,
+  };
+
+  Ok(PrintOutput {
+    code,
+    source_map: Some(source_map),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn emit(source_text: &str) -> String {
+    emit_common_js("source.ts", source_text).unwrap().code
+  }
+
+  #[test]
+  fn test_typescript_exports() {
+    let code = emit("export enum Flags {\n  Dev = 1,\n}\nexport const mode: number = Flags.Dev;\n");
+    assert_eq!(
+      code,
+      r#""use strict";
+
+Object.defineProperty(exports, "__esModule", {
+  value: true
+});
+exports.Flags = void 0;
+exports.mode = void 0;
+let Flags = function(Flags) {
+	Flags[Flags["Dev"] = 1] = "Dev";
+	return Flags;
+}({});
+Object.defineProperty(exports, "Flags", {
+  enumerable: true,
+  get: function () {
+    return Flags;
+  }
+});
+const mode = exports.mode = Flags.Dev;
+"#
+    );
+  }
+
+  #[test]
+  fn test_imports() {
+    let code = emit(
+      r#"import defaultValue, { named as alias } from 'dep';
+import * as ns from 'ns';
+import 'side';
+
+export const value = defaultValue + alias + ns.extra;
+export { alias as renamed };
+export default value;
+"#,
+    );
+
+    assert_eq!(
+      code,
+      r#""use strict";
+
+Object.defineProperty(exports, "__esModule", {
+  value: true
+});
+exports.value = void 0;
+exports.renamed = void 0;
+exports.default = void 0;
+const __wyw_import_0 = require("dep");
+const defaultValue = __wyw_import_0 && __wyw_import_0.__esModule ? __wyw_import_0.default : ("default" in Object(__wyw_import_0) ? __wyw_import_0.default : __wyw_import_0);
+const alias = __wyw_import_0.named;
+const __wyw_import_1 = require("ns");
+const ns = __wyw_import_1;
+require("side");
+
+const value = exports.value = defaultValue + alias + ns.extra;
+Object.defineProperty(exports, "renamed", {
+  enumerable: true,
+  get: function () {
+    return alias;
+  }
+});
+exports.default = value;
+"#
+    );
+  }
+
+  #[test]
+  fn test_declaration_exports() {
+    let code = emit(
+      "export function spring() {\n  return 'spring';\n}\n\nexport class Task {\n  static nameOf() {\n    return spring();\n  }\n}\n",
+    );
+    assert_eq!(
+      code,
+      r#""use strict";
+
+Object.defineProperty(exports, "__esModule", {
+  value: true
+});
+exports.Task = void 0;
+exports.spring = spring;
+function spring() {
+  return 'spring';
+}
+
+class Task {
+  static nameOf() {
+    return spring();
+  }
+}
+exports.Task = Task;
+"#
+    );
+  }
+
+  #[test]
+  fn test_hoisted_default_function() {
+    let code =
+      emit("export const value = run();\nexport default function run() {\n  return 1;\n}\n");
+    assert_eq!(
+      code,
+      r#""use strict";
+
+Object.defineProperty(exports, "__esModule", {
+  value: true
+});
+exports.value = void 0;
+exports.default = run;
+const value = exports.value = run();
+function run() {
+  return 1;
+}
+"#
+    );
+  }
+
+  #[test]
+  fn test_reassigned_exports() {
+    let code = emit("export let a = 1;\na = 2;\n");
+    assert_eq!(
+      code,
+      r#""use strict";
+
+Object.defineProperty(exports, "__esModule", {
+  value: true
+});
+exports.a = void 0;
+let a = 1;
+Object.defineProperty(exports, "a", {
+  enumerable: true,
+  get: function () {
+    return a;
+  }
+});
+a = 2;
+"#
+    );
+  }
+
+  #[test]
+  fn test_local_specifiers() {
+    let code = emit("let theme = 1;\nexport { theme };\ntheme = 2;\n");
+    assert_eq!(
+      code,
+      r#""use strict";
+
+Object.defineProperty(exports, "__esModule", {
+  value: true
+});
+exports.theme = void 0;
+let theme = 1;
+Object.defineProperty(exports, "theme", {
+  enumerable: true,
+  get: function () {
+    return theme;
+  }
+});
+theme = 2;
+"#
+    );
+  }
+
+  #[test]
+  fn test_later_local_specifiers() {
+    let code = emit("export { theme };\nconst theme = 1;\n");
+    assert_eq!(
+      code,
+      r#""use strict";
+
+Object.defineProperty(exports, "__esModule", {
+  value: true
+});
+exports.theme = void 0;
+Object.defineProperty(exports, "theme", {
+  enumerable: true,
+  get: function () {
+    return theme;
+  }
+});
+const theme = 1;
+"#
+    );
+  }
+
+  #[test]
+  fn test_source_map() {
+    let source_text =
+      "export enum Flags {\n  Dev = 1,\n}\nexport const mode: number = Flags.Dev;\n";
+    let output = emit_common_js("source.ts", source_text).unwrap();
+    let map = output.source_map.unwrap();
+    assert_eq!(map.get_source(0), Some("source.ts"));
+
+    // `mode` goes through both the type stripping and the export rewriting
+    let line = output
+      .code
+      .lines()
+      .position(|line| line.starts_with("const mode"))
+      .unwrap() as u32;
+    let lookup_table = map.generate_lookup_table();
+    let token = map.lookup_token(&lookup_table, line, 0).unwrap();
+    assert_eq!(token.get_src_line(), 3);
+  }
+}
@@ -0,0 +1,699 @@
+use crate::edits::SourceEdits;
+use crate::exports_and_imports::reference_symbol;
+use crate::features::Features;
+use crate::parse_error;
+use oxc::allocator::Allocator;
+use oxc::ast::ast::{
+  BindingPatternKind, Expression, IdentifierReference, MetaProperty, ModuleExportName, Program,
+  Statement, StaticMemberExpression, UnaryExpression, UnaryOperator, VariableDeclarator,
+};
+use oxc::parser::Parser;
+use oxc::span::{GetSpan, SourceType, Span};
+use oxc_semantic::{SemanticBuilder, SymbolId, SymbolTable};
+use std::collections::HashSet;
+use wyw_processor::error::WywResult;
+use wyw_traverse::{walk, Ancestor, AnyNode, EnterAction, TraverseCtx, TraverseHooks};
+
+// `typeof window` and friends are replaced with `"undefined"`
+const SSR_CHECK_FIELDS: [&str; 6] = [
+  "document",
+  "location",
+  "navigator",
+  "sessionStorage",
+  "localStorage",
+  "window",
+];
+
+const FORBIDDEN_GLOBALS: [&str; 10] = [
+  "$RefreshReg$",
+  "$RefreshSig$",
+  "XMLHttpRequest",
+  "clearImmediate",
+  "clearInterval",
+  "clearTimeout",
+  "fetch",
+  "setImmediate",
+  "setInterval",
+  "setTimeout",
+];
+
+// Removed even if they are declared locally or used inside functions
+const ALWAYS_FORBIDDEN: [&str; 2] = ["$RefreshReg$", "$RefreshSig$"];
+
+// A piece of code that was replaced because of `name`
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemovedCode {
+  pub name: String,
+  pub span: Span,
+  pub value: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DangerousCodeOptions {
+  // Code that should stay intact, e.g. call sites of processors
+  pub ignored_spans: Vec<Span>,
+  pub preserve_import_meta_env: bool,
+}
+
+#[derive(Debug)]
+pub struct DangerousCodeOutput {
+  pub code: String,
+  pub removed: Vec<RemovedCode>,
+}
+
+fn is_ignored(ignored_spans: &[Span], span: Span) -> bool {
+  ignored_spans
+    .iter()
+    .any(|ignored| ignored.start <= span.start && span.end <= ignored.end)
+}
+
+fn is_forbidden_global(name: &str) -> bool {
+  SSR_CHECK_FIELDS.contains(&name) || FORBIDDEN_GLOBALS.contains(&name)
+}
+
+fn is_value_reference(ident: &IdentifierReference, symbols: &SymbolTable) -> bool {
+  ident
+    .reference_id
+    .get()
+    .is_some_and(|reference_id| symbols.get_reference(reference_id).flags().is_value())
+}
+
+// `_exp`, `_exp2`, … are created by processors and should stay intact
+fn is_processor_helper(name: &str) -> bool {
+  name
+    .strip_prefix("_exp")
+    .is_some_and(|suffix| suffix.chars().all(|ch| ch.is_ascii_digit()))
+}
+
+fn is_inside_typeof(ancestors: &[Ancestor]) -> bool {
+  ancestors.iter().any(|ancestor| {
+    matches!(
+      ancestor.node(),
+      AnyNode::UnaryExpression(unary) if unary.operator == UnaryOperator::Typeof
+    )
+  })
+}
+
+// Is the function at `idx` called right away, e.g. `(() => { … })()`?
+fn is_iife(ancestors: &[Ancestor], idx: usize) -> bool {
+  let parent = ancestors[..idx]
+    .iter()
+    .rev()
+    .find(|ancestor| !matches!(ancestor.node(), AnyNode::ParenthesizedExpression(_)));
+  matches!(
+    parent,
+    Some(Ancestor::Field(AnyNode::CallExpression(_), "callee"))
+  )
+}
+
+// Code inside functions does not run during evaluation unless the function is an IIFE
+fn is_deferred(ancestors: &[Ancestor]) -> bool {
+  ancestors.iter().enumerate().any(|(idx, ancestor)| {
+    matches!(
+      ancestor.node(),
+      AnyNode::Function(_) | AnyNode::ArrowFunctionExpression(_)
+    ) && !is_iife(ancestors, idx)
+  })
+}
+
+fn declarator_name<'a>(declarator: &VariableDeclarator<'a>) -> Option<&'a str> {
+  match &declarator.id.kind {
+    BindingPatternKind::BindingIdentifier(ident) => Some(ident.name.as_str()),
+    _ => None,
+  }
+}
+
+fn is_removable_owner(ancestors: &[Ancestor], idx: usize) -> bool {
+  match ancestors[idx].node() {
+    AnyNode::ExpressionStatement(_) => {
+      // `() => foo` keeps its expression in a statement that cannot be removed
+      !matches!(
+        idx.checked_sub(2).map(|idx| ancestors[idx].node()),
+        Some(AnyNode::ArrowFunctionExpression(arrow)) if arrow.expression
+      )
+    }
+    AnyNode::Function(function) => function.is_declaration(),
+    AnyNode::DoWhileStatement(_)
+    | AnyNode::ForInStatement(_)
+    | AnyNode::ForOfStatement(_)
+    | AnyNode::ForStatement(_)
+    | AnyNode::IfStatement(_)
+    | AnyNode::PropertyDefinition(_)
+    | AnyNode::ReturnStatement(_)
+    | AnyNode::VariableDeclaration(_)
+    | AnyNode::WhileStatement(_) => true,
+    _ => false,
+  }
+}
+
+// The closest statement (or class property) above `ancestors[..end]` that can be removed as a whole
+fn removable_owner(ancestors: &[Ancestor], end: usize) -> Option<Span> {
+  let idx = (0..end)
+    .rev()
+    .find(|idx| is_removable_owner(ancestors, *idx))?;
+  if idx > 0 {
+    if let Ancestor::Field(AnyNode::ExportNamedDeclaration(export), "declaration") =
+      &ancestors[idx - 1]
+    {
+      return Some(export.span);
+    }
+  }
+
+  Some(ancestors[idx].node().span())
+}
+
+// `new Promise(…)` or `….then(…)` that contains the reference
+fn promise_owner(ancestors: &[Ancestor]) -> Option<usize> {
+  ancestors
+    .iter()
+    .rposition(|ancestor| match ancestor.node() {
+      AnyNode::NewExpression(new) => {
+        matches!(&new.callee, Expression::Identifier(callee) if callee.name == "Promise")
+      }
+      AnyNode::CallExpression(call) => call
+        .callee
+        .as_member_expression()
+        .and_then(|member| member.static_property_name())
+        .is_some_and(|name| matches!(name, "then" | "catch" | "finally")),
+      _ => false,
+    })
+}
+
+// `export { foo }` and `export default foo` of local bindings
+fn exported_local_names(program: &Program) -> HashSet<String> {
+  let mut names = HashSet::new();
+  for statement in &program.body {
+    match statement {
+      Statement::ExportDefaultDeclaration(export) => {
+        if let Some(Expression::Identifier(ident)) = export.declaration.as_expression() {
+          names.insert(ident.name.to_string());
+        }
+      }
+      Statement::ExportNamedDeclaration(export)
+        if export.declaration.is_none() && export.source.is_none() =>
+      {
+        for specifier in &export.specifiers {
+          if let ModuleExportName::IdentifierReference(local) = &specifier.local {
+            names.insert(local.name.to_string());
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+
+  names
+}
+
+// Removing the declaration of an exported binding would break imports of the module,
+// so only its value is replaced
+fn exported_value(
+  ancestors: &[Ancestor],
+  exported_names: &HashSet<String>,
+) -> Option<(Span, String)> {
+  for (idx, ancestor) in ancestors.iter().enumerate().rev() {
+    match ancestor.node() {
+      AnyNode::ExportDefaultDeclaration(export) => {
+        return Some((export.declaration.span(), "undefined".to_string()));
+      }
+      AnyNode::VariableDeclarator(declarator) => {
+        let name = declarator_name(declarator)?;
+        let init = declarator.init.as_ref()?;
+        let is_exported = match idx.checked_sub(2).map(|idx| &ancestors[idx]) {
+          Some(Ancestor::Field(AnyNode::ExportNamedDeclaration(_), "declaration")) => true,
+          Some(Ancestor::ListItem(AnyNode::Program(_), "body", _)) => exported_names.contains(name),
+          _ => false,
+        };
+
+        return is_exported.then(|| (init.span(), "undefined".to_string()));
+      }
+      _ => {}
+    }
+  }
+
+  None
+}
+
+// Bindings that are initialized with browser-only values, e.g. `const width = window.innerWidth`
+struct DerivedBindingsCollector<'s> {
+  ignored_spans: &'s [Span],
+  window_scoped: &'s HashSet<String>,
+  // Declarators with their references to other local bindings
+  declarators: Vec<(SymbolId, bool, HashSet<SymbolId>)>,
+  current: Vec<usize>,
+}
+
+impl DerivedBindingsCollector<'_> {
+  fn finish(self) -> HashSet<SymbolId> {
+    let mut derived = self
+      .declarators
+      .iter()
+      .filter(|(_, is_forbidden, _)| *is_forbidden)
+      .map(|(id, ..)| *id)
+      .collect::<HashSet<_>>();
+
+    loop {
+      let found = self
+        .declarators
+        .iter()
+        .filter(|(id, _, references)| {
+          !derived.contains(id) && references.iter().any(|id| derived.contains(id))
+        })
+        .map(|(id, ..)| *id)
+        .collect::<Vec<_>>();
+      if found.is_empty() {
+        return derived;
+      }
+
+      derived.extend(found);
+    }
+  }
+}
+
+impl<'a> TraverseHooks<'a> for DerivedBindingsCollector<'_> {
+  fn enter_variable_declarator(
+    &mut self,
+    node: &'a VariableDeclarator<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if let (BindingPatternKind::BindingIdentifier(ident), Some(_)) = (&node.id.kind, &node.init) {
+      if let Some(symbol_id) = ident.symbol_id.get() {
+        self.current.push(self.declarators.len());
+        self
+          .declarators
+          .push((symbol_id, false, HashSet::default()));
+      }
+    }
+
+    EnterAction::Continue
+  }
+
+  fn exit_variable_declarator(
+    &mut self,
+    node: &'a VariableDeclarator<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) {
+    if let (BindingPatternKind::BindingIdentifier(ident), Some(_)) = (&node.id.kind, &node.init) {
+      if ident.symbol_id.get().is_some() {
+        self.current.pop();
+      }
+    }
+  }
+
+  fn enter_identifier_reference(
+    &mut self,
+    node: &'a IdentifierReference<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.current.is_empty()
+      || is_ignored(self.ignored_spans, node.span)
+      || !is_value_reference(node, ctx.symbols())
+      || is_inside_typeof(&ctx.ancestors)
+    {
+      return EnterAction::Continue;
+    }
+
+    let symbol_id = reference_symbol(node, ctx.symbols());
+    let name = node.name.as_str();
+    let is_forbidden = ALWAYS_FORBIDDEN.contains(&name)
+      || (symbol_id.is_none() && (is_forbidden_global(name) || self.window_scoped.contains(name)));
+    for idx in &self.current {
+      let (_, forbidden, references) = &mut self.declarators[*idx];
+      *forbidden |= is_forbidden;
+      references.extend(symbol_id);
+    }
+
+    EnterAction::Continue
+  }
+}
+
+// `foo` is used as a global if something assigns or reads `window.foo`
+struct WindowScopedNamesCollector<'s> {
+  ignored_spans: &'s [Span],
+  names: HashSet<String>,
+}
+
+impl<'a> TraverseHooks<'a> for WindowScopedNamesCollector<'_> {
+  fn enter_static_member_expression(
+    &mut self,
+    node: &'a StaticMemberExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if is_ignored(self.ignored_spans, node.span) {
+      return EnterAction::Continue;
+    }
+
+    if let Expression::Identifier(object) = &node.object {
+      if object.name == "window" && reference_symbol(object, ctx.symbols()).is_none() {
+        self.names.insert(node.property.name.to_string());
+      }
+    }
+
+    EnterAction::Continue
+  }
+}
+
+struct DangerousCodeCollector<'s> {
+  options: &'s DangerousCodeOptions,
+  window_scoped: &'s HashSet<String>,
+  derived: &'s HashSet<SymbolId>,
+  exported_names: HashSet<String>,
+  removed: Vec<RemovedCode>,
+}
+
+impl DangerousCodeCollector<'_> {
+  fn push(&mut self, name: &str, span: Span, value: impl Into<String>) {
+    self.removed.push(RemovedCode {
+      name: name.to_string(),
+      span,
+      value: value.into(),
+    });
+  }
+
+  // Outer replacements win
+  fn finish(mut self) -> Vec<RemovedCode> {
+    self
+      .removed
+      .sort_by_key(|removed| (removed.span.start, u32::MAX - removed.span.end));
+
+    let mut result: Vec<RemovedCode> = vec![];
+    for removed in self.removed {
+      if let Some(last) = result.last() {
+        if removed.span.start >= last.span.start && removed.span.end <= last.span.end {
+          continue;
+        }
+      }
+
+      result.push(removed);
+    }
+
+    result
+  }
+}
+
+impl<'a> TraverseHooks<'a> for DangerousCodeCollector<'_> {
+  fn enter_unary_expression(
+    &mut self,
+    node: &'a UnaryExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if node.operator != UnaryOperator::Typeof || is_ignored(&self.options.ignored_spans, node.span)
+    {
+      return EnterAction::Continue;
+    }
+
+    if let Expression::Identifier(argument) = &node.argument {
+      let name = argument.name.as_str();
+      if SSR_CHECK_FIELDS.contains(&name) && reference_symbol(argument, ctx.symbols()).is_none() {
+        self.push(name, node.span, "\"undefined\"");
+        return EnterAction::Ignore;
+      }
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_meta_property(
+    &mut self,
+    node: &'a MetaProperty<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if is_ignored(&self.options.ignored_spans, node.span) {
+      return EnterAction::Continue;
+    }
+
+    let is_env = matches!(
+      ctx.parent(),
+      Some(Ancestor::Field(AnyNode::StaticMemberExpression(member), "object"))
+        if member.property.name == "env"
+    );
+    if is_env && self.options.preserve_import_meta_env {
+      return EnterAction::Continue;
+    }
+
+    let span = removable_owner(&ctx.ancestors, ctx.ancestors.len()).unwrap_or(node.span);
+    let name = format!("{}.{}", node.meta.name, node.property.name);
+    self.push(&name, span, "");
+    EnterAction::Continue
+  }
+
+  fn enter_identifier_reference(
+    &mut self,
+    node: &'a IdentifierReference<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let symbols = ctx.symbols();
+    let ancestors = &ctx.ancestors;
+    if is_ignored(&self.options.ignored_spans, node.span)
+      || !is_value_reference(node, symbols)
+      || is_inside_typeof(ancestors)
+    {
+      return EnterAction::Continue;
+    }
+
+    let name = node.name.as_str();
+    let symbol_id = reference_symbol(node, symbols);
+    let is_always_forbidden = ALWAYS_FORBIDDEN.contains(&name);
+    let is_forbidden = match symbol_id {
+      Some(symbol_id) => self.derived.contains(&symbol_id),
+      None => is_forbidden_global(name) || self.window_scoped.contains(name),
+    };
+    if !is_always_forbidden && (!is_forbidden || is_deferred(ancestors)) {
+      return EnterAction::Continue;
+    }
+
+    if let Some(Ancestor::Field(AnyNode::ExportDefaultDeclaration(_), "declaration")) = ctx.parent()
+    {
+      return EnterAction::Continue;
+    }
+
+    let is_inside_helper = ancestors.iter().any(|ancestor| {
+      matches!(
+        ancestor.node(),
+        AnyNode::VariableDeclarator(declarator)
+          if declarator_name(declarator).is_some_and(is_processor_helper)
+      )
+    });
+    if is_inside_helper || (is_processor_helper(name) && symbol_id.is_some()) {
+      return EnterAction::Continue;
+    }
+
+    if let Some(Ancestor::Field(AnyNode::ObjectProperty(property), "value")) = ctx.parent() {
+      if symbol_id.is_none() {
+        let value = match property.shorthand {
+          true => format!("{name}: undefined"),
+          false => "undefined".to_string(),
+        };
+        self.push(name, node.span, value);
+      }
+
+      return EnterAction::Continue;
+    }
+
+    // `...window.foo` → `...{}`
+    if let [.., Ancestor::Field(AnyNode::SpreadElement(spread), "argument"), Ancestor::Field(
+      AnyNode::StaticMemberExpression(_) | AnyNode::ComputedMemberExpression(_),
+      "object",
+    )] = ancestors.as_slice()
+    {
+      self.push(name, spread.span, "...{}");
+      return EnterAction::Continue;
+    }
+
+    if let Some((span, value)) = exported_value(ancestors, &self.exported_names) {
+      self.push(name, span, value);
+      return EnterAction::Continue;
+    }
+
+    let end = promise_owner(ancestors).unwrap_or(ancestors.len());
+    match removable_owner(ancestors, end) {
+      Some(span) => self.push(name, span, ""),
+      None => self.push(name, node.span, "undefined"),
+    }
+
+    EnterAction::Continue
+  }
+}
+
+// The same as `collectDangerousCodeReplacementsWithOxc` without the React-specific replacements
+pub fn collect_dangerous_code<'a>(
+  program: &'a Program<'a>,
+  source_text: &str,
+  symbols: &'a SymbolTable,
+  options: &DangerousCodeOptions,
+) -> Vec<RemovedCode> {
+  let mut window_scoped = WindowScopedNamesCollector {
+    ignored_spans: &options.ignored_spans,
+    names: HashSet::new(),
+  };
+  if source_text.contains("window") {
+    walk(&mut window_scoped, program, symbols);
+  }
+
+  let mut derived = DerivedBindingsCollector {
+    ignored_spans: &options.ignored_spans,
+    window_scoped: &window_scoped.names,
+    declarators: vec![],
+    current: vec![],
+  };
+  walk(&mut derived, program, symbols);
+  let derived = derived.finish();
+
+  let mut collector = DangerousCodeCollector {
+    options,
+    window_scoped: &window_scoped.names,
+    derived: &derived,
+    exported_names: exported_local_names(program),
+    removed: vec![],
+  };
+  walk(&mut collector, program, symbols);
+  collector.finish()
+}
+
+// Replaces browser-only code before evaluation unless `dangerousCodeRemover` is disabled for the file
+pub fn remove_dangerous_code(
+  filename: &str,
+  source_text: &str,
+  features: &Features,
+) -> WywResult<DangerousCodeOutput> {
+  if !features.dangerous_code_remover.is_enabled(filename) {
+    return Ok(DangerousCodeOutput {
+      code: source_text.to_string(),
+      removed: vec![],
+    });
+  }
+
+  let allocator = Allocator::default();
+  let source_type = SourceType::from_path(filename).unwrap_or_default();
+  let ret = Parser::new(&allocator, source_text, source_type).parse();
+  if let Some(error) = ret.errors.first() {
+    return Err(parse_error(filename, error));
+  }
+
+  let program = ret.program;
+  let (symbols, _) = SemanticBuilder::new()
+    .build(&program)
+    .semantic
+    .into_symbol_table_and_scope_tree();
+
+  let removed = collect_dangerous_code(
+    &program,
+    source_text,
+    &symbols,
+    &DangerousCodeOptions::default(),
+  );
+  let mut edits = SourceEdits::new(source_text);
+  for removed in &removed {
+    edits.overwrite(removed.span, removed.value.clone());
+  }
+
+  Ok(DangerousCodeOutput {
+    code: edits.to_code(),
+    removed,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::features::FeatureFlag;
+
+  fn remove(source_text: &str) -> DangerousCodeOutput {
+    remove_dangerous_code("index.ts", source_text, &Features::default()).unwrap()
+  }
+
+  #[test]
+  fn test_forbidden_globals() {
+    let output = remove(
+      r#"
+const isBrowser = typeof window !== 'undefined';
+const width = window.innerWidth;
+const half = width / 2;
+setTimeout(() => {}, 100);
+export const size = document.body.clientHeight;
+const config = { window, fetch: fetch };
+const spread = { ...window.config };
+function later() {
+  return window.location;
+}
+"#,
+    );
+
+    assert_eq!(
+      output.code,
+      r#"
+const isBrowser = "undefined" !== 'undefined';
+
+
+
+export const size = undefined;
+const config = { window: undefined, fetch: undefined };
+const spread = { ...{} };
+function later() {
+  return window.location;
+}
+"#
+    );
+    assert_eq!(
+      output
+        .removed
+        .iter()
+        .map(|removed| removed.name.as_str())
+        .collect::<Vec<_>>(),
+      [
+        "window",
+        "window",
+        "width",
+        "setTimeout",
+        "document",
+        "window",
+        "fetch",
+        "window"
+      ]
+    );
+  }
+
+  #[test]
+  fn test_local_bindings() {
+    let output = remove(
+      r#"
+const setTimeout = (fn) => fn();
+setTimeout(() => {});
+(() => {
+  fetch('/api');
+})();
+new Promise((resolve) => setTimeout(resolve)).then(() => fetch('/api'));
+$RefreshReg$(Component, "Component");
+"#,
+    );
+
+    assert_eq!(
+      output.code,
+      r#"
+const setTimeout = (fn) => fn();
+setTimeout(() => {});
+(() => {
+  
+})();
+new Promise((resolve) => setTimeout(resolve)).then(() => fetch('/api'));
+
+"#
+    );
+  }
+
+  #[test]
+  fn test_feature_flag() {
+    let features = Features {
+      dangerous_code_remover: FeatureFlag::patterns(["**/*", "!**/vendor/**"]),
+      ..Default::default()
+    };
+    let source_text = "const width = window.innerWidth;\n";
+
+    let output = remove_dangerous_code("src/vendor/index.ts", source_text, &features).unwrap();
+    assert_eq!(output.code, source_text);
+    assert!(output.removed.is_empty());
+
+    let output = remove_dangerous_code("src/index.ts", source_text, &features).unwrap();
+    assert_eq!(output.code, "\n");
+  }
+}
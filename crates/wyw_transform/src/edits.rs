@@ -0,0 +1,274 @@
+use oxc::sourcemap::{SourceMap, SourceMapBuilder};
+use oxc::span::Span;
+
+struct Edit {
+  span: Span,
+  value: String,
+}
+
+// Line and UTF-16 column of a position, the same units that JS tools use in source maps
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct Position {
+  line: u32,
+  column: u32,
+}
+
+impl Position {
+  fn advance(&mut self, text: &str) {
+    for ch in text.chars() {
+      if ch == '\n' {
+        self.line += 1;
+        self.column = 0;
+      } else {
+        self.column += ch.len_utf16() as u32;
+      }
+    }
+  }
+}
+
+// Tracks replacements of spans in the original source, like magic-string does,
+// and produces the resulting code together with a source map.
+pub struct SourceEdits<'s> {
+  source_text: &'s str,
+  edits: Vec<Edit>,
+}
+
+impl<'s> SourceEdits<'s> {
+  pub fn new(source_text: &'s str) -> Self {
+    Self {
+      source_text,
+      edits: vec![],
+    }
+  }
+
+  pub fn overwrite(&mut self, span: Span, value: impl Into<String>) {
+    self.edits.push(Edit {
+      span,
+      value: value.into(),
+    });
+  }
+
+  pub fn remove(&mut self, span: Span) {
+    self.overwrite(span, "");
+  }
+
+  // Inserts go before other edits at the same offset and are kept unless they are inside a replaced span
+  pub fn insert(&mut self, offset: u32, value: impl Into<String>) {
+    self.overwrite(Span::new(offset, offset), value);
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.edits.is_empty()
+  }
+
+  // Inserts and outer edits go first, nested ones are dropped
+  fn sorted_edits(&mut self) -> Vec<Edit> {
+    let mut edits = std::mem::take(&mut self.edits);
+    edits.sort_by_key(|edit| {
+      let is_insert = edit.span.is_empty();
+      (edit.span.start, !is_insert, u32::MAX - edit.span.end)
+    });
+
+    let mut last = 0;
+    edits.retain(|edit| {
+      if edit.span.start < last {
+        return false;
+      }
+
+      last = edit.span.end;
+      true
+    });
+
+    edits
+  }
+
+  pub fn to_code(mut self) -> String {
+    let mut result = String::with_capacity(self.source_text.len());
+    let mut last = 0;
+    for edit in self.sorted_edits() {
+      result.push_str(&self.source_text[last..edit.span.start as usize]);
+      result.push_str(&edit.value);
+      last = edit.span.end as usize;
+    }
+
+    result.push_str(&self.source_text[last..]);
+    result
+  }
+
+  // Every line of unchanged code is mapped to its original position,
+  // replacements are mapped to the start of the replaced span.
+  pub fn to_code_and_map(mut self, filename: &str) -> (String, SourceMap) {
+    let mut output = MappedOutput::new(filename, self.source_text);
+    let mut last = 0;
+    for edit in self.sorted_edits() {
+      output.push_original(&self.source_text[last..edit.span.start as usize]);
+      output.push_replacement(&edit.value, edit.span.source_text(self.source_text));
+      last = edit.span.end as usize;
+    }
+
+    output.push_original(&self.source_text[last..]);
+    (output.code, output.builder.into_sourcemap())
+  }
+}
+
+struct MappedOutput {
+  code: String,
+  builder: SourceMapBuilder,
+  source_id: u32,
+  generated: Position,
+  original: Position,
+}
+
+impl MappedOutput {
+  fn new(filename: &str, source_text: &str) -> Self {
+    let mut builder = SourceMapBuilder::default();
+    let source_id = builder.set_source_and_content(filename, source_text);
+    Self {
+      code: String::with_capacity(source_text.len()),
+      builder,
+      source_id,
+      generated: Position::default(),
+      original: Position::default(),
+    }
+  }
+
+  fn add_token(&mut self) {
+    self.builder.add_token(
+      self.generated.line,
+      self.generated.column,
+      self.original.line,
+      self.original.column,
+      Some(self.source_id),
+      None,
+    );
+  }
+
+  fn push_original(&mut self, text: &str) {
+    for line in text.split_inclusive('\n') {
+      self.add_token();
+      self.code.push_str(line);
+      self.generated.advance(line);
+      self.original.advance(line);
+    }
+  }
+
+  fn push_replacement(&mut self, value: &str, replaced: &str) {
+    if !value.is_empty() {
+      self.add_token();
+    }
+
+    self.code.push_str(value);
+    self.generated.advance(value);
+    self.original.advance(replaced);
+  }
+}
+
+// Maps positions of `map` through `input`, so that the result points to the sources of `input`
+pub fn compose(map: &SourceMap, input: &SourceMap) -> SourceMap {
+  let lookup_table = input.generate_lookup_table();
+  let mut builder = SourceMapBuilder::default();
+
+  for token in map.get_tokens() {
+    let Some(original) =
+      input.lookup_source_view_token(&lookup_table, token.get_src_line(), token.get_src_col())
+    else {
+      continue;
+    };
+
+    let source_id = original.get_source().map(|source| {
+      builder.add_source_and_content(source, original.get_source_content().unwrap_or_default())
+    });
+    let name_id = original.get_name().map(|name| builder.add_name(name));
+
+    builder.add_token(
+      token.get_dst_line(),
+      token.get_dst_col(),
+      original.get_src_line(),
+      original.get_src_col(),
+      source_id,
+      name_id,
+    );
+  }
+
+  if let Some(file) = map.get_file() {
+    builder.set_file(file);
+  }
+
+  builder.into_sourcemap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn tokens(map: &SourceMap) -> Vec<(u32, u32, u32, u32)> {
+    map
+      .get_tokens()
+      .map(|token| {
+        (
+          token.get_dst_line(),
+          token.get_dst_col(),
+          token.get_src_line(),
+          token.get_src_col(),
+        )
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_to_code() {
+    let source_text = "const a = css`color: red;`;";
+    let mut edits = SourceEdits::new(source_text);
+    edits.overwrite(Span::new(10, 26), "\"a0\"");
+    edits.overwrite(Span::new(13, 26), "nested");
+    assert_eq!(edits.to_code(), "const a = \"a0\";");
+  }
+
+  #[test]
+  fn test_insert() {
+    let source_text = "css`a`;\nb;";
+    let mut edits = SourceEdits::new(source_text);
+    edits.overwrite(Span::new(0, 6), "\"a0\"");
+    edits.insert(0, "const _exp = 1;\n");
+    edits.insert(0, "const _exp2 = 2;\n");
+    edits.insert(3, "dropped");
+    assert_eq!(
+      edits.to_code(),
+      "const _exp = 1;\nconst _exp2 = 2;\n\"a0\";\nb;"
+    );
+  }
+
+  #[test]
+  fn test_source_map() {
+    let source_text =
+      "import { css } from 'lib';\nconst a = css`\n  color: red;\n`;\nexport { a };\n";
+    let mut edits = SourceEdits::new(source_text);
+    edits.remove(Span::new(0, 27));
+    edits.overwrite(Span::new(37, 57), "\"a0\"");
+
+    let (code, map) = edits.to_code_and_map("index.ts");
+    assert_eq!(code, "const a = \"a0\";\nexport { a };\n");
+    assert_eq!(
+      tokens(&map),
+      [(0, 0, 1, 0), (0, 10, 1, 10), (0, 14, 3, 1), (1, 0, 4, 0)]
+    );
+    assert_eq!(map.get_source_content(0), Some(source_text));
+  }
+
+  #[test]
+  fn test_compose() {
+    // `a` was moved one line down by a previous tool
+    let mut builder = SourceMapBuilder::default();
+    let source_id = builder.set_source_and_content("original.ts", "a");
+    builder.add_token(1, 0, 0, 0, Some(source_id), None);
+    let input = builder.into_sourcemap();
+
+    let mut edits = SourceEdits::new("\na;");
+    edits.overwrite(Span::new(1, 2), "b");
+    let (_, map) = edits.to_code_and_map("index.ts");
+
+    let composed = compose(&map, &input);
+    assert_eq!(tokens(&composed), [(1, 0, 0, 0), (1, 1, 0, 0)]);
+    assert_eq!(composed.get_source(0), Some("original.ts"));
+  }
+}
@@ -0,0 +1,653 @@
+use crate::edits::SourceEdits;
+use crate::exports_and_imports::reference_symbol;
+use crate::parse_error;
+use crate::static_values::{loose_equals, strict_equals, StaticValue};
+use oxc::allocator::Allocator;
+use oxc::ast::ast::{
+  AssignmentExpression, BinaryOperator, ComputedMemberExpression, ConditionalExpression,
+  Expression, IdentifierReference, IfStatement, LogicalExpression, LogicalOperator,
+  StaticMemberExpression, UnaryExpression, UnaryOperator, UpdateExpression,
+};
+use oxc::parser::Parser;
+use oxc::span::{GetSpan, SourceType, Span};
+use oxc_semantic::{SemanticBuilder, SymbolTable};
+use std::collections::{BTreeMap, HashSet};
+use wyw_processor::error::WywResult;
+use wyw_traverse::{walk, Ancestor, AnyNode, EnterAction, TraverseCtx, TraverseHooks};
+
+#[derive(Clone, Debug, Default)]
+pub struct EnvInliningOptions {
+  // Vite-style `import.meta.env`. If it's set, unknown variables are `undefined`.
+  pub import_meta_env: Option<BTreeMap<String, StaticValue>>,
+  // Known variables of `process.env`, other ones are left as is
+  pub process_env: BTreeMap<String, StaticValue>,
+  // `DefinePlugin`-style keys, e.g. `__DEV__` or `process.env.NODE_ENV`
+  pub defines: BTreeMap<String, StaticValue>,
+  // `typeof window` is inlined as `"object"` or `"undefined"` if it's set
+  pub window: Option<bool>,
+}
+
+impl EnvInliningOptions {
+  fn is_empty(&self) -> bool {
+    self.import_meta_env.is_none()
+      && self.process_env.is_empty()
+      && self.defines.is_empty()
+      && self.window.is_none()
+  }
+
+  fn lookup(&self, path: &str) -> Option<StaticValue> {
+    if let Some(value) = self.defines.get(path) {
+      return Some(value.clone());
+    }
+
+    if let Some(env) = &self.import_meta_env {
+      if path == "import.meta.env" {
+        let entries = env.iter().map(|(key, value)| (key.clone(), value.clone()));
+        return Some(StaticValue::Object(entries.collect()));
+      }
+
+      if let Some(key) = path.strip_prefix("import.meta.env.") {
+        if let Some(value) = env.get(key) {
+          return Some(value.clone());
+        }
+
+        // `import.meta.env.MODE.startsWith` is a member of a variable, not a variable itself
+        if !key.contains('.') {
+          return Some(StaticValue::Undefined);
+        }
+      }
+    }
+
+    let key = path.strip_prefix("process.env.")?;
+    self.process_env.get(key).cloned()
+  }
+}
+
+#[derive(Debug)]
+pub struct EnvInliningOutput {
+  pub code: String,
+  // Keys that were replaced with their values, e.g. `import.meta.env.DEV`
+  pub inlined: Vec<String>,
+  // The number of conditions that became constant
+  pub folded: usize,
+}
+
+// `process.env.NODE_ENV` for a member chain that starts with a global or `import.meta`
+fn member_path(expression: &Expression, symbols: &SymbolTable) -> Option<String> {
+  match expression.get_inner_expression() {
+    Expression::Identifier(ident) if reference_symbol(ident, symbols).is_none() => {
+      Some(ident.name.to_string())
+    }
+    Expression::MetaProperty(meta) if meta.meta.name == "import" => {
+      Some(format!("import.{}", meta.property.name))
+    }
+    Expression::StaticMemberExpression(member) => Some(format!(
+      "{}.{}",
+      member_path(&member.object, symbols)?,
+      member.property.name
+    )),
+    Expression::ComputedMemberExpression(member) => match &member.expression {
+      Expression::StringLiteral(property) => Some(format!(
+        "{}.{}",
+        member_path(&member.object, symbols)?,
+        property.value
+      )),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+fn value_code(value: &StaticValue, parent: Option<&Ancestor>) -> String {
+  let is_object = matches!(
+    parent,
+    Some(Ancestor::Field(
+      AnyNode::StaticMemberExpression(_) | AnyNode::ComputedMemberExpression(_),
+      "object"
+    ))
+  );
+  let is_operand = matches!(
+    parent,
+    Some(Ancestor::Field(
+      AnyNode::UnaryExpression(_) | AnyNode::BinaryExpression(_),
+      _
+    ))
+  );
+
+  match value {
+    // `{` at the beginning of a statement is a block
+    StaticValue::Object(_) => format!("({})", value.to_code()),
+    // `1.toFixed()` and `x - -1` are not what they look like
+    StaticValue::Number(number) if is_object || (is_operand && number.is_sign_negative()) => {
+      format!("({})", value.to_code())
+    }
+    value => value.to_code(),
+  }
+}
+
+struct EnvInliner<'o, 's> {
+  options: &'o EnvInliningOptions,
+  symbols: &'s SymbolTable,
+  edits: SourceEdits<'s>,
+  // Assignment targets and operands of `delete`, `++` and `--`
+  writes: HashSet<Span>,
+  // Code removed by folded conditions
+  dropped: Vec<Span>,
+  inlined: Vec<String>,
+  folded: usize,
+}
+
+impl EnvInliner<'_, '_> {
+  fn constant(&self, expression: &Expression) -> Option<StaticValue> {
+    match expression.get_inner_expression() {
+      Expression::StringLiteral(literal) => Some(StaticValue::String(literal.value.to_string())),
+      Expression::NumericLiteral(literal) => Some(StaticValue::Number(literal.value)),
+      Expression::BooleanLiteral(literal) => Some(StaticValue::Boolean(literal.value)),
+      Expression::NullLiteral(_) => Some(StaticValue::Null),
+      Expression::Identifier(ident)
+        if ident.name == "undefined" && reference_symbol(ident, self.symbols).is_none() =>
+      {
+        Some(StaticValue::Undefined)
+      }
+      Expression::UnaryExpression(unary) => match unary.operator {
+        UnaryOperator::LogicalNot => Some(StaticValue::Boolean(
+          !self.constant(&unary.argument)?.is_truthy(),
+        )),
+        UnaryOperator::Typeof => match self.typeof_window(unary) {
+          Some(value) => Some(StaticValue::String(value.to_string())),
+          None => Some(StaticValue::String(
+            self.constant(&unary.argument)?.type_of().to_string(),
+          )),
+        },
+        _ => None,
+      },
+      Expression::BinaryExpression(binary) => {
+        let left = self.constant(&binary.left)?;
+        let right = self.constant(&binary.right)?;
+        let equals = match binary.operator {
+          BinaryOperator::StrictEquality => strict_equals(&left, &right).ok()?,
+          BinaryOperator::StrictInequality => !strict_equals(&left, &right).ok()?,
+          BinaryOperator::Equality => loose_equals(&left, &right).ok()?,
+          BinaryOperator::Inequality => !loose_equals(&left, &right).ok()?,
+          _ => return None,
+        };
+        Some(StaticValue::Boolean(equals))
+      }
+      Expression::LogicalExpression(logical) => {
+        let left = self.constant(&logical.left)?;
+        let use_left = match logical.operator {
+          LogicalOperator::And => !left.is_truthy(),
+          LogicalOperator::Or => left.is_truthy(),
+          LogicalOperator::Coalesce => !left.is_nullish(),
+        };
+        match use_left {
+          true => Some(left),
+          false => self.constant(&logical.right),
+        }
+      }
+      expression => self.options.lookup(&member_path(expression, self.symbols)?),
+    }
+  }
+
+  fn typeof_window(&self, node: &UnaryExpression) -> Option<&'static str> {
+    let window = self.options.window?;
+    match &node.argument {
+      Expression::Identifier(ident)
+        if ident.name == "window" && reference_symbol(ident, self.symbols).is_none() =>
+      {
+        Some(if window { "object" } else { "undefined" })
+      }
+      _ => None,
+    }
+  }
+
+  fn is_dropped(&self, span: Span) -> bool {
+    self
+      .dropped
+      .iter()
+      .any(|dropped| dropped.start <= span.start && span.end <= dropped.end)
+  }
+
+  fn inline(&mut self, span: Span, path: String, parent: Option<&Ancestor>) -> EnterAction {
+    if self.writes.contains(&span) {
+      return EnterAction::Continue;
+    }
+
+    let Some(value) = self.options.lookup(&path) else {
+      return EnterAction::Continue;
+    };
+
+    self.edits.overwrite(span, value_code(&value, parent));
+    if !self.inlined.contains(&path) {
+      self.inlined.push(path);
+    }
+
+    EnterAction::Ignore
+  }
+
+  // Replaces `outer` with `kept`, the rest of the code is dropped together with nested edits
+  fn keep(&mut self, outer: Span, kept: Span, parenthesize: bool) {
+    let (open, close) = if parenthesize { ("(", ")") } else { ("", "") };
+    let (before, after) = (
+      Span::new(outer.start, kept.start),
+      Span::new(kept.end, outer.end),
+    );
+    self.edits.overwrite(before, open);
+    self.edits.overwrite(after, close);
+    self.dropped.extend([before, after]);
+    self.folded += 1;
+  }
+}
+
+impl<'a> TraverseHooks<'a> for EnvInliner<'_, '_> {
+  fn enter_assignment_expression(
+    &mut self,
+    node: &'a AssignmentExpression<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    self.writes.insert(node.left.span());
+    EnterAction::Continue
+  }
+
+  fn enter_update_expression(
+    &mut self,
+    node: &'a UpdateExpression<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    self.writes.insert(node.argument.span());
+    EnterAction::Continue
+  }
+
+  fn enter_unary_expression(
+    &mut self,
+    node: &'a UnaryExpression<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.is_dropped(node.span) {
+      return EnterAction::Ignore;
+    }
+
+    if node.operator == UnaryOperator::Delete {
+      self.writes.insert(node.argument.span());
+    }
+
+    match self.typeof_window(node) {
+      Some(value) => {
+        self.edits.overwrite(node.span, format!("\"{value}\""));
+        EnterAction::Ignore
+      }
+      None => EnterAction::Continue,
+    }
+  }
+
+  fn enter_static_member_expression(
+    &mut self,
+    node: &'a StaticMemberExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.is_dropped(node.span) {
+      return EnterAction::Ignore;
+    }
+
+    match member_path(&node.object, self.symbols) {
+      Some(object) => self.inline(
+        node.span,
+        format!("{object}.{}", node.property.name),
+        ctx.parent(),
+      ),
+      None => EnterAction::Continue,
+    }
+  }
+
+  fn enter_computed_member_expression(
+    &mut self,
+    node: &'a ComputedMemberExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.is_dropped(node.span) {
+      return EnterAction::Ignore;
+    }
+
+    let Expression::StringLiteral(property) = &node.expression else {
+      return EnterAction::Continue;
+    };
+
+    match member_path(&node.object, self.symbols) {
+      Some(object) => self.inline(
+        node.span,
+        format!("{object}.{}", property.value),
+        ctx.parent(),
+      ),
+      None => EnterAction::Continue,
+    }
+  }
+
+  fn enter_identifier_reference(
+    &mut self,
+    node: &'a IdentifierReference<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.is_dropped(node.span) {
+      return EnterAction::Ignore;
+    }
+
+    if reference_symbol(node, self.symbols).is_some()
+      || !self.options.defines.contains_key(node.name.as_str())
+    {
+      return EnterAction::Continue;
+    }
+
+    self.inline(node.span, node.name.to_string(), ctx.parent())
+  }
+
+  fn enter_if_statement(
+    &mut self,
+    node: &'a IfStatement<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.is_dropped(node.span) {
+      return EnterAction::Ignore;
+    }
+
+    let Some(test) = self.constant(&node.test) else {
+      return EnterAction::Continue;
+    };
+
+    let kept = match test.is_truthy() {
+      true => Some(&node.consequent),
+      false => node.alternate.as_ref(),
+    };
+    match kept {
+      Some(kept) => self.keep(node.span, kept.span(), false),
+      None => {
+        // `else if (…)` and bodies of loops need a statement
+        let value = match ctx.parent() {
+          Some(Ancestor::ListItem(..)) => "",
+          _ => ";",
+        };
+        self.edits.overwrite(node.span, value);
+        self.dropped.push(node.span);
+        self.folded += 1;
+      }
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_conditional_expression(
+    &mut self,
+    node: &'a ConditionalExpression<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.is_dropped(node.span) {
+      return EnterAction::Ignore;
+    }
+
+    if let Some(test) = self.constant(&node.test) {
+      let kept = match test.is_truthy() {
+        true => node.consequent.span(),
+        false => node.alternate.span(),
+      };
+      self.keep(node.span, kept, true);
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_logical_expression(
+    &mut self,
+    node: &'a LogicalExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.is_dropped(node.span) {
+      return EnterAction::Ignore;
+    }
+
+    let Some(left) = self.constant(&node.left) else {
+      return EnterAction::Continue;
+    };
+
+    let use_left = match node.operator {
+      LogicalOperator::And => !left.is_truthy(),
+      LogicalOperator::Or => left.is_truthy(),
+      LogicalOperator::Coalesce => !left.is_nullish(),
+    };
+    if use_left {
+      self
+        .edits
+        .overwrite(node.span, value_code(&left, ctx.parent()));
+      self.folded += 1;
+      return EnterAction::Ignore;
+    }
+
+    self.keep(node.span, node.right.span(), true);
+    EnterAction::Continue
+  }
+}
+
+// Inlines `import.meta.env.X`, `process.env.X`, defines and `typeof window` before shaking,
+// so that branches for other modes and environments become dead code
+pub fn inline_env(
+  filename: &str,
+  source_text: &str,
+  options: &EnvInliningOptions,
+) -> WywResult<EnvInliningOutput> {
+  if options.is_empty() {
+    return Ok(EnvInliningOutput {
+      code: source_text.to_string(),
+      inlined: vec![],
+      folded: 0,
+    });
+  }
+
+  let allocator = Allocator::default();
+  let source_type = SourceType::from_path(filename)
+    .unwrap_or_default()
+    .with_unambiguous(true);
+  let ret = Parser::new(&allocator, source_text, source_type).parse();
+  if let Some(error) = ret.errors.first() {
+    return Err(parse_error(filename, error));
+  }
+
+  let program = ret.program;
+  let (symbols, _) = SemanticBuilder::new()
+    .build(&program)
+    .semantic
+    .into_symbol_table_and_scope_tree();
+
+  let mut inliner = EnvInliner {
+    options,
+    symbols: &symbols,
+    edits: SourceEdits::new(source_text),
+    writes: HashSet::new(),
+    dropped: vec![],
+    inlined: vec![],
+    folded: 0,
+  };
+  walk(&mut inliner, &program, &symbols);
+
+  Ok(EnvInliningOutput {
+    code: inliner.edits.to_code(),
+    inlined: inliner.inlined,
+    folded: inliner.folded,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::shaker::{shake, ShakerOptions};
+
+  fn options() -> EnvInliningOptions {
+    EnvInliningOptions {
+      import_meta_env: Some(BTreeMap::from([
+        ("DEV".to_string(), StaticValue::Boolean(true)),
+        (
+          "MODE".to_string(),
+          StaticValue::String("development".to_string()),
+        ),
+      ])),
+      process_env: BTreeMap::from([(
+        "NODE_ENV".to_string(),
+        StaticValue::String("production".to_string()),
+      )]),
+      defines: BTreeMap::from([("__TEST__".to_string(), StaticValue::Boolean(false))]),
+      window: Some(false),
+    }
+  }
+
+  fn inline(source_text: &str) -> EnvInliningOutput {
+    inline_env("index.ts", source_text, &options()).unwrap()
+  }
+
+  #[test]
+  fn test_inline_env() {
+    let output = inline(
+      r#"const { MODE } = import.meta.env;
+export const color = import.meta.env.DEV ? "red" : "blue";
+export const env = process.env["NODE_ENV"];
+export const other = process.env.OTHER;
+export const missing = import.meta.env.MISSING;
+export const url = import.meta.url;
+export const isBrowser = typeof window !== "undefined";
+process.env.NODE_ENV = "test";
+"#,
+    );
+
+    assert_eq!(
+      output.code,
+      r#"const { MODE } = ({"DEV": true, "MODE": "development"});
+export const color = ("red");
+export const env = "production";
+export const other = process.env.OTHER;
+export const missing = undefined;
+export const url = import.meta.url;
+export const isBrowser = "undefined" !== "undefined";
+process.env.NODE_ENV = "test";
+"#
+    );
+    assert_eq!(
+      output.inlined,
+      [
+        "import.meta.env",
+        "process.env.NODE_ENV",
+        "import.meta.env.MISSING"
+      ]
+    );
+    assert_eq!(output.folded, 1);
+  }
+
+  #[test]
+  fn test_env_members() {
+    let output = inline(
+      r#"export const isDev = import.meta.env.MODE.startsWith("dev");
+export const length = import.meta.env.MISSING.length;
+"#,
+    );
+
+    assert_eq!(
+      output.code,
+      r#"export const isDev = "development".startsWith("dev");
+export const length = undefined.length;
+"#
+    );
+    assert_eq!(
+      output.inlined,
+      ["import.meta.env.MODE", "import.meta.env.MISSING"]
+    );
+  }
+
+  #[test]
+  fn test_number_values() {
+    let options = EnvInliningOptions {
+      process_env: BTreeMap::from([
+        ("M".to_string(), StaticValue::Number(1.0)),
+        ("N".to_string(), StaticValue::Number(-1.0)),
+      ]),
+      ..Default::default()
+    };
+    let output = inline_env(
+      "index.ts",
+      r#"export const fixed = process.env.M.toFixed(2);
+export const item = process.env["M"]["toString"]();
+export const diff = x -process.env.N;
+export const negated = -process.env.N;
+export const sum = process.env.M + process.env.N;
+export const power = process.env.N ** 2;
+export const list = [process.env.N, process.env.M];
+"#,
+      &options,
+    )
+    .unwrap();
+
+    assert_eq!(
+      output.code,
+      r#"export const fixed = (1).toFixed(2);
+export const item = (1)["toString"]();
+export const diff = x -(-1);
+export const negated = -(-1);
+export const sum = 1 + (-1);
+export const power = (-1) ** 2;
+export const list = [-1, 1];
+"#
+    );
+  }
+
+  #[test]
+  fn test_dead_branches() {
+    let output = inline(
+      r#"import { devtools } from "./devtools";
+import { logger } from "./logger";
+let setup;
+if (process.env.NODE_ENV !== "production") {
+  setup = devtools;
+} else if (__TEST__) {
+  setup = logger;
+} else {
+  setup = () => typeof window === "undefined" && "server";
+}
+export const debug = __TEST__ || import.meta.env.MODE === "development";
+export { setup };
+"#,
+    );
+
+    assert_eq!(
+      output.code,
+      r#"import { devtools } from "./devtools";
+import { logger } from "./logger";
+let setup;
+{
+  setup = () => ("server");
+}
+export const debug = ("development" === "development");
+export { setup };
+"#
+    );
+    assert_eq!(output.folded, 4);
+
+    let shaken = shake(
+      "index.ts",
+      &output.code,
+      &ShakerOptions {
+        only_exports: vec!["setup".to_string()],
+        ..Default::default()
+      },
+    )
+    .unwrap();
+    assert!(shaken.imports.is_empty());
+  }
+
+  #[test]
+  fn test_shadowed() {
+    let output = inline(
+      r#"function read(process, window) {
+  return [process.env.NODE_ENV, typeof window, __TEST__];
+}
+const __TEST__ = true;
+"#,
+    );
+
+    assert!(output.inlined.is_empty());
+    assert_eq!(output.folded, 0);
+  }
+}
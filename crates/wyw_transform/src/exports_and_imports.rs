@@ -0,0 +1,1522 @@
+use crate::cache::ModuleCache;
+use crate::parse_error;
+use indexmap::IndexMap;
+use oxc::allocator::Allocator;
+use oxc::ast::ast::{
+  Argument, AssignmentExpression, AssignmentOperator, AssignmentTarget, BinaryOperator,
+  BindingIdentifier, BindingPattern, BindingPatternKind, CallExpression, Declaration,
+  ExportAllDeclaration, ExportDefaultDeclaration, ExportDefaultDeclarationKind,
+  ExportNamedDeclaration, Expression, ExpressionStatement, FunctionBody, IdentifierReference,
+  ImportDeclaration, ImportDeclarationSpecifier, ImportExpression, MemberExpression,
+  ModuleExportName, ObjectExpression, ObjectPattern, ObjectPropertyKind, Program, PropertyKey,
+  Statement, VariableDeclarator,
+};
+use oxc::parser::Parser;
+use oxc::span::{GetSpan, SourceType, Span};
+use oxc_semantic::{SemanticBuilder, SymbolId, SymbolTable};
+use std::collections::HashMap;
+use wyw_processor::error::WywResult;
+use wyw_traverse::symbol::Symbol;
+use wyw_traverse::{walk, Ancestor, AnyNode, EnterAction, TraverseCtx, TraverseHooks};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImportKind {
+  Cjs,
+  Dynamic,
+  Esm,
+}
+
+// A node that represents an import or an export in the source
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Local {
+  pub code: String,
+  pub span: Span,
+  pub name: Option<String>,
+  // The binding that the node declares or refers to
+  pub symbol: Option<Symbol>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CollectedImport {
+  // A name, `default`, `*` or `side-effect`
+  pub imported: String,
+  pub local: Local,
+  pub source: String,
+  pub kind: ImportKind,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CollectedReexport {
+  pub exported: String,
+  pub imported: String,
+  pub local: Local,
+  pub source: String,
+}
+
+// The same as `OxcCollectedState`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CollectedState {
+  pub dead_exports: Vec<String>,
+  pub exports: IndexMap<String, Local>,
+  pub imports: Vec<CollectedImport>,
+  pub is_es_module: bool,
+  pub reexports: Vec<CollectedReexport>,
+}
+
+impl CollectedState {
+  // Imported names grouped by source in the order of appearance, the same as `toOxcImportMap`
+  pub fn import_map(&self) -> Vec<(String, Vec<String>)> {
+    let mut result: Vec<(String, Vec<String>)> = vec![];
+    let imports = self
+      .imports
+      .iter()
+      .map(|import| (&import.source, &import.imported));
+    let reexports = self
+      .reexports
+      .iter()
+      .map(|reexport| (&reexport.source, &reexport.imported));
+
+    for (source, imported) in imports.chain(reexports) {
+      let idx = match result.iter().position(|(s, _)| s == source) {
+        Some(idx) => idx,
+        None => {
+          result.push((source.clone(), vec![]));
+          result.len() - 1
+        }
+      };
+
+      if !result[idx].1.contains(imported) {
+        result[idx].1.push(imported.clone());
+      }
+    }
+
+    result
+  }
+}
+
+struct NamespaceBinding {
+  local: Local,
+  source: String,
+  kind: ImportKind,
+  used: bool,
+}
+
+fn member_property_name<'a>(member: &MemberExpression<'a>) -> Option<&'a str> {
+  match member {
+    MemberExpression::StaticMemberExpression(member) => Some(member.property.name.as_str()),
+    MemberExpression::ComputedMemberExpression(member) => match &member.expression {
+      Expression::StringLiteral(literal) => Some(literal.value.as_str()),
+      _ => None,
+    },
+    MemberExpression::PrivateFieldExpression(_) => None,
+  }
+}
+
+fn property_key_name<'a>(key: &PropertyKey<'a>) -> Option<&'a str> {
+  match key {
+    PropertyKey::StaticIdentifier(ident) => Some(ident.name.as_str()),
+    PropertyKey::StringLiteral(literal) => Some(literal.value.as_str()),
+    // `{ [key]: value }`, the JS collector treats it as `key`
+    PropertyKey::Identifier(ident) => Some(ident.name.as_str()),
+    _ => None,
+  }
+}
+
+pub(crate) fn binding_identifiers<'p, 'a>(
+  pattern: &'p BindingPattern<'a>,
+  result: &mut Vec<&'p BindingIdentifier<'a>>,
+) {
+  match &pattern.kind {
+    BindingPatternKind::BindingIdentifier(ident) => result.push(ident),
+    BindingPatternKind::AssignmentPattern(assignment) => {
+      binding_identifiers(&assignment.left, result)
+    }
+    BindingPatternKind::ObjectPattern(object) => {
+      for property in &object.properties {
+        binding_identifiers(&property.value, result);
+      }
+
+      if let Some(rest) = &object.rest {
+        binding_identifiers(&rest.argument, result);
+      }
+    }
+    BindingPatternKind::ArrayPattern(array) => {
+      for element in array.elements.iter().flatten() {
+        binding_identifiers(element, result);
+      }
+
+      if let Some(rest) = &array.rest {
+        binding_identifiers(&rest.argument, result);
+      }
+    }
+  }
+}
+
+fn binding_count(pattern: &BindingPattern) -> usize {
+  let mut identifiers = vec![];
+  binding_identifiers(pattern, &mut identifiers);
+  identifiers.len()
+}
+
+// Every binding of `const { a, b: { c, d } } = …` is imported by the first key, i.e. `a`, `b` and `b`
+fn collect_destructed<'p, 'a>(
+  pattern: &'p ObjectPattern<'a>,
+) -> Vec<(&'p BindingPattern<'a>, &'a str)> {
+  let mut result = vec![];
+  for property in &pattern.properties {
+    let Some(key) = property_key_name(&property.key) else {
+      continue;
+    };
+
+    for _ in 0..binding_count(&property.value) {
+      result.push((&property.value, key));
+    }
+  }
+
+  if let Some(rest) = &pattern.rest {
+    for _ in 0..binding_count(&rest.argument) {
+      result.push((&rest.argument, "*"));
+    }
+  }
+
+  result
+}
+
+pub fn get_string_constant(expression: &Expression) -> Option<String> {
+  match expression {
+    Expression::StringLiteral(literal) => Some(literal.value.to_string()),
+    Expression::TemplateLiteral(literal) if literal.expressions.is_empty() => literal
+      .quasis
+      .first()
+      .and_then(|quasi| quasi.value.cooked.as_ref())
+      .map(|cooked| cooked.to_string()),
+    Expression::BinaryExpression(binary) if binary.operator == BinaryOperator::Addition => {
+      Some(get_string_constant(&binary.left)? + &get_string_constant(&binary.right)?)
+    }
+    // `'./foo'.concat('/bar')`
+    Expression::CallExpression(call) => {
+      let callee = call.callee.as_member_expression()?;
+      if member_property_name(callee) != Some("concat") {
+        return None;
+      }
+
+      let mut result = get_string_constant(callee.object())?;
+      for argument in &call.arguments {
+        result.push_str(&get_string_constant(argument.as_expression()?)?);
+      }
+
+      Some(result)
+    }
+    Expression::TSAsExpression(expression) => get_string_constant(&expression.expression),
+    Expression::TSSatisfiesExpression(expression) => get_string_constant(&expression.expression),
+    Expression::TSNonNullExpression(expression) => get_string_constant(&expression.expression),
+    Expression::TSTypeAssertion(expression) => get_string_constant(&expression.expression),
+    Expression::ParenthesizedExpression(expression) => get_string_constant(&expression.expression),
+    _ => None,
+  }
+}
+
+fn first_argument<'b, 'a>(call: &'b CallExpression<'a>) -> Option<&'b Expression<'a>> {
+  call.arguments.first().and_then(Argument::as_expression)
+}
+
+fn callee_name<'a>(callee: &Expression<'a>) -> Option<&'a str> {
+  match callee {
+    Expression::Identifier(ident) => Some(ident.name.as_str()),
+    _ => callee.as_member_expression().and_then(member_property_name),
+  }
+}
+
+fn is_exports_object(expression: &Expression) -> bool {
+  expression.is_specific_id("exports")
+}
+
+// `exports.foo` or `module.exports` that is treated as `default`
+fn export_assignment_name<'a>(member: &MemberExpression<'a>) -> Option<&'a str> {
+  if is_exports_object(member.object()) {
+    return member_property_name(member);
+  }
+
+  if member.object().is_specific_id("module") && member_property_name(member) == Some("exports") {
+    return Some("default");
+  }
+
+  None
+}
+
+fn object_property<'b, 'a>(
+  object: &'b ObjectExpression<'a>,
+  name: &str,
+) -> Option<&'b Expression<'a>> {
+  object
+    .properties
+    .iter()
+    .find_map(|property| match property {
+      ObjectPropertyKind::ObjectProperty(property)
+        if property_key_name(&property.key) == Some(name) =>
+      {
+        Some(&property.value)
+      }
+      _ => None,
+    })
+}
+
+fn returned_from_body<'b, 'a>(body: &'b FunctionBody<'a>) -> Option<&'b Expression<'a>> {
+  body
+    .statements
+    .iter()
+    .find_map(|statement| match statement {
+      Statement::ReturnStatement(statement) => statement.argument.as_ref(),
+      _ => None,
+    })
+}
+
+// The returned value of a getter, e.g. `function () { return _foo.bar; }`
+fn get_returned_expression<'b, 'a>(expression: &'b Expression<'a>) -> Option<&'b Expression<'a>> {
+  match expression {
+    Expression::ArrowFunctionExpression(arrow) => match arrow.get_expression() {
+      Some(expression) => Some(expression),
+      None => returned_from_body(&arrow.body),
+    },
+    Expression::FunctionExpression(function) => {
+      function.body.as_deref().and_then(returned_from_body)
+    }
+    _ => None,
+  }
+}
+
+// `__exportStar`, `_exportStar`, `tslib.__exportStar`
+fn is_export_star_helper(name: &str) -> bool {
+  let lowercase = name.to_ascii_lowercase();
+  let ends_with_export_star = lowercase.ends_with("exportstar")
+    && (lowercase.len() == "exportstar".len()
+      || lowercase.as_bytes()[lowercase.len() - "exportstar".len() - 1] == b'_');
+  ends_with_export_star || name == "_export_star" || name == "__reExport"
+}
+
+// `require('…')` that is not shadowed by a local binding
+fn require_call_source(call: &CallExpression, symbols: &SymbolTable) -> Option<Option<String>> {
+  let Expression::Identifier(callee) = &call.callee else {
+    return None;
+  };
+
+  if callee.name != "require" || reference_symbol(callee, symbols).is_some() {
+    return None;
+  }
+
+  Some(first_argument(call).and_then(get_string_constant))
+}
+
+pub(crate) fn reference_symbol(
+  ident: &IdentifierReference,
+  symbols: &SymbolTable,
+) -> Option<SymbolId> {
+  ident
+    .reference_id
+    .get()
+    .and_then(|reference_id| symbols.get_reference(reference_id).symbol_id())
+}
+
+// Only the syntax is checked, so the sources are known before the bindings are used
+fn source_from_require_syntax(expression: &Expression) -> Option<String> {
+  let Expression::CallExpression(call) = expression else {
+    return None;
+  };
+
+  if call.callee.is_specific_id("require") {
+    return first_argument(call).and_then(get_string_constant);
+  }
+
+  call
+    .arguments
+    .iter()
+    .filter_map(Argument::as_expression)
+    .find_map(source_from_require_syntax)
+}
+
+struct RequireSourcesCollector {
+  require_sources: HashMap<SymbolId, String>,
+}
+
+impl<'a> TraverseHooks<'a> for RequireSourcesCollector {
+  fn enter_variable_declarator(
+    &mut self,
+    node: &'a VariableDeclarator<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let BindingPatternKind::BindingIdentifier(ident) = &node.id.kind else {
+      return EnterAction::Continue;
+    };
+
+    let source = node.init.as_ref().and_then(source_from_require_syntax);
+    if let Some((symbol_id, source)) = ident.symbol_id.get().zip(source) {
+      self.require_sources.insert(symbol_id, source);
+    }
+
+    EnterAction::Continue
+  }
+}
+
+pub struct ExportsAndImportsCollector<'a, 's> {
+  source_text: &'s str,
+  symbols: &'a SymbolTable,
+  // Only imports are collected, the same as `collectOxcProcessorImportsFromProgram`
+  imports_only: bool,
+  namespaces: Vec<NamespaceBinding>,
+  namespace_ids: HashMap<SymbolId, usize>,
+  require_sources: HashMap<SymbolId, String>,
+  pub state: CollectedState,
+}
+
+impl<'a, 's> ExportsAndImportsCollector<'a, 's> {
+  pub fn new(
+    program: &'a Program<'a>,
+    source_text: &'s str,
+    symbols: &'a SymbolTable,
+    is_es_module: bool,
+    imports_only: bool,
+  ) -> Self {
+    let mut precollector = RequireSourcesCollector {
+      require_sources: HashMap::new(),
+    };
+    // There are no `require` calls in most of ES modules
+    if source_text.contains("require(") {
+      walk(&mut precollector, program, symbols);
+    }
+
+    Self {
+      source_text,
+      symbols,
+      imports_only,
+      namespaces: vec![],
+      namespace_ids: HashMap::new(),
+      require_sources: precollector.require_sources,
+      state: CollectedState {
+        is_es_module,
+        ..CollectedState::default()
+      },
+    }
+  }
+
+  // Unused namespaces are still imported for their side effects
+  pub fn finish(mut self) -> CollectedState {
+    if !self.imports_only {
+      for namespace in self.namespaces {
+        if !namespace.used {
+          self.state.imports.push(CollectedImport {
+            imported: "side-effect".to_string(),
+            local: namespace.local,
+            source: namespace.source,
+            kind: namespace.kind,
+          });
+        }
+      }
+    }
+
+    self.state
+  }
+
+  fn local(&self, span: Span, name: Option<&str>, symbol_id: Option<SymbolId>) -> Local {
+    Local {
+      code: span.source_text(self.source_text).to_string(),
+      span,
+      name: name.map(str::to_string),
+      symbol: symbol_id.map(|id| Symbol::new(self.symbols, id, self.symbols.get_span(id))),
+    }
+  }
+
+  fn local_from_binding(&self, ident: &BindingIdentifier) -> Local {
+    self.local(ident.span, Some(&ident.name), ident.symbol_id.get())
+  }
+
+  fn local_from_pattern(&self, pattern: &BindingPattern) -> Local {
+    match &pattern.kind {
+      BindingPatternKind::BindingIdentifier(ident) => self.local_from_binding(ident),
+      _ => self.local(pattern.span(), None, None),
+    }
+  }
+
+  fn local_from_expression(&self, expression: &Expression) -> Local {
+    match expression {
+      Expression::Identifier(ident) => self.local(
+        ident.span,
+        Some(&ident.name),
+        reference_symbol(ident, self.symbols),
+      ),
+      _ => self.local(expression.span(), None, None),
+    }
+  }
+
+  fn add_import(&mut self, imported: &str, local: Local, source: &str, kind: ImportKind) {
+    self.state.imports.push(CollectedImport {
+      imported: imported.to_string(),
+      local,
+      source: source.to_string(),
+      kind,
+    });
+  }
+
+  fn add_export(&mut self, exported: &str, local: Local) {
+    self.state.exports.insert(exported.to_string(), local);
+  }
+
+  fn add_reexport(&mut self, exported: &str, imported: &str, local: Local, source: &str) {
+    self.state.reexports.push(CollectedReexport {
+      exported: exported.to_string(),
+      imported: imported.to_string(),
+      local,
+      source: source.to_string(),
+    });
+  }
+
+  // `require('…')`, a binding initialised with it, or a helper call that wraps one of them
+  fn source_from_require_like(&self, expression: &Expression) -> Option<String> {
+    match expression {
+      Expression::Identifier(ident) => reference_symbol(ident, self.symbols)
+        .and_then(|symbol_id| self.require_sources.get(&symbol_id))
+        .cloned(),
+      Expression::CallExpression(call) => match require_call_source(call, self.symbols) {
+        Some(source) => source,
+        None => call
+          .arguments
+          .iter()
+          .filter_map(Argument::as_expression)
+          .find_map(|argument| self.source_from_require_like(argument)),
+      },
+      _ => None,
+    }
+  }
+
+  fn source_from_direct_require(&self, expression: &Expression) -> Option<String> {
+    match expression {
+      Expression::Identifier(_) => self.source_from_require_like(expression),
+      Expression::CallExpression(call) => require_call_source(call, self.symbols).flatten(),
+      _ => None,
+    }
+  }
+
+  // `_foo.bar` where `_foo` is a required module
+  fn source_from_imported_member<'b>(
+    &self,
+    expression: &'b Expression<'a>,
+  ) -> Option<(&'a str, String)> {
+    let member = expression.as_member_expression()?;
+    let source = self.source_from_require_like(member.object())?;
+    Some((member_property_name(member)?, source))
+  }
+
+  // Adds a reexport if the value comes from a required module, otherwise an export
+  fn export_value(&mut self, exported: &str, value: &Expression<'a>, local: Span) {
+    if let Some((imported, source)) = self.source_from_imported_member(value) {
+      let local = self.local(local, None, None);
+      self.add_reexport(exported, imported, local, &source);
+    } else if let Some(source) = self.source_from_direct_require(value) {
+      let symbol_id = match value {
+        Expression::Identifier(ident) => reference_symbol(ident, self.symbols),
+        _ => None,
+      };
+      let local = self.local(local, None, symbol_id);
+      self.add_reexport(exported, "*", local, &source);
+    } else {
+      let local = self.local_from_expression(value);
+      self.add_export(exported, local);
+    }
+  }
+
+  fn add_namespace(&mut self, ident: &BindingIdentifier, source: String, kind: ImportKind) {
+    let Some(symbol_id) = ident.symbol_id.get() else {
+      return;
+    };
+
+    self.namespace_ids.insert(symbol_id, self.namespaces.len());
+    self.namespaces.push(NamespaceBinding {
+      local: self.local_from_binding(ident),
+      source,
+      kind,
+      used: false,
+    });
+  }
+
+  fn import_from_variable_declarator(
+    &mut self,
+    node: &VariableDeclarator<'a>,
+    is_sync: bool,
+    source: &str,
+    kind: ImportKind,
+  ) {
+    match &node.id.kind {
+      BindingPatternKind::BindingIdentifier(ident) => {
+        let local = self.local_from_binding(ident);
+        self.add_import("*", local, source, kind);
+      }
+      BindingPatternKind::ObjectPattern(pattern) if is_sync => {
+        for (pattern, imported) in collect_destructed(pattern) {
+          let local = self.local_from_pattern(pattern);
+          self.add_import(imported, local, source, kind);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn collect_from_require_declarator(&mut self, node: &VariableDeclarator<'a>) {
+    let Some(init) = &node.init else {
+      return;
+    };
+
+    if let Some((imported, source)) = self.source_from_imported_member(init) {
+      if let BindingPatternKind::BindingIdentifier(ident) = &node.id.kind {
+        let local = self.local_from_binding(ident);
+        self.add_import(imported, local, &source, ImportKind::Cjs);
+      }
+
+      return;
+    }
+
+    let Some(source) = self.source_from_require_like(init) else {
+      return;
+    };
+
+    match &node.id.kind {
+      BindingPatternKind::BindingIdentifier(ident) => {
+        if let Some(symbol_id) = ident.symbol_id.get() {
+          self.require_sources.insert(symbol_id, source.clone());
+        }
+
+        self.add_namespace(ident, source, ImportKind::Cjs);
+      }
+      BindingPatternKind::ObjectPattern(pattern) => {
+        for (pattern, imported) in collect_destructed(pattern) {
+          let local = self.local_from_pattern(pattern);
+          self.add_import(imported, local, &source, ImportKind::Cjs);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn export_from_variable_declarator(&mut self, node: &VariableDeclarator<'a>) {
+    let local_or_init = |collector: &Self, ident: &BindingIdentifier, fallback: Span| {
+      let span = node.init.as_ref().map_or(fallback, GetSpan::span);
+      collector.local(span, Some(&ident.name), ident.symbol_id.get())
+    };
+
+    match &node.id.kind {
+      BindingPatternKind::BindingIdentifier(ident) => {
+        let local = local_or_init(self, ident, ident.span);
+        self.add_export(&ident.name, local);
+      }
+      BindingPatternKind::ObjectPattern(_) | BindingPatternKind::ArrayPattern(_) => {
+        let mut identifiers = vec![];
+        binding_identifiers(&node.id, &mut identifiers);
+        for ident in identifiers {
+          let local = local_or_init(self, ident, node.id.span());
+          self.add_export(&ident.name, local);
+        }
+      }
+      BindingPatternKind::AssignmentPattern(_) => {}
+    }
+  }
+
+  fn collect_exported_declaration(&mut self, declaration: &Declaration<'a>) {
+    let ident = match declaration {
+      Declaration::VariableDeclaration(declaration) => {
+        for declarator in &declaration.declarations {
+          self.export_from_variable_declarator(declarator);
+        }
+
+        return;
+      }
+      Declaration::TSEnumDeclaration(declaration) => Some(&declaration.id),
+      Declaration::FunctionDeclaration(function) => function.id.as_ref(),
+      Declaration::ClassDeclaration(class) => class.id.as_ref(),
+      _ => None,
+    };
+
+    if let Some(ident) = ident {
+      let local = self.local_from_binding(ident);
+      self.add_export(&ident.name, local);
+    }
+  }
+
+  // Handles `Object.defineProperty(exports, 'foo', { … })`
+  fn collect_from_define_property(&mut self, node: &CallExpression<'a>) -> bool {
+    let Some(callee) = node.callee.as_member_expression() else {
+      return false;
+    };
+
+    if !callee.object().is_specific_id("Object")
+      || member_property_name(callee) != Some("defineProperty")
+    {
+      return false;
+    }
+
+    let mut arguments = node.arguments.iter().map(Argument::as_expression);
+    let (Some(Some(target)), Some(Some(name)), Some(Some(descriptor))) =
+      (arguments.next(), arguments.next(), arguments.next())
+    else {
+      return false;
+    };
+
+    if !is_exports_object(target) {
+      return false;
+    }
+
+    let Some(exported) = get_string_constant(name) else {
+      return false;
+    };
+
+    if exported == "__esModule" {
+      return true;
+    }
+
+    let returned = match descriptor {
+      Expression::ObjectExpression(descriptor) => {
+        object_property(descriptor, "get").and_then(get_returned_expression)
+      }
+      _ => None,
+    };
+
+    match returned {
+      Some(returned) => self.export_value(&exported, returned, node.span),
+      None => {
+        let local = self.local(node.span, None, None);
+        self.add_export(&exported, local);
+      }
+    }
+
+    true
+  }
+
+  fn collect_from_helper_call(&mut self, node: &CallExpression<'a>) {
+    let Some(callee) = callee_name(&node.callee) else {
+      return;
+    };
+
+    if self.collect_from_define_property(node) {
+      return;
+    }
+
+    // `Object.keys(_foo).forEach(…)`
+    if callee == "forEach" {
+      let Some(member) = node.callee.as_member_expression() else {
+        return;
+      };
+
+      if let Expression::CallExpression(object) = member.object() {
+        let is_object_keys = object.callee.as_member_expression().is_some_and(|callee| {
+          callee.object().is_specific_id("Object") && member_property_name(callee) == Some("keys")
+        });
+        let source = first_argument(object).and_then(|argument| {
+          is_object_keys
+            .then(|| self.source_from_require_like(argument))
+            .flatten()
+        });
+        if let Some(source) = source {
+          let local = self.local(node.span, None, None);
+          self.add_reexport("*", "*", local, &source);
+        }
+      }
+
+      return;
+    }
+
+    if is_export_star_helper(callee) {
+      let source = node
+        .arguments
+        .iter()
+        .filter_map(Argument::as_expression)
+        .find_map(|argument| self.source_from_require_like(argument));
+      if let Some(source) = source {
+        let local = self.local(node.span, None, None);
+        self.add_reexport("*", "*", local, &source);
+        return;
+      }
+    }
+
+    if callee == "__export" || callee == "_export" {
+      let mut arguments = node.arguments.iter().map(Argument::as_expression);
+      let first = arguments.next().flatten();
+      if let Some(source) = first.and_then(|argument| self.source_from_require_like(argument)) {
+        let local = self.local(node.span, None, None);
+        self.add_reexport("*", "*", local, &source);
+        return;
+      }
+
+      // `__export(exports, { foo: () => _foo.bar })`
+      if let Some(Some(Expression::ObjectExpression(object))) = arguments.next() {
+        for property in &object.properties {
+          let ObjectPropertyKind::ObjectProperty(property) = property else {
+            continue;
+          };
+
+          let exported = property_key_name(&property.key);
+          let returned = get_returned_expression(&property.value);
+          if let Some((exported, returned)) = exported.zip(returned) {
+            self.export_value(exported, returned, property.span);
+          }
+        }
+      }
+    }
+  }
+
+  fn collect_from_wyw_dynamic_import(&mut self, node: &CallExpression<'a>, ctx: &TraverseCtx<'a>) {
+    if !node.callee.is_specific_id("__wyw_dynamic_import") {
+      return;
+    }
+
+    let Some(source) = first_argument(node).and_then(get_string_constant) else {
+      return;
+    };
+
+    if let Some((declarator, awaited)) = container_declarator(ctx) {
+      self.import_from_variable_declarator(declarator, awaited, &source, ImportKind::Dynamic);
+      return;
+    }
+
+    let local = self.local(node.span, None, None);
+    self.add_import("*", local, &source, ImportKind::Dynamic);
+  }
+
+  fn collect_from_namespace_reference(
+    &mut self,
+    node: &IdentifierReference<'a>,
+    ctx: &TraverseCtx<'a>,
+  ) {
+    let Some(reference_id) = node.reference_id.get() else {
+      return;
+    };
+
+    let reference = self.symbols.get_reference(reference_id);
+    // `typeof ns` and other usages in types
+    if !reference.flags().is_value() {
+      return;
+    }
+
+    let Some(symbol_id) = reference.symbol_id() else {
+      return;
+    };
+
+    let Some(&idx) = self.namespace_ids.get(&symbol_id) else {
+      return;
+    };
+
+    self.namespaces[idx].used = true;
+    let source = self.namespaces[idx].source.clone();
+    let kind = self.namespaces[idx].kind;
+
+    match ctx.parent() {
+      // `ns.foo` or `ns['foo']`
+      Some(Ancestor::Field(AnyNode::StaticMemberExpression(member), "object")) => {
+        let local = self.local(member.span, None, Some(symbol_id));
+        self.add_import(&member.property.name, local, &source, kind);
+        return;
+      }
+      Some(Ancestor::Field(AnyNode::ComputedMemberExpression(member), "object")) => {
+        if let Expression::StringLiteral(property) = &member.expression {
+          let local = self.local(member.span, None, Some(symbol_id));
+          self.add_import(&property.value, local, &source, kind);
+          return;
+        }
+      }
+      // `const { foo } = ns`
+      Some(Ancestor::Field(AnyNode::VariableDeclarator(declarator), "init")) => {
+        if let BindingPatternKind::ObjectPattern(pattern) = &declarator.id.kind {
+          for (pattern, imported) in collect_destructed(pattern) {
+            let local = self.local_from_pattern(pattern);
+            self.add_import(imported, local, &source, kind);
+          }
+
+          return;
+        }
+      }
+      _ => {}
+    }
+
+    let local = self.local(node.span, Some(&node.name), Some(symbol_id));
+    self.add_import("*", local, &source, kind);
+  }
+}
+
+// Finds `const foo = import(…)` or `const foo = await import(…)`
+fn container_declarator<'a>(ctx: &TraverseCtx<'a>) -> Option<(&'a VariableDeclarator<'a>, bool)> {
+  let mut ancestors = ctx.ancestors.iter().rev();
+  let mut parent = ancestors.next()?;
+  let mut awaited = false;
+  if let AnyNode::AwaitExpression(_) = parent.node() {
+    awaited = true;
+    parent = ancestors.next()?;
+  }
+
+  match parent.node() {
+    AnyNode::VariableDeclarator(declarator) => Some((declarator, awaited)),
+    _ => None,
+  }
+}
+
+// oxc wraps the body of `() => foo` into an expression statement
+fn is_arrow_expression_body(ctx: &TraverseCtx) -> bool {
+  let mut ancestors = ctx.ancestors.iter().rev().skip(1);
+  matches!(
+    ancestors.next().map(Ancestor::node),
+    Some(AnyNode::ArrowFunctionExpression(arrow)) if arrow.expression
+  )
+}
+
+impl<'a> TraverseHooks<'a> for ExportsAndImportsCollector<'a, '_> {
+  fn enter_import_declaration(
+    &mut self,
+    node: &'a ImportDeclaration<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if node.import_kind.is_type() {
+      return EnterAction::Continue;
+    }
+
+    let source = node.source.value.as_str();
+    let Some(specifiers) = node.specifiers.as_ref().filter(|s| !s.is_empty()) else {
+      let local = self.local(node.span, None, None);
+      self.add_import("side-effect", local, source, ImportKind::Esm);
+      return EnterAction::Continue;
+    };
+
+    for specifier in specifiers {
+      match specifier {
+        ImportDeclarationSpecifier::ImportSpecifier(specifier) => {
+          if specifier.import_kind.is_type() {
+            continue;
+          }
+
+          let local = self.local_from_binding(&specifier.local);
+          self.add_import(
+            specifier.imported.name().as_str(),
+            local,
+            source,
+            ImportKind::Esm,
+          );
+        }
+        ImportDeclarationSpecifier::ImportDefaultSpecifier(specifier) => {
+          let local = self.local_from_binding(&specifier.local);
+          self.add_import("default", local, source, ImportKind::Esm);
+        }
+        ImportDeclarationSpecifier::ImportNamespaceSpecifier(specifier) => {
+          self.add_namespace(&specifier.local, source.to_string(), ImportKind::Esm);
+        }
+      }
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_export_named_declaration(
+    &mut self,
+    node: &'a ExportNamedDeclaration<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.imports_only || node.export_kind.is_type() {
+      return EnterAction::Continue;
+    }
+
+    for specifier in &node.specifiers {
+      if specifier.export_kind.is_type() {
+        continue;
+      }
+
+      let exported = specifier.exported.name();
+      let imported = specifier.local.name();
+      if let Some(source) = &node.source {
+        let local = self.local(specifier.span, None, None);
+        self.add_reexport(&exported, &imported, local, &source.value);
+        continue;
+      }
+
+      let symbol_id = match &specifier.local {
+        ModuleExportName::IdentifierReference(ident) => reference_symbol(ident, self.symbols),
+        _ => None,
+      };
+      let local = self.local(specifier.local.span(), Some(&imported), symbol_id);
+      self.add_export(&exported, local);
+    }
+
+    if let Some(declaration) = &node.declaration {
+      self.collect_exported_declaration(declaration);
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_export_all_declaration(
+    &mut self,
+    node: &'a ExportAllDeclaration<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.imports_only || node.export_kind.is_type() {
+      return EnterAction::Continue;
+    }
+
+    let exported = node.exported.as_ref().map(ModuleExportName::name);
+    let local = self.local(node.span, None, None);
+    self.add_reexport(
+      exported.as_deref().unwrap_or("*"),
+      "*",
+      local,
+      &node.source.value,
+    );
+
+    EnterAction::Continue
+  }
+
+  fn enter_export_default_declaration(
+    &mut self,
+    node: &'a ExportDefaultDeclaration<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.imports_only {
+      return EnterAction::Continue;
+    }
+
+    let local = match &node.declaration {
+      ExportDefaultDeclarationKind::FunctionDeclaration(function) => self.local(
+        function.span,
+        None,
+        function.id.as_ref().and_then(|id| id.symbol_id.get()),
+      ),
+      ExportDefaultDeclarationKind::ClassDeclaration(class) => self.local(
+        class.span,
+        None,
+        class.id.as_ref().and_then(|id| id.symbol_id.get()),
+      ),
+      declaration => match declaration.as_expression() {
+        Some(expression) => self.local_from_expression(expression),
+        None => self.local(declaration.span(), None, None),
+      },
+    };
+    self.add_export("default", local);
+
+    EnterAction::Continue
+  }
+
+  fn enter_variable_declarator(
+    &mut self,
+    node: &'a VariableDeclarator<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    self.collect_from_require_declarator(node);
+    EnterAction::Continue
+  }
+
+  fn enter_import_expression(
+    &mut self,
+    node: &'a ImportExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.imports_only {
+      return EnterAction::Continue;
+    }
+
+    let Some(source) = get_string_constant(&node.source) else {
+      return EnterAction::Continue;
+    };
+
+    // Usages of a dynamically imported module cannot be tracked
+    if let Some((declarator, awaited)) = container_declarator(ctx) {
+      self.import_from_variable_declarator(declarator, awaited, &source, ImportKind::Dynamic);
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_call_expression(
+    &mut self,
+    node: &'a CallExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if !self.imports_only {
+      self.collect_from_wyw_dynamic_import(node, ctx);
+      self.collect_from_helper_call(node);
+    }
+
+    EnterAction::Continue
+  }
+
+  // `require('./side-effects');`
+  fn enter_expression_statement(
+    &mut self,
+    node: &'a ExpressionStatement<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.imports_only || is_arrow_expression_body(ctx) {
+      return EnterAction::Continue;
+    }
+
+    if let Some(source) = self.source_from_direct_require(&node.expression) {
+      let local = self.local_from_expression(&node.expression);
+      self.add_import("side-effect", local, &source, ImportKind::Cjs);
+    }
+
+    EnterAction::Continue
+  }
+
+  // `exports.foo = …` and `module.exports = …`
+  fn enter_assignment_expression(
+    &mut self,
+    node: &'a AssignmentExpression<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self.imports_only || node.operator != AssignmentOperator::Assign {
+      return EnterAction::Continue;
+    }
+
+    let exported = match &node.left {
+      AssignmentTarget::StaticMemberExpression(_)
+      | AssignmentTarget::ComputedMemberExpression(_) => node
+        .left
+        .as_member_expression()
+        .and_then(export_assignment_name),
+      _ => None,
+    };
+
+    if let Some(exported) = exported.filter(|exported| *exported != "__esModule") {
+      self.export_value(exported, &node.right, node.span);
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_identifier_reference(
+    &mut self,
+    node: &'a IdentifierReference<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if !self.namespace_ids.is_empty() {
+      self.collect_from_namespace_reference(node, ctx);
+    }
+
+    EnterAction::Continue
+  }
+}
+
+fn collect<'a>(
+  program: &'a Program<'a>,
+  source_text: &str,
+  symbols: &'a SymbolTable,
+  is_es_module: bool,
+  imports_only: bool,
+) -> CollectedState {
+  let mut collector =
+    ExportsAndImportsCollector::new(program, source_text, symbols, is_es_module, imports_only);
+  walk(&mut collector, program, symbols);
+  collector.finish()
+}
+
+// The same as `collectOxcExportsAndImportsFromProgram`.
+// Cached results are copied, so the caller may change them.
+pub fn collect_exports_and_imports_from_program<'a>(
+  program: &'a Program<'a>,
+  source_text: &str,
+  symbols: &'a SymbolTable,
+  is_es_module: bool,
+  cache: Option<&ModuleCache>,
+) -> CollectedState {
+  if let Some(cached) =
+    cache.and_then(|cache| cache.get_exports_and_imports(source_text, is_es_module))
+  {
+    return cached;
+  }
+
+  let state = collect(program, source_text, symbols, is_es_module, false);
+  if let Some(cache) = cache {
+    cache.insert_exports_and_imports(source_text, is_es_module, state.clone());
+  }
+
+  state
+}
+
+// The same as `collectOxcProcessorImportsFromProgram`: only imports, without side effects of unused namespaces
+pub fn collect_processor_imports_from_program<'a>(
+  program: &'a Program<'a>,
+  source_text: &str,
+  symbols: &'a SymbolTable,
+  cache: Option<&ModuleCache>,
+) -> Vec<CollectedImport> {
+  if let Some(cached) = cache.and_then(|cache| cache.get_processor_imports(source_text)) {
+    return cached;
+  }
+
+  let imports = collect(program, source_text, symbols, true, true).imports;
+  if let Some(cache) = cache {
+    cache.insert_processor_imports(source_text, imports.clone());
+  }
+
+  imports
+}
+
+// Parses the code as a script or as a module depending on its syntax
+pub fn collect_exports_and_imports(
+  filename: &str,
+  source_text: &str,
+  cache: Option<&ModuleCache>,
+) -> WywResult<CollectedState> {
+  if let Some(cached) = cache.and_then(|cache| cache.get_detected_exports_and_imports(source_text))
+  {
+    return Ok(cached);
+  }
+
+  let allocator = Allocator::default();
+  let source_type = SourceType::from_path(filename)
+    .unwrap_or_default()
+    .with_unambiguous(true);
+  let ret = Parser::new(&allocator, source_text, source_type).parse();
+  if let Some(error) = ret.errors.first() {
+    return Err(parse_error(filename, error));
+  }
+
+  let program = ret.program;
+  let is_es_module = program.source_type.is_module();
+  if let Some(cache) = cache {
+    cache.insert_detected_es_module(source_text, is_es_module);
+  }
+
+  let (symbols, _) = SemanticBuilder::new()
+    .build(&program)
+    .semantic
+    .into_symbol_table_and_scope_tree();
+
+  Ok(collect_exports_and_imports_from_program(
+    &program,
+    source_text,
+    &symbols,
+    is_es_module,
+    cache,
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn collect(source_text: &str) -> CollectedState {
+    collect_exports_and_imports("index.ts", source_text, None).unwrap()
+  }
+
+  fn imports(state: &CollectedState) -> Vec<(&str, &str)> {
+    state
+      .imports
+      .iter()
+      .map(|import| (import.imported.as_str(), import.source.as_str()))
+      .collect()
+  }
+
+  fn exports(state: &CollectedState) -> Vec<(&str, &str)> {
+    state
+      .exports
+      .iter()
+      .map(|(exported, local)| (exported.as_str(), local.code.as_str()))
+      .collect()
+  }
+
+  fn reexports(state: &CollectedState) -> Vec<(&str, &str, &str)> {
+    state
+      .reexports
+      .iter()
+      .map(|reexport| {
+        (
+          reexport.exported.as_str(),
+          reexport.imported.as_str(),
+          reexport.source.as_str(),
+        )
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_esm() {
+    let state = collect(
+      r#"
+        import def, { named as renamed } from 'a';
+        import type { Type } from 'b';
+        import { type OtherType, value } from 'c';
+        import 'side-effect';
+        const local = 1;
+        export const { x, y: [z] } = obj, w = 2;
+        export { local as exported };
+        export { token, default as other } from 'd';
+        export * from 'e';
+        export * as ns from 'f';
+        export type { Type };
+        export function fn() {}
+        export enum Enum { A }
+        export default renamed;
+      "#,
+    );
+
+    assert!(state.is_es_module);
+    assert_eq!(
+      imports(&state),
+      [
+        ("default", "a"),
+        ("named", "a"),
+        ("value", "c"),
+        ("side-effect", "side-effect")
+      ]
+    );
+    assert_eq!(
+      exports(&state),
+      [
+        ("x", "obj"),
+        ("z", "obj"),
+        ("w", "2"),
+        ("exported", "local"),
+        ("fn", "fn"),
+        ("Enum", "Enum"),
+        ("default", "renamed"),
+      ]
+    );
+    assert_eq!(
+      reexports(&state),
+      [
+        ("token", "token", "d"),
+        ("other", "default", "d"),
+        ("*", "*", "e"),
+        ("ns", "*", "f"),
+      ]
+    );
+
+    let default = &state.exports["default"];
+    assert_eq!(default.name.as_deref(), Some("renamed"));
+    assert_eq!(default.symbol, state.imports[1].local.symbol);
+    assert!(default.symbol.is_some());
+  }
+
+  #[test]
+  fn test_nested_destructed_exports() {
+    let state = collect("export const { a: { b, c: [d] }, ...rest } = o;\n");
+    assert_eq!(exports(&state), [("b", "o"), ("d", "o"), ("rest", "o")]);
+  }
+
+  #[test]
+  fn test_import_map() {
+    let state = collect(
+      r#"
+        import { alpha, beta } from './values';
+        import './side-effect';
+        export { alpha, gamma as renamed } from './values';
+        export * from './wildcard';
+      "#,
+    );
+
+    assert_eq!(
+      state.import_map(),
+      [
+        (
+          "./values".to_string(),
+          vec!["alpha".to_string(), "beta".to_string(), "gamma".to_string()]
+        ),
+        ("./side-effect".to_string(), vec!["side-effect".to_string()]),
+        ("./wildcard".to_string(), vec!["*".to_string()]),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_namespaces() {
+    let state = collect(
+      r#"
+        import * as ns from 'clear';
+        import * as destructed from 'destructed';
+        import * as dynamic from 'dynamic';
+        import * as unused from 'unused';
+        import * as typed from 'typed';
+        const { a, b: { c } } = destructed;
+        console.log(ns.named, ns['another'], dynamic[key]);
+        type T = typeof typed.value;
+      "#,
+    );
+
+    assert_eq!(
+      imports(&state),
+      [
+        ("a", "destructed"),
+        ("b", "destructed"),
+        ("named", "clear"),
+        ("another", "clear"),
+        ("*", "dynamic"),
+        ("side-effect", "unused"),
+        ("side-effect", "typed"),
+      ]
+    );
+    assert_eq!(state.imports[2].local.code, "ns.named");
+    assert_eq!(state.imports[2].local.symbol.as_ref().unwrap().name, "ns");
+  }
+
+  #[test]
+  fn test_require() {
+    let state = collect(
+      r#"
+        const def = require('default');
+        const named = require('named').named;
+        const { very: { deep } } = require('deep');
+        const { first, ...rest } = require('rest');
+        const interop = _interopRequireDefault(require('interop'));
+        require('side-effect');
+        console.log(def.default, interop, later.foo);
+        const later = require('later');
+        const notModule = (() => {
+          const require = () => ({});
+          const { dep } = require('not-a-module');
+          return dep;
+        })();
+      "#,
+    );
+
+    assert!(!state.is_es_module);
+    assert_eq!(
+      imports(&state),
+      [
+        ("named", "named"),
+        ("very", "deep"),
+        ("first", "rest"),
+        ("*", "rest"),
+        ("side-effect", "side-effect"),
+        ("default", "default"),
+        ("*", "interop"),
+        ("side-effect", "later"),
+      ]
+    );
+    assert!(state
+      .imports
+      .iter()
+      .all(|import| import.kind == ImportKind::Cjs));
+  }
+
+  #[test]
+  fn test_commonjs_exports() {
+    let state = collect(
+      r#"
+        "use strict";
+        Object.defineProperty(exports, "__esModule", { value: true });
+        exports.value = exports.ns = void 0;
+        const tslib_1 = require("tslib");
+        var _ns = _interopRequireWildcard(require("ns"));
+        var _named = require("named");
+        exports.ns = _ns;
+        exports.value = 42;
+        Object.defineProperty(exports, "getter", { get: () => 1 });
+        Object.defineProperty(exports, "token", {
+          enumerable: true,
+          get: function () {
+            return _named.token;
+          },
+        });
+        tslib_1.__exportStar(require("./star"), exports);
+        Object.keys(_named).forEach(function (key) {});
+        __export(exports, { helper: () => _named.helper, own: () => own });
+        module.exports = require("default");
+      "#,
+    );
+
+    assert_eq!(
+      exports(&state),
+      [
+        ("value", "42"),
+        ("ns", "void 0"),
+        ("getter", "1"),
+        ("own", "own")
+      ]
+    );
+    assert_eq!(
+      reexports(&state),
+      [
+        ("ns", "*", "ns"),
+        ("token", "token", "named"),
+        ("*", "*", "./star"),
+        ("*", "*", "named"),
+        ("helper", "helper", "named"),
+        ("default", "*", "default"),
+      ]
+    );
+    assert_eq!(
+      state.reexports[0].local.symbol.as_ref().unwrap().name,
+      "_ns"
+    );
+  }
+
+  #[test]
+  fn test_dynamic_imports() {
+    let state = collect(
+      r#"
+        async function load() {
+          const ns = await import('ns');
+          const { named } = await import('named');
+          const promise = import('promise');
+          const { then } = import('not-awaited');
+          import('ignored');
+          __wyw_dynamic_import('wyw');
+        }
+      "#,
+    );
+
+    assert_eq!(
+      imports(&state),
+      [
+        ("*", "ns"),
+        ("named", "named"),
+        ("*", "promise"),
+        ("*", "wyw")
+      ]
+    );
+    assert!(state
+      .imports
+      .iter()
+      .all(|import| import.kind == ImportKind::Dynamic));
+  }
+
+  #[test]
+  fn test_cache() {
+    let cache = ModuleCache::new();
+    let source_text = "import { css } from 'lib';\nimport * as ns from 'ns';\nexport { css };\n";
+    let mut first = collect_exports_and_imports("index.ts", source_text, Some(&cache)).unwrap();
+    first.dead_exports.push("poisoned".to_string());
+    first.exports.get_mut("css").unwrap().code = "poisoned".to_string();
+
+    let second = collect_exports_and_imports("index.ts", source_text, Some(&cache)).unwrap();
+    assert!(second.dead_exports.is_empty());
+    assert_eq!(second.exports["css"].code, "css");
+    assert_eq!(cache.len(), 1);
+
+    // A cached result is returned without parsing the code again
+    let mut cached = second.clone();
+    cached.dead_exports.push("cached".to_string());
+    cache.insert_exports_and_imports(source_text, true, cached);
+    let third = collect_exports_and_imports("index.ts", source_text, Some(&cache)).unwrap();
+    assert_eq!(third.dead_exports, ["cached"]);
+
+    let allocator = Allocator::default();
+    let program = Parser::new(&allocator, source_text, SourceType::mjs())
+      .parse()
+      .program;
+    let (symbols, _) = SemanticBuilder::new()
+      .build(&program)
+      .semantic
+      .into_symbol_table_and_scope_tree();
+
+    // The same code in another module mode is collected again
+    let script = collect_exports_and_imports_from_program(
+      &program,
+      source_text,
+      &symbols,
+      false,
+      Some(&cache),
+    );
+    assert!(!script.is_es_module);
+
+    let imports =
+      collect_processor_imports_from_program(&program, source_text, &symbols, Some(&cache));
+    assert_eq!(imports.len(), 1);
+    assert_eq!(
+      cache.get_processor_imports(source_text).as_deref(),
+      Some(&imports[..])
+    );
+    assert_eq!(cache.len(), 1);
+  }
+}
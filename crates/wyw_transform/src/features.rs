@@ -0,0 +1,90 @@
+use globset::{GlobBuilder, GlobMatcher};
+
+// The same as `FeatureFlag` from `@wyw-in-js/shared`: a boolean or glob patterns
+// that are checked rule by rule like .gitignore, e.g. `["**/*", "!**/vendor/**"]`
+#[derive(Clone, Debug)]
+pub enum FeatureFlag {
+  Bool(bool),
+  Patterns(Vec<(bool, GlobMatcher)>),
+}
+
+impl Default for FeatureFlag {
+  fn default() -> Self {
+    Self::Bool(false)
+  }
+}
+
+impl FeatureFlag {
+  // Invalid patterns never match
+  pub fn patterns<S: AsRef<str>>(patterns: impl IntoIterator<Item = S>) -> Self {
+    let patterns = patterns
+      .into_iter()
+      .filter_map(|pattern| {
+        let pattern = pattern.as_ref();
+        let (negated, glob) = match pattern.strip_prefix('!') {
+          Some(glob) => (true, glob),
+          None => (false, pattern),
+        };
+
+        // `*` doesn't match `/`, the same as in minimatch
+        GlobBuilder::new(glob)
+          .literal_separator(true)
+          .build()
+          .ok()
+          .map(|glob| (negated, glob.compile_matcher()))
+      })
+      .collect();
+
+    Self::Patterns(patterns)
+  }
+
+  pub fn is_enabled(&self, filename: &str) -> bool {
+    match self {
+      Self::Bool(value) => *value,
+      Self::Patterns(patterns) => patterns.iter().fold(false, |acc, (negated, matcher)| {
+        if *negated {
+          acc && !matcher.is_match(filename)
+        } else {
+          acc || matcher.is_match(filename)
+        }
+      }),
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct Features {
+  // Errors are reported as warnings and files are left untouched
+  pub soft_errors: FeatureFlag,
+  // Browser-only code is removed before evaluation
+  pub dangerous_code_remover: FeatureFlag,
+}
+
+impl Default for Features {
+  fn default() -> Self {
+    Self {
+      soft_errors: FeatureFlag::Bool(false),
+      dangerous_code_remover: FeatureFlag::Bool(true),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_feature_flag() {
+    assert!(!FeatureFlag::default().is_enabled("src/App.tsx"));
+    assert!(FeatureFlag::Bool(true).is_enabled("src/App.tsx"));
+
+    let flag = FeatureFlag::patterns(["**/*.tsx", "!**/vendor/**"]);
+    assert!(flag.is_enabled("src/App.tsx"));
+    assert!(!flag.is_enabled("src/vendor/App.tsx"));
+    assert!(!flag.is_enabled("src/index.ts"));
+
+    let flag = FeatureFlag::patterns(["src/*.ts"]);
+    assert!(flag.is_enabled("src/index.ts"));
+    assert!(!flag.is_enabled("src/deep/x.ts"));
+  }
+}
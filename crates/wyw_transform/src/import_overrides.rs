@@ -0,0 +1,233 @@
+use globset::{GlobBuilder, GlobMatcher};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+// What happens when an import reaches the fallback resolver during evaluation
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum UnknownImportPolicy {
+  Allow,
+  Error,
+  #[default]
+  Warn,
+}
+
+// The same as `ImportOverride` from `@wyw-in-js/shared`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ImportOverride {
+  // Resolves the import to another module. The `source` of the import stays intact.
+  Mock(String),
+  // Disables shaking of the imported module
+  NoShake,
+  Unknown(UnknownImportPolicy),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImportKeyKind {
+  File,
+  Package,
+}
+
+// Files are matched by their path relative to the root, packages by the specifier
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ImportKey {
+  pub key: String,
+  pub kind: ImportKeyKind,
+}
+
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+  let path = path.components().collect::<Vec<_>>();
+  let base = base.components().collect::<Vec<_>>();
+  let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+
+  let mut result = PathBuf::new();
+  for _ in common..base.len() {
+    result.push(Component::ParentDir);
+  }
+
+  result.extend(&path[common..]);
+  result
+}
+
+// `./src/theme.ts` for `<root>/src/theme.ts`
+pub fn to_canonical_file_key(resolved: &str, root: Option<&Path>) -> String {
+  let root = root
+    .map(Path::to_path_buf)
+    .or_else(|| std::env::current_dir().ok())
+    .unwrap_or_default();
+
+  let relative = relative_path(Path::new(resolved), &root)
+    .components()
+    .map(|component| component.as_os_str().to_string_lossy())
+    .collect::<Vec<_>>()
+    .join("/");
+
+  if relative.starts_with('.') {
+    relative
+  } else {
+    format!("./{relative}")
+  }
+}
+
+pub fn to_import_key(source: &str, resolved: Option<&str>, root: Option<&Path>) -> ImportKey {
+  let is_file_import = source.starts_with('.') || Path::new(source).is_absolute();
+  match resolved {
+    Some(resolved) if is_file_import => ImportKey {
+      key: to_canonical_file_key(resolved, root),
+      kind: ImportKeyKind::File,
+    },
+    _ => ImportKey {
+      key: source.to_string(),
+      kind: ImportKeyKind::Package,
+    },
+  }
+}
+
+// Wildcards make a pattern less specific
+fn pattern_specificity(pattern: &str) -> isize {
+  let mut wildcards = 0;
+  let mut escaped = false;
+  for ch in pattern.chars() {
+    if escaped {
+      escaped = false;
+    } else if ch == '\\' {
+      escaped = true;
+    } else if ch == '*' || ch == '?' {
+      wildcards += 1;
+    }
+  }
+
+  pattern.len() as isize - wildcards * 10
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ImportOverrides {
+  direct: HashMap<String, ImportOverride>,
+  // The most specific patterns go first
+  patterns: Vec<(GlobMatcher, ImportOverride)>,
+}
+
+impl ImportOverrides {
+  // Keys are import keys or glob patterns. Invalid patterns only match the same key.
+  pub fn new(overrides: impl IntoIterator<Item = (String, ImportOverride)>) -> Self {
+    let direct = overrides.into_iter().collect::<HashMap<_, _>>();
+
+    let mut patterns = direct
+      .iter()
+      .filter_map(|(pattern, value)| {
+        let glob = GlobBuilder::new(pattern)
+          .literal_separator(true)
+          .build()
+          .ok()?;
+        Some((pattern.clone(), glob.compile_matcher(), value.clone()))
+      })
+      .collect::<Vec<_>>();
+    patterns.sort_by(|(a, ..), (b, ..)| {
+      pattern_specificity(b)
+        .cmp(&pattern_specificity(a))
+        .then(b.len().cmp(&a.len()))
+        .then(a.cmp(b))
+    });
+
+    Self {
+      direct,
+      patterns: patterns
+        .into_iter()
+        .map(|(_, matcher, value)| (matcher, value))
+        .collect(),
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.direct.is_empty()
+  }
+
+  pub fn get(&self, key: &str) -> Option<&ImportOverride> {
+    self.direct.get(key).or_else(|| {
+      self
+        .patterns
+        .iter()
+        .find(|(matcher, _)| matcher.is_match(key))
+        .map(|(_, value)| value)
+    })
+  }
+}
+
+// `noShake` keeps all exports of the module
+pub fn apply_import_override_to_only(
+  only: Vec<String>,
+  value: Option<&ImportOverride>,
+) -> Vec<String> {
+  match value {
+    Some(ImportOverride::NoShake) => vec!["*".to_string()],
+    _ => only,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_import_key() {
+    let root = Path::new("/project");
+    assert_eq!(
+      to_import_key("./theme", Some("/project/src/theme.ts"), Some(root)),
+      ImportKey {
+        key: "./src/theme.ts".to_string(),
+        kind: ImportKeyKind::File,
+      }
+    );
+    assert_eq!(
+      to_import_key("../shared", Some("/shared/index.ts"), Some(root)),
+      ImportKey {
+        key: "../shared/index.ts".to_string(),
+        kind: ImportKeyKind::File,
+      }
+    );
+    assert_eq!(
+      to_import_key(
+        "@acme/ui",
+        Some("/project/node_modules/@acme/ui/index.js"),
+        Some(root)
+      ),
+      ImportKey {
+        key: "@acme/ui".to_string(),
+        kind: ImportKeyKind::Package,
+      }
+    );
+  }
+
+  #[test]
+  fn test_get() {
+    let overrides = ImportOverrides::new([
+      ("@acme/*".to_string(), ImportOverride::NoShake),
+      (
+        "@acme/ui".to_string(),
+        ImportOverride::Mock("./mocks/ui.ts".to_string()),
+      ),
+      (
+        "@acme/ui/*".to_string(),
+        ImportOverride::Unknown(UnknownImportPolicy::Allow),
+      ),
+      (
+        "./src/**/*.ts".to_string(),
+        ImportOverride::Unknown(UnknownImportPolicy::Error),
+      ),
+    ]);
+
+    assert_eq!(
+      overrides.get("@acme/ui"),
+      Some(&ImportOverride::Mock("./mocks/ui.ts".to_string()))
+    );
+    assert_eq!(
+      overrides.get("@acme/ui/button"),
+      Some(&ImportOverride::Unknown(UnknownImportPolicy::Allow))
+    );
+    assert_eq!(overrides.get("@acme/icons"), Some(&ImportOverride::NoShake));
+    assert_eq!(overrides.get("@acme/icons/star"), None);
+    assert_eq!(
+      overrides.get("./src/theme/.colors.ts"),
+      Some(&ImportOverride::Unknown(UnknownImportPolicy::Error))
+    );
+  }
+}
@@ -0,0 +1,70 @@
+use oxc::ast::ast::{
+  ImportDeclaration, ImportDeclarationSpecifier, ImportOrExportKind, Program, Statement,
+};
+use oxc_semantic::SymbolId;
+use wyw_processor::registry::{ImportBinding, Imported};
+
+// An import specifier that can bring a processor into the file
+pub struct ProcessorImport<'a> {
+  pub binding: ImportBinding,
+  pub symbol_id: SymbolId,
+  pub declaration: &'a ImportDeclaration<'a>,
+  pub specifier: &'a ImportDeclarationSpecifier<'a>,
+}
+
+fn import_declarations<'a>(
+  program: &'a Program<'a>,
+) -> impl Iterator<Item = &'a ImportDeclaration<'a>> {
+  program.body.iter().filter_map(|statement| match statement {
+    Statement::ImportDeclaration(declaration) => Some(declaration.as_ref()),
+    _ => None,
+  })
+}
+
+pub fn collect_processor_imports<'a>(program: &'a Program<'a>) -> Vec<ProcessorImport<'a>> {
+  let mut result = vec![];
+
+  for declaration in import_declarations(program) {
+    // `import type { … }` cannot be a processor
+    if declaration.import_kind == ImportOrExportKind::Type {
+      continue;
+    }
+
+    let Some(specifiers) = &declaration.specifiers else {
+      continue;
+    };
+
+    let source = declaration.source.value.to_string();
+    for specifier in specifiers {
+      let (local, imported) = match specifier {
+        ImportDeclarationSpecifier::ImportSpecifier(s) => {
+          if s.import_kind == ImportOrExportKind::Type {
+            continue;
+          }
+
+          (&s.local, Imported::Named(s.imported.name().to_string()))
+        }
+        ImportDeclarationSpecifier::ImportDefaultSpecifier(s) => (&s.local, Imported::Default),
+        ImportDeclarationSpecifier::ImportNamespaceSpecifier(s) => (&s.local, Imported::Namespace),
+      };
+
+      let Some(symbol_id) = local.symbol_id.get() else {
+        continue;
+      };
+
+      result.push(ProcessorImport {
+        binding: ImportBinding {
+          local: local.name.to_string(),
+          source: source.clone(),
+          imported,
+          resolved: None,
+        },
+        symbol_id,
+        declaration,
+        specifier,
+      });
+    }
+  }
+
+  result
+}
@@ -0,0 +1,870 @@
+pub mod barrels;
+pub mod cache;
+pub mod call_sites;
+pub mod codegen;
+pub mod commonjs;
+pub mod dangerous_code;
+pub mod edits;
+pub mod env_inlining;
+pub mod exports_and_imports;
+pub mod features;
+pub mod import_overrides;
+pub mod imports;
+pub mod preeval;
+pub mod resolver;
+pub mod shaker;
+pub mod static_plan;
+pub mod static_values;
+pub mod template_dependencies;
+
+use crate::cache::ModuleCache;
+use crate::call_sites::CallSiteCollector;
+use crate::edits::{compose, SourceEdits};
+use crate::imports::{collect_processor_imports, ProcessorImport};
+use crate::resolver::ModuleResolver;
+use crate::static_values::{ModuleLoader, StaticValueResolver};
+use crate::template_dependencies::{
+  collect_template_dependencies, collect_transitive_dependencies, ExpressionKind,
+};
+use oxc::allocator::Allocator;
+use oxc::ast::ast::{ImportDeclaration, ImportDeclarationSpecifier};
+use oxc::parser::Parser;
+use oxc::sourcemap::SourceMap;
+use oxc::span::{GetSpan, SourceType, Span};
+use oxc_semantic::{SemanticBuilder, SymbolId, SymbolTable};
+use std::collections::{HashMap, HashSet};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use wyw_processor::diagnostics::{Diagnostic, Diagnostics};
+use wyw_processor::error::{WywError, WywResult};
+use wyw_processor::registry::{ImportBinding, Imported, ProcessorRegistry, TagSource};
+use wyw_processor::static_semantics::{can_skip_file_evaluation, InterpolationKind};
+use wyw_processor::ProcessorParams;
+
+// A call site that was replaced by a processor
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcessedTag {
+  pub processor: String,
+  pub tag_source: TagSource,
+  pub span: Span,
+  pub class_name: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TransformOutput {
+  pub code: String,
+  // `None` if the code was not changed
+  pub source_map: Option<SourceMap>,
+  // CSS extracted from all processed tags
+  pub css_text: Option<String>,
+  pub processed_tags: Vec<ProcessedTag>,
+  // Resolved paths of the files that affect the result and should be watched
+  pub dependencies: Vec<String>,
+  // `true` if none of the processed tags needs evaluated values
+  pub can_skip_evaluation: bool,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+impl TransformOutput {
+  fn unchanged(source_text: &str, diagnostics: Diagnostics) -> Self {
+    Self {
+      code: source_text.to_string(),
+      source_map: None,
+      css_text: None,
+      processed_tags: vec![],
+      dependencies: vec![],
+      can_skip_evaluation: true,
+      diagnostics: diagnostics.into_vec(),
+    }
+  }
+
+  // Leaves the file untouched and reports the error as a warning (`softErrors`)
+  pub fn from_soft_error(source_text: &str, error: WywError) -> Self {
+    let mut diagnostics = Diagnostics::default();
+    diagnostics.push(error.into_warning());
+    Self::unchanged(source_text, diagnostics)
+  }
+
+  // Makes the source map point to the sources of the map supplied by a bundler
+  pub fn with_input_source_map(mut self, input_source_map: Option<&str>) -> WywResult<Self> {
+    let Some(input_source_map) = input_source_map else {
+      return Ok(self);
+    };
+
+    let input = SourceMap::from_json_string(input_source_map).map_err(|error| {
+      WywError::new(
+        "wyw/invalid-source-map",
+        format!("Cannot parse the input source map: {error}"),
+      )
+    })?;
+
+    self.source_map = Some(match &self.source_map {
+      Some(map) => compose(map, &input),
+      None => input,
+    });
+
+    Ok(self)
+  }
+}
+
+pub(crate) fn contains(outer: Span, inner: Span) -> bool {
+  outer.start <= inner.start && inner.end <= outer.end
+}
+
+// Offset of the first `ch` after `offset`
+fn find_after(source_text: &str, offset: u32, ch: char) -> Option<u32> {
+  source_text[offset as usize..]
+    .find(ch)
+    .map(|idx| offset + idx as u32)
+}
+
+// Removes the specifiers from an import declaration in place,
+// so formatting, comments and import attributes are kept
+fn rewrite_import(
+  source_text: &str,
+  declaration: &ImportDeclaration,
+  removed: &[&ImportDeclarationSpecifier],
+  edits: &mut SourceEdits,
+) {
+  let is_removed =
+    |specifier: &ImportDeclarationSpecifier| removed.iter().any(|r| r.span() == specifier.span());
+
+  let specifiers = declaration
+    .specifiers
+    .as_ref()
+    .map_or(&[][..], |specifiers| specifiers.as_slice());
+  if specifiers.iter().all(is_removed) {
+    // Remove the whole line if the declaration takes it
+    let mut span = declaration.span;
+    if source_text[span.end as usize..].starts_with('\n') {
+      span.end += 1;
+    }
+
+    edits.remove(span);
+    return;
+  }
+
+  // `import a, { b } from 'lib'` and `import a, * as b from 'lib'`
+  let (default, rest) = match specifiers.first() {
+    Some(specifier @ ImportDeclarationSpecifier::ImportDefaultSpecifier(_)) => {
+      (Some(specifier), &specifiers[1..])
+    }
+    _ => (None, specifiers),
+  };
+
+  if let (Some(default), Some(next)) = (default, rest.first()) {
+    if is_removed(default) {
+      // Up to `{` or `*` of the next clause
+      let end = match next {
+        ImportDeclarationSpecifier::ImportSpecifier(_) => {
+          find_after(source_text, default.span().end, '{')
+        }
+        _ => Some(next.span().start),
+      };
+      edits.remove(Span::new(
+        default.span().start,
+        end.unwrap_or(next.span().start),
+      ));
+    } else if rest.iter().all(is_removed) {
+      // Everything after the default import, including the braces
+      let last = rest[rest.len() - 1].span().end;
+      let end = match rest[0] {
+        ImportDeclarationSpecifier::ImportSpecifier(_) => {
+          find_after(source_text, last, '}').map(|idx| idx + 1)
+        }
+        _ => Some(last),
+      };
+      edits.remove(Span::new(default.span().end, end.unwrap_or(last)));
+      return;
+    }
+  }
+
+  // Runs of removed named specifiers together with their commas
+  let named = rest
+    .iter()
+    .filter(|specifier| matches!(specifier, ImportDeclarationSpecifier::ImportSpecifier(_)))
+    .collect::<Vec<_>>();
+  let mut idx = 0;
+  while idx < named.len() {
+    if !is_removed(named[idx]) {
+      idx += 1;
+      continue;
+    }
+
+    let start = idx;
+    while idx < named.len() && is_removed(named[idx]) {
+      idx += 1;
+    }
+
+    let span = match named.get(idx) {
+      Some(next) => Span::new(named[start].span().start, next.span().start),
+      None => Span::new(named[start - 1].span().end, named[idx - 1].span().end),
+    };
+    edits.remove(span);
+  }
+}
+
+// Imports of processors are not needed anymore if all their usages were replaced
+pub(crate) fn remove_replaced_imports(
+  source_text: &str,
+  imports: &[ProcessorImport],
+  symbols: &SymbolTable,
+  replaced_references: &HashMap<SymbolId, usize>,
+  edits: &mut SourceEdits,
+) {
+  let mut removed_specifiers = HashMap::<Span, Vec<&ProcessorImport>>::new();
+  for import in imports {
+    let references = symbols.get_resolved_reference_ids(import.symbol_id).len();
+    let replaced = replaced_references
+      .get(&import.symbol_id)
+      .copied()
+      .unwrap_or_default();
+    if replaced > 0 && replaced == references {
+      removed_specifiers
+        .entry(import.declaration.span)
+        .or_default()
+        .push(import);
+    }
+  }
+
+  for removed in removed_specifiers.values() {
+    let specifiers = removed
+      .iter()
+      .map(|import| import.specifier)
+      .collect::<Vec<_>>();
+    rewrite_import(source_text, removed[0].declaration, &specifiers, edits);
+  }
+}
+
+pub(crate) fn parse_error(
+  filename: &str,
+  diagnostic: &oxc::diagnostics::OxcDiagnostic,
+) -> WywError {
+  let error = WywError::new("wyw/parse-error", &diagnostic.message).with_filename(filename);
+  match diagnostic.labels.as_ref().and_then(|labels| labels.first()) {
+    Some(label) => error.with_span(Span::sized(label.offset() as u32, label.len() as u32)),
+    None => error,
+  }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+  if let Some(message) = payload.downcast_ref::<&str>() {
+    message
+  } else if let Some(message) = payload.downcast_ref::<String>() {
+    message
+  } else {
+    "unknown error"
+  }
+}
+
+// `tagResolver` gets the resolved path of the import, so it is resolved only if there is one.
+// Unresolvable imports are left to the resolver of the bundler.
+pub(crate) fn resolve_bindings(
+  filename: &str,
+  registry: &ProcessorRegistry,
+  resolver: &dyn ModuleResolver,
+  bindings: &mut [ImportBinding],
+) {
+  if !registry.has_tag_resolver() {
+    return;
+  }
+
+  for binding in bindings {
+    binding.resolved = resolver.resolve(filename, &binding.source).ok();
+  }
+}
+
+// Resolves only the bindings of the transformed file, imported values stay dynamic
+struct LocalLoader<'r>(&'r dyn ModuleResolver);
+
+impl ModuleResolver for LocalLoader<'_> {
+  fn resolve(&self, importer: &str, source: &str) -> WywResult<String> {
+    self.0.resolve(importer, source)
+  }
+}
+
+impl ModuleLoader for LocalLoader<'_> {
+  fn load(&self, importer: &str, source: &str) -> WywResult<(String, String)> {
+    Err(
+      WywError::new(
+        "wyw/import-not-loaded",
+        format!("{source} is not loaded during the transform"),
+      )
+      .with_filename(importer),
+    )
+  }
+}
+
+// Checks if any of the imports can bring a processor into the file
+fn imports_processors(
+  filename: &str,
+  registry: &ProcessorRegistry,
+  bindings: &[ImportBinding],
+) -> bool {
+  let lookup = |binding, imported| {
+    registry
+      .lookup_binding(binding, imported, Some(filename))
+      .is_some()
+  };
+
+  bindings.iter().any(|binding| match &binding.imported {
+    // Namespace members are resolved at the usage
+    Imported::Namespace => true,
+    Imported::Default => lookup(binding, "default"),
+    Imported::Named(name) => lookup(binding, name),
+  })
+}
+
+pub fn transform(
+  filename: &str,
+  source_text: &str,
+  registry: &ProcessorRegistry,
+  resolver: &dyn ModuleResolver,
+) -> WywResult<TransformOutput> {
+  transform_with_cache(filename, source_text, registry, resolver, None)
+}
+
+// The same as `transform`, but returns the previous result for already transformed files
+// and skips parsing of already seen modules without processors
+pub fn transform_with_cache(
+  filename: &str,
+  source_text: &str,
+  registry: &ProcessorRegistry,
+  resolver: &dyn ModuleResolver,
+  cache: Option<&ModuleCache>,
+) -> WywResult<TransformOutput> {
+  let mut diagnostics = Diagnostics::default();
+
+  if let Some(cache) = cache {
+    if let Some(output) = cache.get_output(source_text, filename) {
+      return Ok(output);
+    }
+
+    if let Some(mut bindings) = cache.get_imports(source_text) {
+      resolve_bindings(filename, registry, resolver, &mut bindings);
+      if !imports_processors(filename, registry, &bindings) {
+        return Ok(TransformOutput::unchanged(source_text, diagnostics));
+      }
+    }
+  }
+
+  let allocator = Allocator::default();
+  let source_type = SourceType::from_path(filename).unwrap_or_default();
+  let ret = Parser::new(&allocator, source_text, source_type).parse();
+  if let Some(error) = ret.errors.first() {
+    return Err(parse_error(filename, error));
+  }
+
+  let program = ret.program;
+  let semantic = SemanticBuilder::new().build(&program).semantic;
+  let (symbols, _) = semantic.into_symbol_table_and_scope_tree();
+
+  let imports = collect_processor_imports(&program);
+  let mut bindings = imports
+    .iter()
+    .map(|import| import.binding.clone())
+    .collect::<Vec<_>>();
+  if let Some(cache) = cache {
+    cache.insert_imports(source_text, bindings.clone());
+  }
+
+  resolve_bindings(filename, registry, resolver, &mut bindings);
+  if !imports_processors(filename, registry, &bindings) {
+    return Ok(TransformOutput::unchanged(source_text, diagnostics));
+  }
+
+  let defined_tags = registry.defined_tags(&bindings, Some(filename));
+
+  let mut collector =
+    CallSiteCollector::new(&defined_tags, imports.iter().map(|import| import.symbol_id));
+  wyw_traverse::walk(&mut collector, &program, &symbols);
+
+  let mut edits = SourceEdits::new(source_text);
+  let mut processed_tags = vec![];
+  let mut css_chunks = vec![];
+  let mut replaced_references = HashMap::<SymbolId, usize>::new();
+  let mut processed = vec![];
+  for (idx, call_site) in collector.call_sites.iter().enumerate() {
+    // The outer tag is replaced as a whole, together with the nested one and its reference
+    if processed_tags
+      .iter()
+      .any(|tag: &ProcessedTag| contains(tag.span, call_site.node.span))
+    {
+      *replaced_references.entry(call_site.symbol_id).or_default() += 1;
+      continue;
+    }
+
+    let params = ProcessorParams {
+      filename,
+      source_text,
+      tag_source: &call_site.tag.tag_source,
+      node: call_site.node,
+      idx,
+    };
+
+    let processor = &call_site.tag.processor;
+    let result = catch_unwind(AssertUnwindSafe(|| {
+      processor.transform(&params, &mut diagnostics)
+    }));
+    let output = match result {
+      Ok(Some(output)) => output,
+      Ok(None) => continue,
+      Err(payload) => {
+        let message = format!(
+          "Processor {} failed: {}",
+          processor.id(),
+          panic_message(payload.as_ref())
+        );
+
+        return Err(
+          WywError::new("wyw/processor-panic", message)
+            .with_filename(filename)
+            .with_span(call_site.node.span),
+        );
+      }
+    };
+
+    edits.overwrite(call_site.node.span, output.replacement);
+    processed_tags.push(ProcessedTag {
+      processor: call_site.tag.processor.id().to_string(),
+      tag_source: call_site.tag.tag_source.clone(),
+      span: call_site.node.span,
+      class_name: output.class_name,
+    });
+    css_chunks.extend(output.css_text);
+    *replaced_references.entry(call_site.symbol_id).or_default() += 1;
+    processed.push((call_site.node, processor.static_semantics()));
+  }
+
+  let templates =
+    collect_template_dependencies(processed.iter().map(|(node, _)| *node), &program, &symbols);
+  let loader = LocalLoader(resolver);
+  let static_values = StaticValueResolver::new(&loader);
+  let can_skip_evaluation = can_skip_file_evaluation(processed.iter().zip(&templates).map(
+    |((node, semantics), template)| {
+      let results =
+        static_values.resolve_expressions(filename, &program, &symbols, &node.quasi.expressions);
+      let kinds = template
+        .expressions
+        .iter()
+        .zip(results)
+        .map(|(expression, result)| match (expression.kind, result) {
+          (ExpressionKind::Literal | ExpressionKind::Function, _) => {
+            expression.kind.interpolation_kind()
+          }
+          (_, Ok(_)) => InterpolationKind::StaticBinding,
+          (_, Err(_)) => InterpolationKind::Dynamic,
+        });
+
+      (*semantics, kinds.collect::<Vec<_>>())
+    },
+  ));
+
+  // Files whose values are interpolated into the processed tags
+  let tag_symbols = collector
+    .call_sites
+    .iter()
+    .map(|call_site| call_site.symbol_id)
+    .collect::<HashSet<_>>();
+  let mut dependencies = vec![];
+  for symbol_id in collect_transitive_dependencies(&templates, &program, &symbols) {
+    if tag_symbols.contains(&symbol_id) {
+      continue;
+    }
+
+    let Some(import) = imports.iter().find(|import| import.symbol_id == symbol_id) else {
+      continue;
+    };
+
+    // Modules that cannot be resolved here are left to the bundler
+    let Ok(resolved) = resolver.resolve(filename, &import.binding.source) else {
+      continue;
+    };
+
+    let path = resolved.split(['?', '#']).next().unwrap_or_default();
+    if !dependencies.iter().any(|dependency| dependency == path) {
+      dependencies.push(path.to_string());
+    }
+  }
+
+  remove_replaced_imports(
+    source_text,
+    &imports,
+    &symbols,
+    &replaced_references,
+    &mut edits,
+  );
+
+  let (code, source_map) = if edits.is_empty() {
+    (source_text.to_string(), None)
+  } else {
+    let (code, source_map) = edits.to_code_and_map(filename);
+    (code, Some(source_map))
+  };
+
+  let output = TransformOutput {
+    code,
+    source_map,
+    css_text: (!css_chunks.is_empty()).then(|| css_chunks.join("\n")),
+    processed_tags,
+    dependencies,
+    can_skip_evaluation,
+    diagnostics: diagnostics.into_vec(),
+  };
+  if let Some(cache) = cache {
+    cache.insert_output(source_text, filename, output.clone());
+  }
+
+  Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use oxc::ast::ast::Expression;
+  use std::sync::Arc;
+  use wyw_processor::static_semantics::StaticSemantics;
+  use wyw_processor::{Processor, ProcessorOutput};
+
+  // Replaces a tag with the names of its expressions
+  struct TestProcessor;
+
+  impl Processor for TestProcessor {
+    fn id(&self) -> &str {
+      "test"
+    }
+
+    fn transform(
+      &self,
+      params: &ProcessorParams,
+      _diagnostics: &mut Diagnostics,
+    ) -> Option<ProcessorOutput> {
+      let names = params
+        .node
+        .quasi
+        .expressions
+        .iter()
+        .map(|expression| match expression {
+          Expression::Identifier(ident) => ident.name.to_string(),
+          _ => "?".to_string(),
+        })
+        .collect::<Vec<_>>();
+
+      Some(ProcessorOutput {
+        replacement: format!("{:?}", names.join(",")),
+        class_name: Some(format!("t{}", params.idx)),
+        css_text: Some(format!(".t{} {{}}", params.idx)),
+      })
+    }
+  }
+
+  // The same as `TestProcessor`, but doesn't need evaluated values
+  struct PureProcessor;
+
+  impl Processor for PureProcessor {
+    fn id(&self) -> &str {
+      "pure"
+    }
+
+    fn transform(
+      &self,
+      params: &ProcessorParams,
+      diagnostics: &mut Diagnostics,
+    ) -> Option<ProcessorOutput> {
+      TestProcessor.transform(params, diagnostics)
+    }
+
+    fn static_semantics(&self) -> StaticSemantics {
+      StaticSemantics::Pure {
+        runtime_callbacks: true,
+      }
+    }
+  }
+
+  struct PanicProcessor;
+
+  impl Processor for PanicProcessor {
+    fn id(&self) -> &str {
+      "panic"
+    }
+
+    fn transform(
+      &self,
+      _params: &ProcessorParams,
+      _diagnostics: &mut Diagnostics,
+    ) -> Option<ProcessorOutput> {
+      panic!("something went wrong");
+    }
+  }
+
+  fn registry() -> ProcessorRegistry {
+    let mut registry = ProcessorRegistry::new();
+    registry
+      .register(Arc::new(TestProcessor))
+      .add_tag("lib", "css", "test")
+      .add_tag("lib", "default", "test");
+    registry
+      .register(Arc::new(PureProcessor))
+      .add_tag("lib", "pure", "pure");
+    registry
+      .register(Arc::new(PanicProcessor))
+      .add_tag("lib", "broken", "panic");
+    registry
+  }
+
+  // Relative imports are files in the root, packages cannot be resolved
+  struct TestResolver;
+
+  impl ModuleResolver for TestResolver {
+    fn resolve(&self, _importer: &str, source: &str) -> WywResult<String> {
+      match source.strip_prefix("./") {
+        Some(name) => {
+          let (name, query) = name.split_at(name.find('?').unwrap_or(name.len()));
+          Ok(format!("/{name}.ts{query}"))
+        }
+        None => Err(WywError::new(
+          "wyw/resolve-error",
+          format!("Cannot resolve module {source}"),
+        )),
+      }
+    }
+  }
+
+  fn run(source_text: &str) -> String {
+    let output = transform("index.ts", source_text, &registry(), &TestResolver).unwrap();
+    assert!(output.diagnostics.is_empty());
+    output.code
+  }
+
+  #[test]
+  fn test_transform() {
+    let code = run("import { css } from 'lib';\nexport const a = css`${b}${c}`;\n");
+    assert_eq!(code, "export const a = \"b,c\";\n");
+  }
+
+  #[test]
+  fn test_keeps_used_imports() {
+    let code =
+      run("import { css, cx } from 'lib';\nexport const a = css``;\nexport const b = css;\n");
+    assert_eq!(
+      code,
+      "import { css, cx } from 'lib';\nexport const a = \"\";\nexport const b = css;\n"
+    );
+
+    let code = run("import def, { css, cx } from 'lib';\nexport const a = css``;\n");
+    assert_eq!(
+      code,
+      "import def, { cx } from 'lib';\nexport const a = \"\";\n"
+    );
+  }
+
+  #[test]
+  fn test_rewrites_imports_in_place() {
+    let code =
+      run("import {\n  css,\n  cx,\n} from 'lib' with { type: 'js' };\nexport const a = css``;\n");
+    assert_eq!(
+      code,
+      "import {\n  cx,\n} from 'lib' with { type: 'js' };\nexport const a = \"\";\n"
+    );
+
+    let code = run("import def, { cx, css } from 'lib';\nexport const a = css``;\n");
+    assert_eq!(
+      code,
+      "import def, { cx } from 'lib';\nexport const a = \"\";\n"
+    );
+
+    let code = run("import css, { cx } from 'lib';\nexport const a = css``;\n");
+    assert_eq!(code, "import { cx } from 'lib';\nexport const a = \"\";\n");
+
+    let code = run("import def, { css } from 'lib';\nexport const a = css``;\n");
+    assert_eq!(code, "import def from 'lib';\nexport const a = \"\";\n");
+  }
+
+  #[test]
+  fn test_nested_call_sites() {
+    let source_text = "import { css } from 'lib';\nexport const a = css`${css`${b}`}`;\n";
+    let output = transform("index.ts", source_text, &registry(), &TestResolver).unwrap();
+    assert_eq!(output.code, "export const a = \"?\";\n");
+    assert_eq!(output.processed_tags.len(), 1);
+    assert_eq!(output.css_text.as_deref(), Some(".t0 {}"));
+  }
+
+  #[test]
+  fn test_tag_resolver_gets_resolved_source() {
+    let mut registry = ProcessorRegistry::new().with_tag_resolver(Arc::new(|_, _, meta| {
+      (meta.source_file == Some("/src/index.ts") && meta.resolved_source == Some("/styles.ts"))
+        .then(|| "test".to_string())
+    }));
+    registry.register(Arc::new(TestProcessor));
+
+    let source_text = "import { css } from './styles';\nexport const a = css`${b}`;\n";
+    let output = transform("/src/index.ts", source_text, &registry, &TestResolver).unwrap();
+    assert_eq!(output.code, "export const a = \"b\";\n");
+
+    // Packages cannot be resolved, so there is no resolved source
+    let source_text = "import { css } from 'styles';\nexport const a = css`${b}`;\n";
+    let output = transform("/src/index.ts", source_text, &registry, &TestResolver).unwrap();
+    assert!(output.processed_tags.is_empty());
+  }
+
+  #[test]
+  fn test_namespace_and_aliases() {
+    let code = run("import * as lib from 'lib';\nexport const a = lib.css`${x}`;\n");
+    assert_eq!(code, "export const a = \"x\";\n");
+
+    let code = run("import { css as styles } from 'lib';\nexport const a = styles``;\n");
+    assert_eq!(code, "export const a = \"\";\n");
+  }
+
+  #[test]
+  fn test_ignores_shadowed_and_unknown_tags() {
+    let source_text =
+      "import { css } from 'lib';\nfunction f(css) { return css``; }\nexport const a = other``;\n";
+    assert_eq!(run(source_text), source_text);
+
+    let source_text = "import { css } from 'other-lib';\nexport const a = css``;\n";
+    assert_eq!(run(source_text), source_text);
+  }
+
+  #[test]
+  fn test_processed_tags() {
+    let source_text =
+      "import { css } from 'lib';\nexport const a = css``;\nexport const b = css``;\n";
+    let output = transform("index.ts", source_text, &registry(), &TestResolver).unwrap();
+    let class_names = output
+      .processed_tags
+      .iter()
+      .map(|tag| tag.class_name.as_deref())
+      .collect::<Vec<_>>();
+    assert_eq!(class_names, [Some("t0"), Some("t1")]);
+    assert_eq!(
+      output.processed_tags[0].tag_source,
+      TagSource::new("lib", "css")
+    );
+    assert_eq!(output.css_text.as_deref(), Some(".t0 {}\n.t1 {}"));
+  }
+
+  #[test]
+  fn test_can_skip_evaluation() {
+    let can_skip = |source_text: &str| {
+      transform("index.ts", source_text, &registry(), &TestResolver)
+        .unwrap()
+        .can_skip_evaluation
+    };
+
+    assert!(can_skip("export const a = 1;\n"));
+    assert!(can_skip(
+      "import { pure } from 'lib';\nexport const a = pure`${1}${() => 2}`;\n"
+    ));
+    assert!(!can_skip(
+      "import { pure } from 'lib';\nexport const a = pure`${b}`;\n"
+    ));
+    assert!(can_skip(
+      "import { pure } from 'lib';\nconst size = 2;\nconst theme = { gap: size * 4 };\n\
+        export const a = pure`${size}px ${theme.gap}px`;\n"
+    ));
+    assert!(!can_skip(
+      "import { pure } from 'lib';\nimport { size } from './size';\n\
+        const gap = size * 4;\nexport const a = pure`${gap}px`;\n"
+    ));
+    assert!(!can_skip(
+      "import { pure } from 'lib';\nlet size = 2;\nsize += 1;\nexport const a = pure`${size}px`;\n"
+    ));
+    assert!(!can_skip(
+      "import { css } from 'lib';\nexport const a = css`${1}`;\n"
+    ));
+  }
+
+  #[test]
+  fn test_dependencies() {
+    let source_text = "import { css } from 'lib';\nimport { colors } from './theme';\n\
+      import { size } from './size';\nimport { unused } from './unused';\n\
+      export const a = css`${colors.red}${size}${css`${size}`}`;\n";
+    let output = transform("index.ts", source_text, &registry(), &TestResolver).unwrap();
+    assert_eq!(output.dependencies, ["/theme.ts", "/size.ts"]);
+
+    // Imports that are used through local variables
+    let source_text = "import { css } from 'lib';\nimport { colors } from './theme';\n\
+      import { base } from './size?raw';\nimport pkg from 'pkg';\n\
+      const { red } = colors;\nconst gap = base * 2 + pkg;\nconst color = red;\n\
+      export const a = css`${color}${gap}`;\n";
+    let output = transform("index.ts", source_text, &registry(), &TestResolver).unwrap();
+    assert_eq!(output.dependencies, ["/theme.ts", "/size.ts"]);
+  }
+
+  #[test]
+  fn test_cache() {
+    let registry = registry();
+    let cache = ModuleCache::new();
+    let with_tags = "import { css } from 'lib';\nexport const a = css``;\n";
+    let without_tags = "import { cx } from 'lib';\nexport const a = cx;\n";
+
+    for _ in 0..2 {
+      for source_text in [with_tags, without_tags] {
+        let cached = transform_with_cache(
+          "index.ts",
+          source_text,
+          &registry,
+          &TestResolver,
+          Some(&cache),
+        );
+        let uncached = transform("index.ts", source_text, &registry, &TestResolver);
+        assert_eq!(cached.unwrap().code, uncached.unwrap().code);
+      }
+    }
+
+    assert_eq!(cache.len(), 2);
+    let imports = cache.get_imports(without_tags).unwrap();
+    assert_eq!(imports[0].imported, Imported::Named("cx".into()));
+    assert!(cache.get_output(without_tags, "index.ts").is_none());
+
+    // Files with processors are not transformed again
+    let output = cache.get_output(with_tags, "index.ts").unwrap();
+    assert_eq!(output.processed_tags.len(), 1);
+    assert!(cache.get_output(with_tags, "other.ts").is_none());
+  }
+
+  #[test]
+  fn test_source_map() {
+    let source_text = "import { css } from 'lib';\nexport const a = css``;\n";
+    let output = transform("index.ts", source_text, &registry(), &TestResolver).unwrap();
+    let map = output.source_map.unwrap();
+    assert_eq!(map.get_source(0), Some("index.ts"));
+    assert_eq!(map.get_token(0).unwrap().get_src_line(), 1);
+
+    let output = transform(
+      "index.ts",
+      "export const a = 1;\n",
+      &registry(),
+      &TestResolver,
+    )
+    .unwrap();
+    assert!(output.source_map.is_none());
+
+    let error = output.with_input_source_map(Some("{")).unwrap_err();
+    assert_eq!(error.code, "wyw/invalid-source-map");
+  }
+
+  #[test]
+  fn test_parse_errors() {
+    let error = transform("index.ts", "const a = ;", &registry(), &TestResolver).unwrap_err();
+    assert_eq!(error.code, "wyw/parse-error");
+    assert_eq!(error.filename.as_deref(), Some("index.ts"));
+    assert_eq!(error.span, Some(Span::new(10, 11)));
+
+    let output = TransformOutput::from_soft_error("const a = ;", error);
+    assert_eq!(output.code, "const a = ;");
+    assert!(!output.diagnostics[0].is_error());
+  }
+
+  #[test]
+  fn test_processor_panics() {
+    let source_text = "import { broken } from 'lib';\nexport const a = broken``;\n";
+    let error = transform("index.ts", source_text, &registry(), &TestResolver).unwrap_err();
+    assert_eq!(error.code, "wyw/processor-panic");
+    assert_eq!(
+      error.message,
+      "Processor panic failed: something went wrong"
+    );
+    assert_eq!(error.span, Some(Span::new(47, 55)));
+  }
+}
@@ -0,0 +1,1067 @@
+use crate::call_sites::CallSiteCollector;
+use crate::dangerous_code::{collect_dangerous_code, DangerousCodeOptions, RemovedCode};
+use crate::edits::SourceEdits;
+use crate::exports_and_imports::reference_symbol;
+use crate::features::Features;
+use crate::imports::collect_processor_imports;
+use crate::resolver::ModuleResolver;
+use crate::static_values::quote;
+use crate::{
+  contains, panic_message, parse_error, remove_replaced_imports, resolve_bindings, ProcessedTag,
+};
+use oxc::allocator::Allocator;
+use oxc::ast::ast::{
+  Argument, BinaryOperator, BindingPatternKind, CallExpression, Expression, IdentifierReference,
+  ImportExpression, Program, StaticMemberExpression, VariableDeclarationKind, VariableDeclarator,
+};
+use oxc::parser::Parser;
+use oxc::span::{GetSpan, SourceType, Span};
+use oxc_semantic::{ScopeTree, SemanticBuilder, SymbolId, SymbolTable};
+use std::collections::{HashMap, HashSet};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use wyw_processor::diagnostics::{Diagnostic, Diagnostics};
+use wyw_processor::error::{WywError, WywResult};
+use wyw_processor::registry::ProcessorRegistry;
+use wyw_processor::ProcessorParams;
+use wyw_traverse::{walk, EnterAction, TraverseCtx, TraverseHooks};
+
+#[derive(Debug)]
+pub struct PreevalOutput {
+  pub code: String,
+  // Helpers with interpolations of processed templates, exported through `__wywPreval`
+  pub dependency_names: Vec<String>,
+  pub processed_tags: Vec<ProcessedTag>,
+  // Browser-only code removed by `dangerousCodeRemover`
+  pub removed: Vec<RemovedCode>,
+  pub dynamic_imports: Vec<DynamicImport>,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+fn parse<'a>(
+  allocator: &'a Allocator,
+  filename: &str,
+  source_text: &'a str,
+) -> WywResult<(Program<'a>, SymbolTable, ScopeTree)> {
+  let source_type = SourceType::from_path(filename)
+    .unwrap_or_default()
+    .with_unambiguous(true);
+  let ret = Parser::new(allocator, source_text, source_type).parse();
+  if let Some(error) = ret.errors.first() {
+    return Err(parse_error(filename, error));
+  }
+
+  let program = ret.program;
+  let (symbols, scopes) = SemanticBuilder::new()
+    .build(&program)
+    .semantic
+    .into_symbol_table_and_scope_tree();
+
+  Ok((program, symbols, scopes))
+}
+
+// Literals are known without evaluation and don't need helpers
+fn is_literal(expression: &Expression) -> bool {
+  match expression.get_inner_expression() {
+    Expression::StringLiteral(_) | Expression::NumericLiteral(_) => true,
+    Expression::TemplateLiteral(template) => template.expressions.is_empty(),
+    _ => false,
+  }
+}
+
+// `_exp`, `_exp2`, … that don't clash with existing bindings
+struct HelperNames<'s> {
+  taken: HashSet<&'s str>,
+  counter: usize,
+}
+
+impl HelperNames<'_> {
+  fn next(&mut self) -> String {
+    loop {
+      self.counter += 1;
+      let name = match self.counter {
+        1 => "_exp".to_string(),
+        counter => format!("_exp{counter}"),
+      };
+
+      if !self.taken.contains(name.as_str()) {
+        return name;
+      }
+    }
+  }
+}
+
+// References to bindings that are not declared at the top level of the module
+struct LocalReferences<'s> {
+  scopes: &'s ScopeTree,
+  // The span of the reference and the span of the declaration
+  references: Vec<(Span, Span)>,
+}
+
+impl LocalReferences<'_> {
+  // A reference that stops working when `expression` is moved to the top level
+  fn find_unhoistable(&self, expression: Span) -> Option<Span> {
+    self
+      .references
+      .iter()
+      .find(|(reference, decl)| contains(expression, *reference) && !contains(expression, *decl))
+      .map(|(reference, _)| *reference)
+  }
+}
+
+impl<'a> TraverseHooks<'a> for LocalReferences<'_> {
+  fn enter_identifier_reference(
+    &mut self,
+    node: &'a IdentifierReference<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let symbols = ctx.symbols();
+    if let Some(symbol_id) = reference_symbol(node, symbols) {
+      if symbols.get_scope_id(symbol_id) != self.scopes.root_scope_id() {
+        self
+          .references
+          .push((node.span, symbols.get_span(symbol_id)));
+      }
+    }
+
+    EnterAction::Continue
+  }
+}
+
+struct ReplacedCallSite {
+  span: Span,
+  replacement: String,
+  // Names of the helpers and spans of the interpolations that they return
+  helpers: Vec<(String, Span)>,
+  // The call site is a part of an interpolation that is moved into a helper
+  is_copied: bool,
+  tag: ProcessedTag,
+}
+
+// The code of `span` with the outermost call sites in it replaced
+fn replaced_source(span: Span, source_text: &str, replaced: &[ReplacedCallSite]) -> String {
+  let mut result = String::new();
+  let mut last = span.start;
+  for call_site in replaced {
+    if !contains(span, call_site.span) || call_site.span.start < last {
+      continue;
+    }
+
+    result.push_str(Span::new(last, call_site.span.start).source_text(source_text));
+    result.push_str(&call_site.replacement);
+    last = call_site.span.end;
+  }
+
+  result.push_str(Span::new(last, span.end).source_text(source_text));
+  result
+}
+
+// Processor call sites are replaced with their eval-time values,
+// and their interpolations are moved to helpers
+fn replace_call_sites(
+  filename: &str,
+  source_text: &str,
+  registry: &ProcessorRegistry,
+  resolver: &dyn ModuleResolver,
+  features: &Features,
+) -> WywResult<(String, PreevalOutput)> {
+  let mut diagnostics = Diagnostics::default();
+  let allocator = Allocator::default();
+  let (program, symbols, scopes) = parse(&allocator, filename, source_text)?;
+
+  let imports = collect_processor_imports(&program);
+  let mut bindings = imports
+    .iter()
+    .map(|import| import.binding.clone())
+    .collect::<Vec<_>>();
+  resolve_bindings(filename, registry, resolver, &mut bindings);
+  let defined_tags = registry.defined_tags(&bindings, Some(filename));
+
+  let mut collector =
+    CallSiteCollector::new(&defined_tags, imports.iter().map(|import| import.symbol_id));
+  walk(&mut collector, &program, &symbols);
+
+  let removed = if features.dangerous_code_remover.is_enabled(filename) {
+    let options = DangerousCodeOptions {
+      ignored_spans: collector
+        .call_sites
+        .iter()
+        .map(|call_site| call_site.node.span)
+        .collect(),
+      preserve_import_meta_env: true,
+    };
+    collect_dangerous_code(&program, source_text, &symbols, &options)
+  } else {
+    vec![]
+  };
+
+  let mut dynamic_imports = DynamicImportCollector {
+    removed: &removed,
+    constants: StringConstants::default(),
+    imports: vec![],
+  };
+  walk(&mut dynamic_imports, &program, &symbols);
+  for import in &dynamic_imports.imports {
+    if let Some(diagnostic) = dynamic_import_diagnostic(import) {
+      diagnostics.push(diagnostic);
+    }
+  }
+  let dynamic_imports = dynamic_imports.imports;
+
+  let mut edits = SourceEdits::new(source_text);
+  for removed in &removed {
+    edits.overwrite(removed.span, removed.value.clone());
+  }
+
+  let mut local_references = LocalReferences {
+    scopes: &scopes,
+    references: vec![],
+  };
+  walk(&mut local_references, &program, &symbols);
+
+  let mut helper_names = HelperNames {
+    taken: symbols.names.iter().map(|name| name.as_str()).collect(),
+    counter: 0,
+  };
+  let mut replaced: Vec<ReplacedCallSite> = vec![];
+  let mut replaced_references = HashMap::<SymbolId, usize>::new();
+  // Interpolations that are moved into helpers
+  let mut copied_spans = vec![];
+  for (idx, call_site) in collector.call_sites.iter().enumerate() {
+    let span = call_site.node.span;
+    if removed.iter().any(|removed| contains(removed.span, span)) {
+      continue;
+    }
+
+    // Literal interpolations can't contain call sites, so the rest of the nested ones
+    // are evaluated as a part of the outer one
+    let is_copied = copied_spans.iter().any(|copied| contains(*copied, span));
+    if !is_copied && replaced.iter().any(|outer| contains(outer.span, span)) {
+      *replaced_references.entry(call_site.symbol_id).or_default() += 1;
+      continue;
+    }
+
+    // Helpers are declared at the top level, where local bindings are not available
+    let interpolations = call_site
+      .node
+      .quasi
+      .expressions
+      .iter()
+      .filter(|expression| !is_literal(expression));
+    let unhoistable = interpolations
+      .clone()
+      .find_map(|expression| local_references.find_unhoistable(expression.span()));
+    if let Some(reference) = unhoistable {
+      diagnostics.push(
+        Diagnostic::warning(
+          "wyw/local-interpolation",
+          "The interpolation refers to a binding that is not declared at the top level \
+           of the module, the call site is kept unchanged",
+          span,
+        )
+        .with_secondary(reference, "declared outside of the top level"),
+      );
+      continue;
+    }
+
+    let params = ProcessorParams {
+      filename,
+      source_text,
+      tag_source: &call_site.tag.tag_source,
+      node: call_site.node,
+      idx,
+    };
+
+    let processor = &call_site.tag.processor;
+    let result = catch_unwind(AssertUnwindSafe(|| {
+      processor.evaltime_replacement(&params, &mut diagnostics)
+    }));
+    let replacement = match result {
+      Ok(Some(replacement)) => replacement,
+      Ok(None) => continue,
+      Err(payload) => {
+        let message = format!(
+          "Processor {} failed: {}",
+          processor.id(),
+          panic_message(payload.as_ref())
+        );
+
+        return Err(
+          WywError::new("wyw/processor-panic", message)
+            .with_filename(filename)
+            .with_span(span),
+        );
+      }
+    };
+
+    let helpers = interpolations
+      .map(|expression| (helper_names.next(), expression.span()))
+      .collect::<Vec<_>>();
+    copied_spans.extend(helpers.iter().map(|(_, span)| *span));
+    *replaced_references.entry(call_site.symbol_id).or_default() += 1;
+    replaced.push(ReplacedCallSite {
+      span,
+      replacement,
+      helpers,
+      is_copied,
+      tag: ProcessedTag {
+        processor: processor.id().to_string(),
+        tag_source: call_site.tag.tag_source.clone(),
+        span,
+        class_name: None,
+      },
+    });
+  }
+
+  // Copied interpolations get the replacements of their call sites as well
+  let mut dependency_names = vec![];
+  for call_site in &replaced {
+    let mut helpers = String::new();
+    for (name, span) in &call_site.helpers {
+      let code = replaced_source(*span, source_text, &replaced);
+      helpers.push_str(&format!("const {name} = /*#__PURE__*/ () => ({code});\n"));
+      dependency_names.push(name.clone());
+    }
+
+    // Helpers go before the top-level statement with the call site
+    let statement = program
+      .body
+      .iter()
+      .find(|statement| contains(statement.span(), call_site.span));
+    if let (Some(statement), false) = (statement, helpers.is_empty()) {
+      edits.insert(statement.span().start, helpers);
+    }
+
+    if !call_site.is_copied {
+      edits.overwrite(call_site.span, call_site.replacement.clone());
+    }
+  }
+  let processed_tags = replaced
+    .into_iter()
+    .map(|call_site| call_site.tag)
+    .collect();
+
+  remove_replaced_imports(
+    source_text,
+    &imports,
+    &symbols,
+    &replaced_references,
+    &mut edits,
+  );
+
+  let output = PreevalOutput {
+    code: String::new(),
+    dependency_names,
+    processed_tags,
+    removed,
+    dynamic_imports,
+    diagnostics: diagnostics.into_vec(),
+  };
+
+  Ok((edits.to_code(), output))
+}
+
+fn is_file_like(specifier: &str) -> bool {
+  specifier.starts_with('.') || specifier.starts_with('/') || specifier.starts_with("file:")
+}
+
+// String literals, templates and concatenations with them
+fn is_string_like(expression: &Expression) -> bool {
+  match expression.get_inner_expression() {
+    Expression::StringLiteral(_) | Expression::TemplateLiteral(_) => true,
+    Expression::BinaryExpression(binary) if binary.operator == BinaryOperator::Addition => {
+      is_string_like(&binary.left) || is_string_like(&binary.right)
+    }
+    Expression::CallExpression(call) => call
+      .callee
+      .as_member_expression()
+      .filter(|member| member.static_property_name() == Some("concat"))
+      .is_some_and(|member| is_string_like(member.object())),
+    _ => false,
+  }
+}
+
+// The argument of `import(…)`. Template literals are turned into concatenations.
+fn dynamic_import_argument(expression: &Expression, source_text: &str) -> String {
+  let expression = match is_string_like(expression) {
+    true => expression.get_inner_expression(),
+    false => expression,
+  };
+
+  let Expression::TemplateLiteral(template) = expression else {
+    return expression.span().source_text(source_text).to_string();
+  };
+
+  if template.expressions.is_empty() {
+    return template.span.source_text(source_text).to_string();
+  }
+
+  let mut parts = vec![];
+  for (idx, quasi) in template.quasis.iter().enumerate() {
+    let cooked = quasi.value.cooked.as_ref().unwrap_or(&quasi.value.raw);
+    if !cooked.is_empty() {
+      parts.push(quote(cooked));
+    }
+
+    if let Some(expression) = template.expressions.get(idx) {
+      parts.push(expression.span().source_text(source_text).to_string());
+    }
+  }
+
+  parts.join(" + ")
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DynamicImportKind {
+  // `import("./foo")` or a specifier built from constants
+  Literal,
+  // `import(`./locales/${name}`)`, only the beginning of the specifier is known
+  StaticPrefix,
+  Dynamic,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynamicImport {
+  pub span: Span,
+  pub kind: DynamicImportKind,
+  // The whole specifier of literal imports or the known prefix
+  pub specifier: Option<String>,
+}
+
+// String constants that can be used as `require` and `import()` specifiers
+#[derive(Default)]
+struct StringConstants<'a> {
+  constants: HashMap<SymbolId, &'a Expression<'a>>,
+}
+
+impl<'a> StringConstants<'a> {
+  fn collect(&mut self, node: &'a VariableDeclarator<'a>) {
+    if let (
+      VariableDeclarationKind::Const,
+      BindingPatternKind::BindingIdentifier(ident),
+      Some(init),
+    ) = (node.kind, &node.id.kind, &node.init)
+    {
+      if let Some(symbol_id) = ident.symbol_id.get() {
+        self.constants.insert(symbol_id, init);
+      }
+    }
+  }
+
+  fn static_string(
+    &self,
+    expression: &Expression,
+    symbols: &SymbolTable,
+    depth: usize,
+  ) -> Option<String> {
+    if depth > 16 {
+      return None;
+    }
+
+    match expression.get_inner_expression() {
+      Expression::StringLiteral(literal) => Some(literal.value.to_string()),
+      Expression::TemplateLiteral(template) => {
+        let mut result = String::new();
+        for (idx, quasi) in template.quasis.iter().enumerate() {
+          result.push_str(quasi.value.cooked.as_ref()?);
+          if let Some(expression) = template.expressions.get(idx) {
+            result.push_str(&self.static_string(expression, symbols, depth + 1)?);
+          }
+        }
+
+        Some(result)
+      }
+      Expression::Identifier(ident) => {
+        let init = self.constants.get(&reference_symbol(ident, symbols)?)?;
+        self.static_string(init, symbols, depth + 1)
+      }
+      Expression::BinaryExpression(binary) if binary.operator == BinaryOperator::Addition => {
+        let left = self.static_string(&binary.left, symbols, depth + 1)?;
+        let right = self.static_string(&binary.right, symbols, depth + 1)?;
+        Some(left + &right)
+      }
+      _ => None,
+    }
+  }
+
+  // The known beginning of a string that is built at runtime
+  fn static_prefix(&self, expression: &Expression, symbols: &SymbolTable) -> String {
+    if let Some(value) = self.static_string(expression, symbols, 0) {
+      return value;
+    }
+
+    match expression.get_inner_expression() {
+      Expression::TemplateLiteral(template) => {
+        let mut result = String::new();
+        for (idx, quasi) in template.quasis.iter().enumerate() {
+          let Some(cooked) = &quasi.value.cooked else {
+            break;
+          };
+          result.push_str(cooked);
+
+          let value = template
+            .expressions
+            .get(idx)
+            .and_then(|expression| self.static_string(expression, symbols, 0));
+          match value {
+            Some(value) => result.push_str(&value),
+            None => break,
+          }
+        }
+
+        result
+      }
+      Expression::BinaryExpression(binary) if binary.operator == BinaryOperator::Addition => {
+        match self.static_string(&binary.left, symbols, 0) {
+          Some(left) => left + &self.static_prefix(&binary.right, symbols),
+          None => self.static_prefix(&binary.left, symbols),
+        }
+      }
+      Expression::CallExpression(call) => call
+        .callee
+        .as_member_expression()
+        .filter(|member| member.static_property_name() == Some("concat"))
+        .map(|member| self.static_prefix(member.object(), symbols))
+        .unwrap_or_default(),
+      _ => String::new(),
+    }
+  }
+
+  fn classify(
+    &self,
+    node: &ImportExpression,
+    symbols: &SymbolTable,
+  ) -> (DynamicImportKind, Option<String>) {
+    if let Some(specifier) = self.static_string(&node.source, symbols, 0) {
+      return (DynamicImportKind::Literal, Some(specifier));
+    }
+
+    let prefix = self.static_prefix(&node.source, symbols);
+    match prefix.is_empty() {
+      true => (DynamicImportKind::Dynamic, None),
+      false => (DynamicImportKind::StaticPrefix, Some(prefix)),
+    }
+  }
+}
+
+// Dynamic imports of the original code, the evaluator can't resolve non-literal ones in advance
+struct DynamicImportCollector<'a, 'r> {
+  removed: &'r [RemovedCode],
+  constants: StringConstants<'a>,
+  imports: Vec<DynamicImport>,
+}
+
+impl<'a> TraverseHooks<'a> for DynamicImportCollector<'a, '_> {
+  fn enter_variable_declarator(
+    &mut self,
+    node: &'a VariableDeclarator<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    self.constants.collect(node);
+    EnterAction::Continue
+  }
+
+  fn enter_import_expression(
+    &mut self,
+    node: &'a ImportExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if self
+      .removed
+      .iter()
+      .any(|removed| contains(removed.span, node.span))
+    {
+      return EnterAction::Continue;
+    }
+
+    let (kind, specifier) = self.constants.classify(node, ctx.symbols());
+    self.imports.push(DynamicImport {
+      span: node.span,
+      kind,
+      specifier,
+    });
+
+    EnterAction::Continue
+  }
+}
+
+fn dynamic_import_diagnostic(import: &DynamicImport) -> Option<Diagnostic> {
+  let message = match (import.kind, &import.specifier) {
+    (DynamicImportKind::Literal, _) => return None,
+    (DynamicImportKind::StaticPrefix, Some(prefix)) => format!(
+      "The specifier of `import()` is only known to start with {prefix:?}, \
+       the module will be resolved during evaluation"
+    ),
+    _ => "The specifier of `import()` is not known before evaluation, \
+          the module will be resolved during evaluation"
+      .to_string(),
+  };
+
+  Some(Diagnostic::warning(
+    "wyw/dynamic-import",
+    message,
+    import.span,
+  ))
+}
+
+// `import.meta.env` → `__wyw_import_meta_env`. It runs before `SyntaxRewriter`,
+// which copies specifiers of `import()` with the replaced code.
+struct ImportMetaEnvRewriter<'s> {
+  edits: SourceEdits<'s>,
+}
+
+impl<'a> TraverseHooks<'a> for ImportMetaEnvRewriter<'_> {
+  fn enter_static_member_expression(
+    &mut self,
+    node: &'a StaticMemberExpression<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let Expression::MetaProperty(meta) = &node.object else {
+      return EnterAction::Continue;
+    };
+
+    if meta.meta.name == "import" && meta.property.name == "meta" && node.property.name == "env" {
+      self.edits.overwrite(node.span, "__wyw_import_meta_env");
+      return EnterAction::Ignore;
+    }
+
+    EnterAction::Continue
+  }
+}
+
+// Rewrites syntax that the evaluator cannot run as is
+struct SyntaxRewriter<'a, 's> {
+  source_text: &'s str,
+  constants: StringConstants<'a>,
+  edits: SourceEdits<'s>,
+}
+
+impl<'a> TraverseHooks<'a> for SyntaxRewriter<'a, '_> {
+  fn enter_variable_declarator(
+    &mut self,
+    node: &'a VariableDeclarator<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    self.constants.collect(node);
+    EnterAction::Continue
+  }
+
+  // Literal imports become synchronous requires, so the evaluator resolves them as usual.
+  // The promise keeps errors asynchronous, as they are with `import()`.
+  fn enter_import_expression(
+    &mut self,
+    node: &'a ImportExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let replacement = match self.constants.classify(node, ctx.symbols()) {
+      (DynamicImportKind::Literal, Some(specifier)) if node.arguments.is_empty() => {
+        format!(
+          "Promise.resolve().then(() => require({}))",
+          quote(&specifier)
+        )
+      }
+      _ => format!(
+        "__wyw_dynamic_import({})",
+        dynamic_import_argument(&node.source, self.source_text)
+      ),
+    };
+
+    self.edits.overwrite(node.span, replacement);
+    EnterAction::Continue
+  }
+
+  // `require(specifier)` with a computed specifier either gets a static one
+  // or is allowed to fail softly with `require(specifier, true)`
+  fn enter_call_expression(
+    &mut self,
+    node: &'a CallExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let Expression::Identifier(callee) = &node.callee else {
+      return EnterAction::Continue;
+    };
+
+    let symbols = ctx.symbols();
+    if callee.name != "require"
+      || reference_symbol(callee, symbols).is_some()
+      || node.arguments.len() != 1
+    {
+      return EnterAction::Continue;
+    }
+
+    let Some(argument) = node.arguments.first().and_then(Argument::as_expression) else {
+      return EnterAction::Continue;
+    };
+
+    let is_static = match argument.get_inner_expression() {
+      Expression::StringLiteral(_) => true,
+      Expression::TemplateLiteral(template) => template.expressions.is_empty(),
+      _ => false,
+    };
+    if is_static {
+      return EnterAction::Continue;
+    }
+
+    let span = argument.span();
+    match self.constants.static_string(argument, symbols, 0) {
+      Some(specifier) if is_file_like(&specifier) => {
+        self.edits.overwrite(span, quote(&specifier));
+      }
+      _ => self.edits.insert(span.end, ", true"),
+    }
+
+    EnterAction::Continue
+  }
+}
+
+// The same as `runOxcPreevalStage`: prepares the code for the JS evaluator
+pub fn preeval(
+  filename: &str,
+  source_text: &str,
+  registry: &ProcessorRegistry,
+  resolver: &dyn ModuleResolver,
+  features: &Features,
+) -> WywResult<PreevalOutput> {
+  let (code, mut output) = replace_call_sites(filename, source_text, registry, resolver, features)?;
+
+  let allocator = Allocator::default();
+  let (program, symbols, _) = parse(&allocator, filename, &code)?;
+  let mut env_rewriter = ImportMetaEnvRewriter {
+    edits: SourceEdits::new(&code),
+  };
+  walk(&mut env_rewriter, &program, &symbols);
+  let code = env_rewriter.edits.to_code();
+
+  let allocator = Allocator::default();
+  let (program, symbols, _) = parse(&allocator, filename, &code)?;
+  let mut rewriter = SyntaxRewriter {
+    source_text: &code,
+    constants: StringConstants::default(),
+    edits: SourceEdits::new(&code),
+  };
+  walk(&mut rewriter, &program, &symbols);
+  let mut code = rewriter.edits.to_code();
+
+  if !output.processed_tags.is_empty() {
+    let properties = output
+      .dependency_names
+      .iter()
+      .map(|name| format!("{name}: {name}"))
+      .collect::<Vec<_>>();
+    let object = match properties.is_empty() {
+      true => "{}".to_string(),
+      false => format!("{{ {} }}", properties.join(", ")),
+    };
+
+    if program.source_type.is_module() {
+      code.push_str(&format!("\nexport const __wywPreval = {object};"));
+    } else {
+      code.push_str(&format!("\nexports.__wywPreval = {object};"));
+    }
+  }
+
+  output.code = code;
+  Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::features::FeatureFlag;
+  use crate::resolver::{NativeResolver, ResolverOptions};
+  use std::sync::Arc;
+  use wyw_processor::{Processor, ProcessorOutput};
+
+  struct ClassNameProcessor;
+
+  impl Processor for ClassNameProcessor {
+    fn id(&self) -> &str {
+      "class-name"
+    }
+
+    fn transform(
+      &self,
+      params: &ProcessorParams,
+      _diagnostics: &mut Diagnostics,
+    ) -> Option<ProcessorOutput> {
+      Some(ProcessorOutput {
+        replacement: format!("\"t{}\"", params.idx),
+        class_name: Some(format!("t{}", params.idx)),
+        css_text: None,
+      })
+    }
+  }
+
+  fn run(filename: &str, source_text: &str, features: &Features) -> PreevalOutput {
+    let mut registry = ProcessorRegistry::new();
+    registry
+      .register(Arc::new(ClassNameProcessor))
+      .add_tag("lib", "css", "class-name");
+    let resolver = NativeResolver::new(&ResolverOptions::default());
+    preeval(filename, source_text, &registry, &resolver, features).unwrap()
+  }
+
+  #[test]
+  fn test_preeval() {
+    let output = run(
+      "index.js",
+      r#"import { css } from 'lib';
+import { color } from './theme';
+const _exp = 1;
+export const a = css`color: ${color}; width: ${10}px; top: ${window.top}`;
+const width = window.innerWidth;
+"#,
+      &Features::default(),
+    );
+
+    assert_eq!(
+      output.code,
+      r#"import { color } from './theme';
+const _exp = 1;
+const _exp2 = /*#__PURE__*/ () => (color);
+const _exp3 = /*#__PURE__*/ () => (window.top);
+export const a = "t0";
+
+
+export const __wywPreval = { _exp2: _exp2, _exp3: _exp3 };"#
+    );
+    assert_eq!(output.dependency_names, ["_exp2", "_exp3"]);
+    assert_eq!(output.removed.len(), 1);
+  }
+
+  #[test]
+  fn test_syntax() {
+    let features = Features {
+      dangerous_code_remover: FeatureFlag::Bool(false),
+      ..Default::default()
+    };
+    let output = run(
+      "index.js",
+      r#"const dir = './locales';
+const env = import.meta.env.MODE;
+const page = import(`./pages/${name}.js`);
+const en = require(dir + '/en');
+const pkg = require(name);
+const literal = require('./literal');
+"#,
+      &features,
+    );
+
+    assert_eq!(
+      output.code,
+      r#"const dir = './locales';
+const env = __wyw_import_meta_env.MODE;
+const page = __wyw_dynamic_import("./pages/" + name + ".js");
+const en = require("./locales/en");
+const pkg = require(name, true);
+const literal = require('./literal');
+"#
+    );
+    assert!(output.processed_tags.is_empty());
+  }
+
+  #[test]
+  fn test_dynamic_imports() {
+    let output = run(
+      "index.js",
+      r#"const dir = './locales';
+const en = import(dir + '/en');
+const page = import(`./pages/${name}.js`);
+const any = import(name);
+"#,
+      &Features::default(),
+    );
+
+    assert_eq!(
+      output.code,
+      r#"const dir = './locales';
+const en = Promise.resolve().then(() => require("./locales/en"));
+const page = __wyw_dynamic_import("./pages/" + name + ".js");
+const any = __wyw_dynamic_import(name);
+"#
+    );
+    let imports = output
+      .dynamic_imports
+      .iter()
+      .map(|import| (import.kind, import.specifier.as_deref()))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      imports,
+      [
+        (DynamicImportKind::Literal, Some("./locales/en")),
+        (DynamicImportKind::StaticPrefix, Some("./pages/")),
+        (DynamicImportKind::Dynamic, None),
+      ]
+    );
+    let diagnostics = output
+      .diagnostics
+      .iter()
+      .map(|diagnostic| (diagnostic.code.as_str(), diagnostic.primary.span))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      diagnostics,
+      [
+        ("wyw/dynamic-import", output.dynamic_imports[1].span),
+        ("wyw/dynamic-import", output.dynamic_imports[2].span),
+      ]
+    );
+
+    // Specifiers are written as JS strings
+    let output = run(
+      "index.js",
+      "const a = import('./a\\u2028\\x01.js');\n",
+      &Features::default(),
+    );
+    assert_eq!(
+      output.code,
+      "const a = Promise.resolve().then(() => require(\"./a\\u2028\\u0001.js\"));\n"
+    );
+  }
+
+  #[test]
+  fn test_commonjs() {
+    let output = run(
+      "index.js",
+      "const { css } = require('lib');\nexports.a = 1;\n",
+      &Features::default(),
+    );
+    assert!(!output.code.contains("__wywPreval"));
+
+    let output = run(
+      "index.js",
+      "import { css } from 'lib';\nexport const a = css``;\n",
+      &Features::default(),
+    );
+    assert!(output.code.ends_with("\nexport const __wywPreval = {};"));
+
+    // The processor import is removed, so the rest is a script
+    let output = run(
+      "index.js",
+      "import { css } from 'lib';\nexports.a = css`color: ${color}`;\n",
+      &Features::default(),
+    );
+    assert_eq!(
+      output.code,
+      r#"const _exp = /*#__PURE__*/ () => (color);
+exports.a = "t0";
+
+exports.__wywPreval = { _exp: _exp };"#
+    );
+  }
+
+  #[test]
+  fn test_statement_call_site() {
+    // The helper is inserted at the start of the replaced statement
+    let output = run(
+      "index.js",
+      "import { css } from 'lib';\nimport { color } from './theme';\ncss`color: ${color}`;\n",
+      &Features::default(),
+    );
+    assert_eq!(
+      output.code,
+      r#"import { color } from './theme';
+const _exp = /*#__PURE__*/ () => (color);
+"t0";
+
+export const __wywPreval = { _exp: _exp };"#
+    );
+  }
+
+  #[test]
+  fn test_nested_call_sites() {
+    // Helpers get the eval-time replacements of the nested call sites
+    let output = run(
+      "index.js",
+      "import { css } from 'lib';\nimport { color } from './theme';\n\
+        export const a = css`color: ${css`x: ${color}`}; ${(p) => css`y`}`;\n",
+      &Features::default(),
+    );
+    assert_eq!(
+      output.code,
+      r#"import { color } from './theme';
+const _exp = /*#__PURE__*/ () => ("t1");
+const _exp2 = /*#__PURE__*/ () => ((p) => "t2");
+const _exp3 = /*#__PURE__*/ () => (color);
+export const a = "t0";
+
+export const __wywPreval = { _exp: _exp, _exp2: _exp2, _exp3: _exp3 };"#
+    );
+    assert_eq!(output.processed_tags.len(), 3);
+
+    // A nested call site with a local interpolation stays a runtime tag
+    let output = run(
+      "index.js",
+      "import { css } from 'lib';\nexport const a = css`${(p) => css`y: ${p}`}`;\n",
+      &Features::default(),
+    );
+    assert_eq!(
+      output.code,
+      r#"import { css } from 'lib';
+const _exp = /*#__PURE__*/ () => ((p) => css`y: ${p}`);
+export const a = "t0";
+
+export const __wywPreval = { _exp: _exp };"#
+    );
+  }
+
+  #[test]
+  fn test_import_meta_env_specifiers() {
+    let features = Features {
+      dangerous_code_remover: FeatureFlag::Bool(false),
+      ..Default::default()
+    };
+    let output = run(
+      "index.js",
+      r#"const a = import(`${import.meta.env.BASE}/x.js`);
+const b = import(import.meta.env.BASE + name);
+const c = require(import.meta.env.BASE + "/y.js");
+"#,
+      &features,
+    );
+
+    assert_eq!(
+      output.code,
+      r#"const a = __wyw_dynamic_import(__wyw_import_meta_env.BASE + "/x.js");
+const b = __wyw_dynamic_import(__wyw_import_meta_env.BASE + name);
+const c = require(__wyw_import_meta_env.BASE + "/y.js", true);
+"#
+    );
+  }
+
+  #[test]
+  fn test_local_interpolations() {
+    // `size` is not available at the top level, where the helpers are declared
+    let source_text = "import { css } from 'lib';\nexport function make(size) {\n  return css`width: ${size}px; ${(p) => p.x}`;\n}\nexport const b = css`color: ${make}`;\n";
+    let output = run("index.js", source_text, &Features::default());
+    assert_eq!(
+      output.code,
+      r#"import { css } from 'lib';
+export function make(size) {
+  return css`width: ${size}px; ${(p) => p.x}`;
+}
+const _exp = /*#__PURE__*/ () => (make);
+export const b = "t1";
+
+export const __wywPreval = { _exp: _exp };"#
+    );
+
+    let diagnostics = output
+      .diagnostics
+      .iter()
+      .map(|diagnostic| {
+        (
+          diagnostic.code.as_str(),
+          diagnostic.primary.span.source_text(source_text),
+          diagnostic.secondary[0].span.source_text(source_text),
+        )
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      diagnostics,
+      [(
+        "wyw/local-interpolation",
+        "css`width: ${size}px; ${(p) => p.x}`",
+        "size"
+      )]
+    );
+  }
+}
@@ -0,0 +1,449 @@
+use crate::import_overrides::{
+  apply_import_override_to_only, to_import_key, ImportOverride, ImportOverrides,
+};
+use oxc_resolver::{ResolveOptions, Resolver, TsconfigOptions, TsconfigReferences};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use wyw_processor::error::{WywError, WywResult};
+
+const CJS_DEFAULT_CONDITIONS: [&str; 3] = ["require", "node", "default"];
+const ESM_DEFAULT_CONDITIONS: [&str; 3] = ["node", "import", "default"];
+// The same as the default `extensions` option of wyw
+const DEFAULT_EXTENSIONS: [&str; 8] =
+  [".cjs", ".cts", ".js", ".jsx", ".mjs", ".mts", ".ts", ".tsx"];
+const FALLBACK_EXTENSIONS: [&str; 2] = [".json", ".node"];
+
+// The same as `EvalResolverKind` from `@wyw-in-js/shared`
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ResolveKind {
+  #[default]
+  Import,
+  Require,
+}
+
+fn unique(items: impl IntoIterator<Item = String>) -> Vec<String> {
+  let mut result = Vec::<String>::new();
+  for item in items {
+    if !result.contains(&item) {
+      result.push(item);
+    }
+  }
+
+  result
+}
+
+// `...` is replaced with the default conditions of the kind, no names mean only the defaults
+pub fn expand_conditions(kind: ResolveKind, condition_names: &[String]) -> Vec<String> {
+  let defaults = match kind {
+    ResolveKind::Require => CJS_DEFAULT_CONDITIONS,
+    ResolveKind::Import => ESM_DEFAULT_CONDITIONS,
+  };
+
+  if condition_names.is_empty() {
+    return defaults.map(String::from).to_vec();
+  }
+
+  unique(condition_names.iter().flat_map(|name| {
+    if name == "..." {
+      defaults.map(String::from).to_vec()
+    } else {
+      vec![name.clone()]
+    }
+  }))
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ResolverOptions {
+  pub kind: ResolveKind,
+  pub condition_names: Vec<String>,
+  // The default extensions of wyw are used if it's empty
+  pub extensions: Vec<String>,
+  // The config with `paths` aliases, `tsconfig.json` in `root` is used if it's not set
+  pub tsconfig: Option<PathBuf>,
+  // The project root where `.pnp.cjs` and `tsconfig.json` are looked up.
+  // File keys of `import_overrides` and relative mocks are relative to it.
+  pub root: Option<PathBuf>,
+  pub import_overrides: ImportOverrides,
+}
+
+impl ResolverOptions {
+  fn tsconfig(&self) -> Option<PathBuf> {
+    self.tsconfig.clone().or_else(|| {
+      let tsconfig = self.root.as_ref()?.join("tsconfig.json");
+      tsconfig.is_file().then_some(tsconfig)
+    })
+  }
+
+  fn extensions(&self) -> Vec<String> {
+    let extensions = if self.extensions.is_empty() {
+      DEFAULT_EXTENSIONS.map(String::from).to_vec()
+    } else {
+      self.extensions.clone()
+    };
+
+    unique(
+      extensions
+        .into_iter()
+        .chain(FALLBACK_EXTENSIONS.map(String::from)),
+    )
+  }
+
+  fn to_resolve_options(&self) -> ResolveOptions {
+    ResolveOptions {
+      condition_names: expand_conditions(self.kind, &self.condition_names),
+      extensions: self.extensions(),
+      tsconfig: self.tsconfig().map(|config_file| TsconfigOptions {
+        config_file,
+        references: TsconfigReferences::Auto,
+      }),
+      pnp_manifest: self
+        .root
+        .as_ref()
+        .and_then(|root| pnp::find_pnp_manifest(root).ok().flatten()),
+      ..ResolveOptions::default()
+    }
+  }
+}
+
+// `./foo` may be resolved to `foo.cjs` even though `foo.js` exists next to it
+fn prefer_js_over_cjs(specifier: &str, resolved: PathBuf) -> PathBuf {
+  let filename = specifier.split(['?', '#']).next().unwrap_or_default();
+  let is_file_specifier = filename.starts_with('.') || Path::new(filename).is_absolute();
+  if !is_file_specifier || Path::new(filename).extension().is_some() {
+    return resolved;
+  }
+
+  if resolved
+    .extension()
+    .is_some_and(|extension| extension == "cjs")
+  {
+    let js = resolved.with_extension("js");
+    if js.is_file() {
+      return js;
+    }
+  }
+
+  resolved
+}
+
+// Resolves imports of a module to absolute paths
+pub trait ModuleResolver {
+  fn resolve(&self, importer: &str, source: &str) -> WywResult<String>;
+}
+
+impl ModuleResolver for NativeResolver {
+  fn resolve(&self, importer: &str, source: &str) -> WywResult<String> {
+    NativeResolver::resolve(self, importer, source)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedImport {
+  pub source: String,
+  pub resolved: String,
+  // Exports that the importer uses, `*` for all of them
+  pub only: Vec<String>,
+}
+
+// The counterpart of `resolveWithNativeResolver`
+pub struct NativeResolver {
+  resolver: Resolver,
+  root: Option<PathBuf>,
+  import_overrides: ImportOverrides,
+  // Results and failures by the directory of the importer and the specifier
+  cache: RwLock<HashMap<PathBuf, HashMap<String, WywResult<String>>>>,
+}
+
+impl NativeResolver {
+  pub fn new(options: &ResolverOptions) -> Self {
+    Self {
+      resolver: Resolver::new(options.to_resolve_options()),
+      root: options.root.clone(),
+      import_overrides: options.import_overrides.clone(),
+      cache: RwLock::default(),
+    }
+  }
+
+  // Returns the absolute path with the query and the hash of the specifier
+  pub fn resolve(&self, importer: &str, specifier: &str) -> WywResult<String> {
+    let directory = Path::new(importer)
+      .parent()
+      .unwrap_or(Path::new("/"))
+      .to_path_buf();
+
+    let cached = self
+      .cache
+      .read()
+      .unwrap()
+      .get(&directory)
+      .and_then(|results| results.get(specifier).cloned());
+    let result = match cached {
+      Some(result) => result,
+      None => {
+        let result = self.resolve_in(&directory, specifier);
+        self
+          .cache
+          .write()
+          .unwrap()
+          .entry(directory)
+          .or_default()
+          .insert(specifier.to_string(), result.clone());
+        result
+      }
+    };
+
+    result.map_err(|error| error.with_filename(importer))
+  }
+
+  fn resolve_in(&self, directory: &Path, specifier: &str) -> WywResult<String> {
+    let resolution = self
+      .resolver
+      .resolve(directory, specifier)
+      .map_err(|error| {
+        WywError::new(
+          "wyw/resolve-error",
+          format!("Cannot resolve module {specifier}: {error}"),
+        )
+      })?;
+
+    let path = prefer_js_over_cjs(specifier, resolution.path().to_path_buf());
+    Ok(format!(
+      "{}{}{}",
+      path.display(),
+      resolution.query().unwrap_or_default(),
+      resolution.fragment().unwrap_or_default()
+    ))
+  }
+
+  pub fn import_override(&self, source: &str, resolved: Option<&str>) -> Option<&ImportOverride> {
+    if self.import_overrides.is_empty() {
+      return None;
+    }
+
+    let key = to_import_key(source, resolved, self.root.as_deref());
+    self.import_overrides.get(&key.key)
+  }
+
+  // Resolves an import and applies `importOverrides` to it. Packages are matched
+  // by the specifier, so they can be mocked even if they cannot be resolved.
+  pub fn resolve_import(
+    &self,
+    importer: &str,
+    source: &str,
+    only: Vec<String>,
+  ) -> WywResult<ResolvedImport> {
+    let resolved = self.resolve(importer, source);
+    let import_override = self.import_override(source, resolved.as_deref().ok());
+    let resolved = match import_override {
+      Some(ImportOverride::Mock(mock)) => self.resolve_mock(importer, mock)?,
+      _ => resolved?,
+    };
+
+    Ok(ResolvedImport {
+      source: source.to_string(),
+      resolved,
+      only: apply_import_override_to_only(only, import_override),
+    })
+  }
+
+  // Relative mocks are resolved from the root
+  fn resolve_mock(&self, importer: &str, mock: &str) -> WywResult<String> {
+    match &self.root {
+      Some(root) if mock.starts_with('.') => {
+        self.resolve(importer, &root.join(mock).display().to_string())
+      }
+      _ => self.resolve(importer, mock),
+    }
+  }
+
+  pub fn clear_cache(&self) {
+    self.cache.write().unwrap().clear();
+    self.resolver.clear_cache();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  fn fixture(files: &[(&str, &str)]) -> TempDir {
+    let root = TempDir::with_prefix("wyw-resolver-").unwrap();
+    for (path, content) in files {
+      let path = root.path().join(path);
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(path, content).unwrap();
+    }
+
+    root
+  }
+
+  fn importer(root: &Path) -> String {
+    root.join("src/index.ts").display().to_string()
+  }
+
+  #[test]
+  fn test_expand_conditions() {
+    assert_eq!(
+      expand_conditions(ResolveKind::Require, &[]),
+      ["require", "node", "default"]
+    );
+    assert_eq!(
+      expand_conditions(
+        ResolveKind::Import,
+        &[
+          "development".to_string(),
+          "...".to_string(),
+          "node".to_string()
+        ]
+      ),
+      ["development", "node", "import", "default"]
+    );
+  }
+
+  #[test]
+  fn test_conditions() {
+    let dir = fixture(&[
+      (
+        "node_modules/pkg/package.json",
+        r#"{ "exports": { ".": { "development": "./dev.js", "import": "./index.mjs", "require": "./index.cjs" } } }"#,
+      ),
+      ("node_modules/pkg/dev.js", ""),
+      ("node_modules/pkg/index.mjs", ""),
+      ("node_modules/pkg/index.cjs", ""),
+      ("src/index.ts", ""),
+    ]);
+    let root = dir.path();
+
+    let resolve =
+      |options: ResolverOptions| NativeResolver::new(&options).resolve(&importer(root), "pkg");
+    let pkg = root.join("node_modules/pkg");
+    assert_eq!(
+      resolve(ResolverOptions::default()),
+      Ok(pkg.join("index.mjs").display().to_string())
+    );
+    assert_eq!(
+      resolve(ResolverOptions {
+        kind: ResolveKind::Require,
+        ..ResolverOptions::default()
+      }),
+      Ok(pkg.join("index.cjs").display().to_string())
+    );
+    assert_eq!(
+      resolve(ResolverOptions {
+        condition_names: vec!["development".to_string(), "...".to_string()],
+        ..ResolverOptions::default()
+      }),
+      Ok(pkg.join("dev.js").display().to_string())
+    );
+  }
+
+  #[test]
+  fn test_paths_and_extensions() {
+    let dir = fixture(&[
+      (
+        "tsconfig.json",
+        r#"{ "compilerOptions": { "baseUrl": ".", "paths": { "@/*": ["src/*"] } } }"#,
+      ),
+      ("src/theme.ts", ""),
+      ("src/legacy.cjs", ""),
+      ("src/legacy.js", ""),
+      ("src/index.ts", ""),
+    ]);
+    let root = dir.path();
+
+    let resolver = NativeResolver::new(&ResolverOptions {
+      extensions: vec![".ts".to_string(), ".cjs".to_string()],
+      root: Some(root.to_path_buf()),
+      ..ResolverOptions::default()
+    });
+    let src = root.join("src");
+    assert_eq!(
+      resolver.resolve(&importer(root), "@/theme?raw"),
+      Ok(format!("{}?raw", src.join("theme.ts").display()))
+    );
+    assert_eq!(
+      resolver.resolve(&importer(root), "./legacy"),
+      Ok(src.join("legacy.js").display().to_string())
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_symlinks() {
+    let dir = fixture(&[("packages/ui/index.js", ""), ("src/index.ts", "")]);
+    let root = dir.path();
+    fs::create_dir_all(root.join("node_modules")).unwrap();
+    std::os::unix::fs::symlink(root.join("packages/ui"), root.join("node_modules/ui")).unwrap();
+
+    let resolver = NativeResolver::new(&ResolverOptions::default());
+    assert_eq!(
+      resolver.resolve(&importer(root), "ui"),
+      Ok(root.join("packages/ui/index.js").display().to_string())
+    );
+  }
+
+  #[test]
+  fn test_import_overrides() {
+    let dir = fixture(&[
+      ("mocks/ui.ts", ""),
+      ("src/theme.ts", ""),
+      ("src/index.ts", ""),
+    ]);
+    let root = dir.path();
+
+    let resolver = NativeResolver::new(&ResolverOptions {
+      root: Some(root.to_path_buf()),
+      import_overrides: ImportOverrides::new([
+        (
+          "@acme/*".to_string(),
+          ImportOverride::Mock("./mocks/ui.ts".to_string()),
+        ),
+        ("./src/*.ts".to_string(), ImportOverride::NoShake),
+      ]),
+      ..ResolverOptions::default()
+    });
+
+    let resolved = resolver
+      .resolve_import(&importer(root), "@acme/ui", vec!["Button".to_string()])
+      .unwrap();
+    assert_eq!(
+      resolved.resolved,
+      root.join("mocks/ui.ts").display().to_string()
+    );
+    assert_eq!(resolved.only, ["Button"]);
+
+    let resolved = resolver
+      .resolve_import(&importer(root), "./theme", vec!["colors".to_string()])
+      .unwrap();
+    assert_eq!(
+      resolved.resolved,
+      root.join("src/theme.ts").display().to_string()
+    );
+    assert_eq!(resolved.only, ["*"]);
+
+    assert!(resolver
+      .resolve_import(&importer(root), "@other/ui", vec![])
+      .is_err());
+  }
+
+  #[test]
+  fn test_cache() {
+    let dir = fixture(&[("src/index.ts", "")]);
+    let root = dir.path();
+    let resolver = NativeResolver::new(&ResolverOptions::default());
+
+    let error = resolver.resolve(&importer(root), "./theme.js").unwrap_err();
+    assert_eq!(error.code, "wyw/resolve-error");
+    assert_eq!(error.filename, Some(importer(root)));
+
+    // The failure is cached until the cache is cleared
+    fs::write(root.join("src/theme.js"), "").unwrap();
+    assert!(resolver.resolve(&importer(root), "./theme.js").is_err());
+
+    resolver.clear_cache();
+    assert!(resolver.resolve(&importer(root), "./theme.js").is_ok());
+  }
+}
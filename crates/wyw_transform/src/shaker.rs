@@ -0,0 +1,1143 @@
+use crate::edits::SourceEdits;
+use crate::exports_and_imports::{
+  binding_identifiers, collect_exports_and_imports, collect_exports_and_imports_from_program,
+  reference_symbol,
+};
+use crate::import_overrides::{ImportOverride, ImportOverrides};
+use crate::parse_error;
+use oxc::allocator::Allocator;
+use oxc::ast::ast::{
+  Argument, ArrowFunctionExpression, AssignmentExpression, AssignmentTarget,
+  AssignmentTargetMaybeDefault, AssignmentTargetProperty, BindingIdentifier, BindingPattern,
+  BindingPatternKind, CallExpression, Declaration, ExportDefaultDeclarationKind, Expression,
+  ForInStatement, ForOfStatement, Function, IdentifierReference, ImportDeclaration,
+  ImportDeclarationSpecifier, NewExpression, Program, SimpleAssignmentTarget, Statement,
+  TaggedTemplateExpression, UnaryExpression, UnaryOperator, UpdateExpression, VariableDeclaration,
+};
+use oxc::parser::Parser;
+use oxc::span::{GetSpan, SourceType, Span};
+use oxc_semantic::{SemanticBuilder, SymbolId, SymbolTable};
+use std::collections::{HashMap, HashSet, VecDeque};
+use wyw_processor::error::WywResult;
+use wyw_traverse::{walk, Ancestor, AnyNode, EnterAction, TraverseCtx, TraverseHooks};
+
+// The evaluator reads it from the exports, so it is never unwrapped or split
+const PREVAL_EXPORT: &str = "__wywPreval";
+
+#[derive(Clone, Debug, Default)]
+pub struct ShakerOptions {
+  // Exports that should be kept. `*` keeps all of them, `side-effect` keeps side-effect imports.
+  pub only_exports: Vec<String>,
+  pub keep_side_effects: bool,
+  // Side-effect imports of mocked and `noShake` modules are always kept
+  pub import_overrides: ImportOverrides,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShakerOutput {
+  pub code: String,
+  // Imported names that are still used, grouped by source
+  pub imports: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Default)]
+struct StatementFacts {
+  bindings: Vec<(SymbolId, String)>,
+  export_names: Vec<String>,
+  side_effect_import: bool,
+  // Value references to top-level bindings, including ones inside functions
+  references: HashSet<SymbolId>,
+  // Bindings changed when the module is executed
+  mutations: HashSet<SymbolId>,
+  // Bindings changed inside functions
+  nested_mutations: HashSet<SymbolId>,
+  // Roots of callees and arguments of impure calls
+  invocation_roots: HashSet<SymbolId>,
+  local_calls: HashSet<SymbolId>,
+  imported_call: bool,
+  // An impure call or a mutation that is not bound to any top-level binding
+  unbound_effect: bool,
+}
+
+fn declaration_bindings<'p, 'a>(
+  declaration: &'p Declaration<'a>,
+  result: &mut Vec<&'p BindingIdentifier<'a>>,
+) {
+  match declaration {
+    Declaration::VariableDeclaration(declaration) => variable_bindings(declaration, result),
+    Declaration::FunctionDeclaration(function) => result.extend(&function.id),
+    Declaration::ClassDeclaration(class) => result.extend(&class.id),
+    Declaration::TSEnumDeclaration(declaration) => result.push(&declaration.id),
+    _ => {}
+  }
+}
+
+fn variable_bindings<'p, 'a>(
+  declaration: &'p VariableDeclaration<'a>,
+  result: &mut Vec<&'p BindingIdentifier<'a>>,
+) {
+  for declarator in &declaration.declarations {
+    binding_identifiers(&declarator.id, result);
+  }
+}
+
+fn statement_bindings<'p, 'a>(statement: &'p Statement<'a>) -> Vec<&'p BindingIdentifier<'a>> {
+  let mut result = vec![];
+  match statement {
+    Statement::ImportDeclaration(declaration) if !declaration.import_kind.is_type() => {
+      for specifier in declaration.specifiers.iter().flatten() {
+        result.push(match specifier {
+          ImportDeclarationSpecifier::ImportSpecifier(specifier) => &specifier.local,
+          ImportDeclarationSpecifier::ImportDefaultSpecifier(specifier) => &specifier.local,
+          ImportDeclarationSpecifier::ImportNamespaceSpecifier(specifier) => &specifier.local,
+        });
+      }
+    }
+    Statement::ExportNamedDeclaration(export) => {
+      if let Some(declaration) = &export.declaration {
+        declaration_bindings(declaration, &mut result);
+      }
+    }
+    Statement::ExportDefaultDeclaration(export) => match &export.declaration {
+      ExportDefaultDeclarationKind::FunctionDeclaration(function) => result.extend(&function.id),
+      ExportDefaultDeclarationKind::ClassDeclaration(class) => result.extend(&class.id),
+      _ => {}
+    },
+    Statement::VariableDeclaration(declaration) => variable_bindings(declaration, &mut result),
+    Statement::FunctionDeclaration(function) => result.extend(&function.id),
+    Statement::ClassDeclaration(class) => result.extend(&class.id),
+    Statement::TSEnumDeclaration(declaration) => result.push(&declaration.id),
+    _ => {}
+  }
+
+  result
+}
+
+fn statement_export_names(statement: &Statement) -> Vec<String> {
+  match statement {
+    Statement::ExportNamedDeclaration(export) if !export.export_kind.is_type() => {
+      let mut bindings = vec![];
+      if let Some(declaration) = &export.declaration {
+        declaration_bindings(declaration, &mut bindings);
+      }
+
+      let names = bindings.iter().map(|ident| ident.name.to_string());
+      let specifiers = export
+        .specifiers
+        .iter()
+        .filter(|specifier| !specifier.export_kind.is_type())
+        .map(|specifier| specifier.exported.name().to_string());
+      names.chain(specifiers).collect()
+    }
+    Statement::ExportDefaultDeclaration(_) => vec!["default".to_string()],
+    Statement::ExportAllDeclaration(export) if !export.export_kind.is_type() => {
+      match &export.exported {
+        Some(exported) => vec![exported.name().to_string()],
+        None => vec!["*".to_string()],
+      }
+    }
+    _ => vec![],
+  }
+}
+
+// Calls annotated with `/*#__PURE__*/` or `/*@__PURE__*/`, by their start
+fn pure_annotations(program: &Program, source_text: &str) -> HashSet<u32> {
+  program
+    .comments
+    .iter()
+    .filter(|comment| comment.is_block() && comment.is_leading())
+    .filter(|comment| {
+      let content = comment.content_span().source_text(source_text).trim();
+      content == "#__PURE__" || content == "@__PURE__"
+    })
+    .map(|comment| comment.attached_to)
+    .collect()
+}
+
+// The top-level binding that is read or changed by `foo`, `foo.bar` or `foo?.bar[baz]`
+fn expression_root(
+  expression: &Expression,
+  symbols: &SymbolTable,
+  owners: &HashMap<SymbolId, usize>,
+) -> Option<SymbolId> {
+  match expression.get_inner_expression() {
+    Expression::Identifier(ident) => {
+      reference_symbol(ident, symbols).filter(|id| owners.contains_key(id))
+    }
+    Expression::ChainExpression(chain) => chain
+      .expression
+      .as_member_expression()
+      .and_then(|member| expression_root(member.object(), symbols, owners)),
+    expression => expression
+      .as_member_expression()
+      .and_then(|member| expression_root(member.object(), symbols, owners)),
+  }
+}
+
+// `Object.assign(foo, …)` and `Object.defineProperty(foo, …)` change `foo`
+pub(crate) fn object_mutation_target<'b, 'a>(
+  call: &'b CallExpression<'a>,
+) -> Option<&'b Expression<'a>> {
+  let member = call.callee.get_inner_expression().as_member_expression()?;
+  let Expression::Identifier(object) = member.object() else {
+    return None;
+  };
+
+  let is_mutation = matches!(
+    member.static_property_name(),
+    Some("assign" | "defineProperty" | "defineProperties")
+  );
+  if object.name != "Object" || !is_mutation {
+    return None;
+  }
+
+  call.arguments.first().and_then(Argument::as_expression)
+}
+
+fn statement_index(ctx: &TraverseCtx) -> Option<usize> {
+  match ctx.ancestors.first()? {
+    Ancestor::ListItem(AnyNode::Program(_), "body", idx) => Some(*idx),
+    _ => None,
+  }
+}
+
+// `(function () { … })()` is executed together with the module
+fn is_iife(ctx: &TraverseCtx) -> bool {
+  let parent = ctx
+    .ancestors
+    .iter()
+    .rev()
+    .find(|ancestor| !matches!(ancestor.node(), AnyNode::ParenthesizedExpression(_)));
+  matches!(
+    parent,
+    Some(Ancestor::Field(AnyNode::CallExpression(_), "callee"))
+  )
+}
+
+struct FactsCollector<'s> {
+  symbols: &'s SymbolTable,
+  owners: &'s HashMap<SymbolId, usize>,
+  imports: &'s HashSet<SymbolId>,
+  pure: HashSet<u32>,
+  // `true` for functions that are executed together with the module
+  functions: Vec<bool>,
+  facts: Vec<StatementFacts>,
+}
+
+impl FactsCollector<'_> {
+  fn is_executed(&self) -> bool {
+    self.functions.iter().all(|executed| *executed)
+  }
+
+  fn root(&self, expression: &Expression) -> Option<SymbolId> {
+    expression_root(expression, self.symbols, self.owners)
+  }
+
+  fn identifier_root(&self, ident: &IdentifierReference) -> Option<SymbolId> {
+    reference_symbol(ident, self.symbols).filter(|id| self.owners.contains_key(id))
+  }
+
+  fn target_roots(&self, target: &AssignmentTarget, result: &mut Vec<Option<SymbolId>>) {
+    match target {
+      AssignmentTarget::AssignmentTargetIdentifier(ident) => {
+        result.push(self.identifier_root(ident))
+      }
+      AssignmentTarget::ArrayAssignmentTarget(array) => {
+        for element in array.elements.iter().flatten() {
+          self.maybe_default_roots(element, result);
+        }
+
+        if let Some(rest) = &array.rest {
+          self.target_roots(&rest.target, result);
+        }
+      }
+      AssignmentTarget::ObjectAssignmentTarget(object) => {
+        for property in &object.properties {
+          match property {
+            AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(property) => {
+              result.push(self.identifier_root(&property.binding))
+            }
+            AssignmentTargetProperty::AssignmentTargetPropertyProperty(property) => {
+              self.maybe_default_roots(&property.binding, result)
+            }
+          }
+        }
+
+        if let Some(rest) = &object.rest {
+          self.target_roots(&rest.target, result);
+        }
+      }
+      target => {
+        if let Some(member) = target.as_member_expression() {
+          result.push(self.root(member.object()));
+        }
+      }
+    }
+  }
+
+  fn maybe_default_roots(
+    &self,
+    target: &AssignmentTargetMaybeDefault,
+    result: &mut Vec<Option<SymbolId>>,
+  ) {
+    match target {
+      AssignmentTargetMaybeDefault::AssignmentTargetWithDefault(target) => {
+        self.target_roots(&target.binding, result)
+      }
+      target => {
+        if let Some(target) = target.as_assignment_target() {
+          self.target_roots(target, result);
+        }
+      }
+    }
+  }
+
+  fn add_mutations(&mut self, roots: Vec<Option<SymbolId>>, ctx: &TraverseCtx) {
+    let Some(idx) = statement_index(ctx) else {
+      return;
+    };
+
+    let is_executed = self.is_executed();
+    let facts = &mut self.facts[idx];
+    for root in roots {
+      match root {
+        Some(root) if is_executed => {
+          facts.mutations.insert(root);
+        }
+        Some(root) => {
+          facts.nested_mutations.insert(root);
+        }
+        None if is_executed => facts.unbound_effect = true,
+        None => {}
+      }
+    }
+  }
+
+  fn add_invocation<'b>(
+    &mut self,
+    start: u32,
+    callee: &Expression,
+    arguments: impl IntoIterator<Item = &'b Expression<'b>>,
+    ctx: &TraverseCtx,
+  ) {
+    if !self.is_executed() || self.pure.contains(&start) {
+      return;
+    }
+
+    let Some(idx) = statement_index(ctx) else {
+      return;
+    };
+
+    let callee_root = self.root(callee);
+    let argument_roots = arguments
+      .into_iter()
+      .filter_map(|argument| self.root(argument))
+      .collect::<Vec<_>>();
+
+    let facts = &mut self.facts[idx];
+    match callee_root {
+      Some(root) if self.imports.contains(&root) => facts.imported_call = true,
+      Some(root) => {
+        facts.local_calls.insert(root);
+      }
+      None => facts.unbound_effect = true,
+    }
+
+    facts.invocation_roots.extend(callee_root);
+    facts.invocation_roots.extend(argument_roots);
+  }
+}
+
+impl<'a> TraverseHooks<'a> for FactsCollector<'_> {
+  fn enter_function(&mut self, _node: &'a Function<'a>, ctx: &mut TraverseCtx<'a>) -> EnterAction {
+    self.functions.push(is_iife(ctx));
+    EnterAction::Continue
+  }
+
+  fn exit_function(&mut self, _node: &'a Function<'a>, _ctx: &mut TraverseCtx<'a>) {
+    self.functions.pop();
+  }
+
+  fn enter_arrow_function_expression(
+    &mut self,
+    _node: &'a ArrowFunctionExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    self.functions.push(is_iife(ctx));
+    EnterAction::Continue
+  }
+
+  fn exit_arrow_function_expression(
+    &mut self,
+    _node: &'a ArrowFunctionExpression<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) {
+    self.functions.pop();
+  }
+
+  fn enter_identifier_reference(
+    &mut self,
+    node: &'a IdentifierReference<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let is_value = node
+      .reference_id
+      .get()
+      .is_some_and(|reference_id| self.symbols.get_reference(reference_id).flags().is_value());
+    if let (true, Some(root), Some(idx)) =
+      (is_value, self.identifier_root(node), statement_index(ctx))
+    {
+      self.facts[idx].references.insert(root);
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_assignment_expression(
+    &mut self,
+    node: &'a AssignmentExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let mut roots = vec![];
+    self.target_roots(&node.left, &mut roots);
+    self.add_mutations(roots, ctx);
+    EnterAction::Continue
+  }
+
+  fn enter_update_expression(
+    &mut self,
+    node: &'a UpdateExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let root = match &node.argument {
+      SimpleAssignmentTarget::AssignmentTargetIdentifier(ident) => self.identifier_root(ident),
+      target => target
+        .as_member_expression()
+        .and_then(|member| self.root(member.object())),
+    };
+    self.add_mutations(vec![root], ctx);
+    EnterAction::Continue
+  }
+
+  fn enter_unary_expression(
+    &mut self,
+    node: &'a UnaryExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if node.operator == UnaryOperator::Delete {
+      let root = self.root(&node.argument);
+      self.add_mutations(vec![root], ctx);
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_for_in_statement(
+    &mut self,
+    node: &'a ForInStatement<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if let Some(target) = node.left.as_assignment_target() {
+      let mut roots = vec![];
+      self.target_roots(target, &mut roots);
+      self.add_mutations(roots, ctx);
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_for_of_statement(
+    &mut self,
+    node: &'a ForOfStatement<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if let Some(target) = node.left.as_assignment_target() {
+      let mut roots = vec![];
+      self.target_roots(target, &mut roots);
+      self.add_mutations(roots, ctx);
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_call_expression(
+    &mut self,
+    node: &'a CallExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if let Some(target) = object_mutation_target(node) {
+      let root = self.root(target);
+      self.add_mutations(vec![root], ctx);
+      return EnterAction::Continue;
+    }
+
+    let arguments = node.arguments.iter().filter_map(|argument| match argument {
+      Argument::SpreadElement(spread) => Some(&spread.argument),
+      argument => argument.as_expression(),
+    });
+    self.add_invocation(node.span.start, &node.callee, arguments, ctx);
+    EnterAction::Continue
+  }
+
+  fn enter_new_expression(
+    &mut self,
+    node: &'a NewExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let arguments = node.arguments.iter().filter_map(|argument| match argument {
+      Argument::SpreadElement(spread) => Some(&spread.argument),
+      argument => argument.as_expression(),
+    });
+    self.add_invocation(node.span.start, &node.callee, arguments, ctx);
+    EnterAction::Continue
+  }
+
+  fn enter_tagged_template_expression(
+    &mut self,
+    node: &'a TaggedTemplateExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    self.add_invocation(
+      node.span.start,
+      &node.tag,
+      node.quasi.expressions.iter(),
+      ctx,
+    );
+    EnterAction::Continue
+  }
+}
+
+// Bindings that refer to the same object, e.g. `const alias = source.nested`
+#[derive(Default)]
+struct Aliases {
+  parents: HashMap<SymbolId, SymbolId>,
+}
+
+impl Aliases {
+  fn find(&self, id: SymbolId) -> SymbolId {
+    let mut current = id;
+    while let Some(&parent) = self.parents.get(&current) {
+      current = parent;
+    }
+
+    current
+  }
+
+  fn union(&mut self, a: SymbolId, b: SymbolId) {
+    let (a, b) = (self.find(a), self.find(b));
+    if a != b {
+      self.parents.insert(a, b);
+    }
+  }
+}
+
+// `const { a = b } = …` makes `a` an alias of `b`
+fn default_aliases(
+  pattern: &BindingPattern,
+  symbols: &SymbolTable,
+  owners: &HashMap<SymbolId, usize>,
+  aliases: &mut Aliases,
+) {
+  match &pattern.kind {
+    BindingPatternKind::BindingIdentifier(_) => {}
+    BindingPatternKind::AssignmentPattern(assignment) => {
+      if let Some(source) = expression_root(&assignment.right, symbols, owners) {
+        let mut bindings = vec![];
+        binding_identifiers(&assignment.left, &mut bindings);
+        for symbol_id in bindings.iter().filter_map(|ident| ident.symbol_id.get()) {
+          aliases.union(symbol_id, source);
+        }
+      }
+
+      default_aliases(&assignment.left, symbols, owners, aliases);
+    }
+    BindingPatternKind::ObjectPattern(object) => {
+      for property in &object.properties {
+        default_aliases(&property.value, symbols, owners, aliases);
+      }
+    }
+    BindingPatternKind::ArrayPattern(array) => {
+      for element in array.elements.iter().flatten() {
+        default_aliases(element, symbols, owners, aliases);
+      }
+    }
+  }
+}
+
+fn collect_aliases(
+  program: &Program,
+  symbols: &SymbolTable,
+  owners: &HashMap<SymbolId, usize>,
+) -> Aliases {
+  let mut aliases = Aliases::default();
+  for statement in &program.body {
+    let declaration = match statement {
+      Statement::VariableDeclaration(declaration) => declaration,
+      Statement::ExportNamedDeclaration(export) => match &export.declaration {
+        Some(Declaration::VariableDeclaration(declaration)) => declaration,
+        _ => continue,
+      },
+      _ => continue,
+    };
+
+    for declarator in &declaration.declarations {
+      default_aliases(&declarator.id, symbols, owners, &mut aliases);
+
+      let source = declarator
+        .init
+        .as_ref()
+        .and_then(|init| expression_root(init, symbols, owners));
+      let Some(source) = source else {
+        continue;
+      };
+
+      let mut bindings = vec![];
+      binding_identifiers(&declarator.id, &mut bindings);
+      for ident in bindings {
+        if let Some(symbol_id) = ident.symbol_id.get() {
+          aliases.union(symbol_id, source);
+        }
+      }
+    }
+  }
+
+  aliases
+}
+
+fn source_slice(source_text: &str, span: Span) -> &str {
+  &source_text[span.start as usize..span.end as usize]
+}
+
+// `import { a, b } from` without the unused specifiers
+fn import_clause(
+  declaration: &ImportDeclaration,
+  source_text: &str,
+  live_bindings: &HashSet<SymbolId>,
+) -> Option<String> {
+  let specifiers = declaration.specifiers.as_ref()?;
+  let is_live = |local: &BindingIdentifier| {
+    local
+      .symbol_id
+      .get()
+      .is_some_and(|id| live_bindings.contains(&id))
+  };
+
+  let mut parts = vec![];
+  let mut named = vec![];
+  for specifier in specifiers {
+    match specifier {
+      ImportDeclarationSpecifier::ImportSpecifier(specifier) if is_live(&specifier.local) => {
+        named.push(source_slice(source_text, specifier.span))
+      }
+      ImportDeclarationSpecifier::ImportDefaultSpecifier(specifier)
+        if is_live(&specifier.local) =>
+      {
+        parts.push(source_slice(source_text, specifier.span).to_string())
+      }
+      ImportDeclarationSpecifier::ImportNamespaceSpecifier(specifier)
+        if is_live(&specifier.local) =>
+      {
+        parts.push(source_slice(source_text, specifier.span).to_string())
+      }
+      _ => {}
+    }
+  }
+
+  if parts.len() + named.len() == specifiers.len() {
+    return None;
+  }
+
+  if !named.is_empty() {
+    parts.push(format!("{{ {} }}", named.join(", ")));
+  }
+
+  let kind = if declaration.import_kind.is_type() {
+    "type "
+  } else {
+    ""
+  };
+  Some(format!("import {kind}{} from ", parts.join(", ")))
+}
+
+// `export const a = 1, b = 2;` → `const a = 1, b = 2;\nexport { a };`
+fn split_exported_declaration(
+  declaration: &VariableDeclaration,
+  source_text: &str,
+  selected: &[&str],
+) -> Option<String> {
+  let mut bindings = vec![];
+  variable_bindings(declaration, &mut bindings);
+  let names = bindings
+    .iter()
+    .map(|ident| ident.name.as_str())
+    .filter(|name| selected.contains(name))
+    .collect::<Vec<_>>();
+  if names.len() == bindings.len() {
+    return None;
+  }
+
+  let declarators = declaration
+    .declarations
+    .iter()
+    .map(|declarator| source_slice(source_text, declarator.span))
+    .collect::<Vec<_>>();
+  let mut result = format!("{} {};", declaration.kind.as_str(), declarators.join(", "));
+  if !names.is_empty() {
+    result.push_str(&format!("\nexport {{ {} }};", names.join(", ")));
+  }
+
+  Some(result)
+}
+
+fn has_import_override(source: &str, overrides: &ImportOverrides) -> bool {
+  let stripped = source.split(['?', '#']).next().unwrap_or(source);
+  let value = overrides.get(source).or_else(|| {
+    (stripped != source)
+      .then(|| overrides.get(stripped))
+      .flatten()
+  });
+  matches!(
+    value,
+    Some(ImportOverride::Mock(_) | ImportOverride::NoShake)
+  )
+}
+
+// The same as `shakeOxcToESM`: keeps the requested exports and everything they depend on
+pub fn shake_program<'a>(
+  filename: &str,
+  program: &'a Program<'a>,
+  source_text: &str,
+  symbols: &'a SymbolTable,
+  is_es_module: bool,
+  options: &ShakerOptions,
+) -> WywResult<ShakerOutput> {
+  let collected =
+    collect_exports_and_imports_from_program(program, source_text, symbols, is_es_module, None);
+
+  let mut owners = HashMap::new();
+  let mut imports = HashSet::new();
+  let mut facts = vec![];
+  for (idx, statement) in program.body.iter().enumerate() {
+    let mut statement_facts = StatementFacts {
+      export_names: statement_export_names(statement),
+      ..Default::default()
+    };
+
+    for ident in statement_bindings(statement) {
+      let Some(symbol_id) = ident.symbol_id.get() else {
+        continue;
+      };
+
+      owners.entry(symbol_id).or_insert(idx);
+      if let Statement::ImportDeclaration(_) = statement {
+        imports.insert(symbol_id);
+      }
+
+      statement_facts
+        .bindings
+        .push((symbol_id, ident.name.to_string()));
+    }
+
+    if let Statement::ImportDeclaration(declaration) = statement {
+      statement_facts.side_effect_import = !declaration.import_kind.is_type()
+        && declaration
+          .specifiers
+          .as_ref()
+          .map_or(true, |specifiers| specifiers.is_empty());
+    }
+
+    // `exports.foo = …` in CommonJS
+    let span = statement.span();
+    let cjs_exports = collected.exports.iter().filter(|_| !is_es_module);
+    for (name, local) in cjs_exports {
+      let is_inside = span.start <= local.span.start && local.span.end <= span.end;
+      if is_inside && !statement_facts.export_names.contains(name) {
+        statement_facts.export_names.push(name.clone());
+      }
+    }
+
+    facts.push(statement_facts);
+  }
+
+  let mut collector = FactsCollector {
+    symbols,
+    owners: &owners,
+    imports: &imports,
+    pure: pure_annotations(program, source_text),
+    functions: vec![],
+    facts,
+  };
+  walk(&mut collector, program, symbols);
+  let facts = collector.facts;
+
+  // Statements that should be kept if the binding (or any of its aliases) is used
+  let aliases = collect_aliases(program, symbols, &owners);
+  let mut effects: HashMap<SymbolId, Vec<usize>> = HashMap::new();
+  for (idx, statement) in facts.iter().enumerate() {
+    let mut roots = statement
+      .mutations
+      .iter()
+      .chain(&statement.invocation_roots)
+      .copied()
+      .collect::<HashSet<_>>();
+    for callee in &statement.local_calls {
+      roots.extend(&facts[owners[callee]].nested_mutations);
+    }
+
+    if statement.imported_call || roots.iter().any(|root| imports.contains(root)) {
+      roots.extend(&imports);
+    }
+
+    for root in roots {
+      effects.entry(aliases.find(root)).or_default().push(idx);
+    }
+  }
+
+  let requested = options
+    .only_exports
+    .iter()
+    .map(String::as_str)
+    .collect::<HashSet<_>>();
+  let keep_all = requested.contains("*");
+  let keep_wildcards = !requested.is_empty() && !requested.contains("side-effect");
+
+  let mut live = vec![false; facts.len()];
+  let mut live_exports = vec![false; facts.len()];
+  let mut live_bindings = HashSet::new();
+  let mut visited_aliases = HashSet::new();
+  let mut queue = VecDeque::new();
+  let mut binding_queue = VecDeque::new();
+
+  for (idx, statement) in facts.iter().enumerate() {
+    let selected = statement
+      .export_names
+      .iter()
+      .filter(|name| {
+        keep_all || (*name == "*" && keep_wildcards) || requested.contains(name.as_str())
+      })
+      .collect::<Vec<_>>();
+    if !selected.is_empty() {
+      live[idx] = true;
+      live_exports[idx] = true;
+      queue.push_back(idx);
+
+      let direct = statement
+        .bindings
+        .iter()
+        .filter(|(_, name)| selected.contains(&name))
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+      if direct.is_empty() && selected.iter().any(|name| *name == "default") {
+        binding_queue.extend(statement.bindings.iter().map(|(id, _)| *id));
+      } else {
+        binding_queue.extend(direct);
+      }
+    }
+
+    let keep_import = statement.side_effect_import
+      && (requested.contains("side-effect")
+        || options.keep_side_effects
+        || matches!(
+          &program.body[idx],
+          Statement::ImportDeclaration(declaration)
+            if has_import_override(&declaration.source.value, &options.import_overrides)
+        ));
+    let keep_effect = statement.unbound_effect && statement.export_names.is_empty();
+    if (keep_import || keep_effect) && !live[idx] {
+      live[idx] = true;
+      queue.push_back(idx);
+    }
+  }
+
+  loop {
+    if let Some(idx) = queue.pop_front() {
+      binding_queue.extend(facts[idx].references.iter().copied());
+      continue;
+    }
+
+    let Some(binding) = binding_queue.pop_front() else {
+      break;
+    };
+
+    if !live_bindings.insert(binding) {
+      continue;
+    }
+
+    let mut dependents = owners
+      .get(&binding)
+      .copied()
+      .into_iter()
+      .collect::<Vec<_>>();
+    let alias = aliases.find(binding);
+    if visited_aliases.insert(alias) {
+      dependents.extend(effects.get(&alias).into_iter().flatten());
+    }
+
+    for idx in dependents {
+      if !live[idx] {
+        live[idx] = true;
+        queue.push_back(idx);
+      }
+    }
+  }
+
+  let selected_names = facts
+    .iter()
+    .flat_map(|statement| &statement.export_names)
+    .map(String::as_str)
+    .filter(|name| keep_all || requested.contains(name))
+    .collect::<Vec<_>>();
+
+  let mut edits = SourceEdits::new(source_text);
+  for (idx, statement) in program.body.iter().enumerate() {
+    if !live[idx] {
+      edits.remove(statement.span());
+      continue;
+    }
+
+    match statement {
+      Statement::ImportDeclaration(declaration) => {
+        if let Some(clause) = import_clause(declaration, source_text, &live_bindings) {
+          edits.overwrite(
+            Span::new(declaration.span.start, declaration.source.span.start),
+            clause,
+          );
+        }
+      }
+      Statement::ExportNamedDeclaration(export) => {
+        let Some(declaration) = &export.declaration else {
+          continue;
+        };
+
+        let is_preval = facts[idx]
+          .export_names
+          .iter()
+          .any(|name| name == PREVAL_EXPORT);
+        if is_preval {
+          continue;
+        }
+
+        if !live_exports[idx] {
+          edits.remove(Span::new(export.span.start, declaration.span().start));
+        } else if let Declaration::VariableDeclaration(declaration) = declaration {
+          if let Some(code) = split_exported_declaration(declaration, source_text, &selected_names)
+          {
+            edits.overwrite(export.span, code);
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+
+  let code = edits.to_code();
+  let imports = collect_exports_and_imports(filename, &code, None)?.import_map();
+  Ok(ShakerOutput { code, imports })
+}
+
+pub fn shake(
+  filename: &str,
+  source_text: &str,
+  options: &ShakerOptions,
+) -> WywResult<ShakerOutput> {
+  let allocator = Allocator::default();
+  let source_type = SourceType::from_path(filename)
+    .unwrap_or_default()
+    .with_unambiguous(true);
+  let ret = Parser::new(&allocator, source_text, source_type).parse();
+  if let Some(error) = ret.errors.first() {
+    return Err(parse_error(filename, error));
+  }
+
+  let program = ret.program;
+  let is_es_module = program.source_type.is_module();
+  let (symbols, _) = SemanticBuilder::new()
+    .build(&program)
+    .semantic
+    .into_symbol_table_and_scope_tree();
+
+  shake_program(
+    filename,
+    &program,
+    source_text,
+    &symbols,
+    is_es_module,
+    options,
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn run(only_exports: &[&str], source_text: &str) -> ShakerOutput {
+    let options = ShakerOptions {
+      only_exports: only_exports.iter().map(|name| name.to_string()).collect(),
+      ..Default::default()
+    };
+    shake("index.js", source_text, &options).unwrap()
+  }
+
+  #[test]
+  fn test_preval_dependencies() {
+    let output = run(
+      &["__wywPreval"],
+      r#"
+        import * as RAC from 'react-aria-components';
+        import { foo1, foo2 } from './foo';
+        export const activeClass = "s1gxjcbn";
+        const _exp = /*#__PURE__*/() => activeClass + foo1;
+        export const __wywPreval = {
+          _exp: _exp,
+        };
+        export function Button(props) {
+          return RAC.Button(props);
+        }
+      "#,
+    );
+
+    assert!(output
+      .code
+      .contains("\n        const activeClass = \"s1gxjcbn\";"));
+    assert!(output.code.contains("const _exp ="));
+    assert!(output.code.contains("export const __wywPreval"));
+    assert!(output.code.contains("import { foo1 } from './foo';"));
+    assert!(!output.code.contains("Button"));
+    assert!(!output.code.contains("react-aria-components"));
+    assert_eq!(
+      output.imports,
+      [("./foo".to_string(), vec!["foo1".to_string()])]
+    );
+  }
+
+  #[test]
+  fn test_shadowing_and_split() {
+    let output = run(
+      &["live", "a", "__wywPreval"],
+      r#"
+        import { source } from './source';
+        export function live(source) {
+          return source;
+        }
+        export const __wywPreval = {}, dead = 1;
+        export const a = 1, b = 2, c = 3;
+      "#,
+    );
+
+    assert!(output.code.contains("export function live(source)"));
+    assert!(!output.code.contains("./source"));
+    assert!(output
+      .code
+      .contains("export const __wywPreval = {}, dead = 1;"));
+    assert!(output
+      .code
+      .contains("const a = 1, b = 2, c = 3;\nexport { a };"));
+  }
+
+  #[test]
+  fn test_mutations() {
+    let output = run(
+      &["__wywPreval"],
+      r#"
+        import { source } from './tokens';
+        const unused = {};
+        const { expect } = __STORYBOOK_MODULE_TEST__;
+        export const Primary = {};
+        Primary.parameters = { ...Primary.parameters };
+        const alias = source;
+        const { selected = alias } = {};
+        selected.width = 400;
+        Object.assign(unused, {});
+        const { width } = source;
+        const register = (value) => { source.registered = value; };
+        register(1);
+        const _exp = () => width;
+        export const __wywPreval = {
+          _exp,
+        };
+      "#,
+    );
+
+    assert!(!output.code.contains("__STORYBOOK_MODULE_TEST__"));
+    assert!(!output.code.contains("Primary"));
+    assert!(output.code.contains("const alias = source"));
+    assert!(output.code.contains("const { selected = alias }"));
+    assert!(output.code.contains("selected.width = 400"));
+    assert!(output.code.contains("register(1)"));
+    assert!(!output.code.contains("unused"));
+  }
+
+  #[test]
+  fn test_pure_calls() {
+    let output = run(
+      &["__wywPreval"],
+      r#"
+        const theme = { color: 'red' };
+        const dead = /*#__PURE__*/ freeze(theme);
+        const effect = extend(theme);
+        console.log('loaded');
+        export const __wywPreval = {
+          color: () => theme.color,
+        };
+      "#,
+    );
+
+    assert!(!output.code.contains("dead"));
+    assert!(output.code.contains("const effect = extend(theme);"));
+    assert!(output.code.contains("console.log('loaded');"));
+  }
+
+  #[test]
+  fn test_side_effect_imports() {
+    let source_text = r#"
+      import '@radix-ui/react-tooltip';
+      export * from './reexported';
+      export const __wywPreval = {};
+    "#;
+
+    let dropped = run(&["__wywPreval"], source_text);
+    assert!(!dropped.code.contains("@radix-ui/react-tooltip"));
+    assert_eq!(
+      dropped.imports,
+      [("./reexported".to_string(), vec!["*".to_string()])]
+    );
+
+    let kept = run(&["side-effect"], source_text);
+    assert_eq!(
+      kept.imports,
+      [(
+        "@radix-ui/react-tooltip".to_string(),
+        vec!["side-effect".to_string()]
+      )]
+    );
+
+    let options = ShakerOptions {
+      only_exports: vec!["__wywPreval".to_string()],
+      import_overrides: ImportOverrides::new([(
+        "@radix-ui/react-tooltip".to_string(),
+        ImportOverride::NoShake,
+      )]),
+      ..Default::default()
+    };
+    let overridden = shake("index.js", source_text, &options).unwrap();
+    assert!(overridden
+      .code
+      .contains("import '@radix-ui/react-tooltip';"));
+  }
+
+  #[test]
+  fn test_commonjs() {
+    let output = run(
+      &["__wywPreval"],
+      r#"
+        exports.Button = () => 'button';
+        exports.__wywPreval = {
+          Button: exports.Button,
+        };
+      "#,
+    );
+
+    assert!(output.code.contains("exports.__wywPreval ="));
+    assert!(!output.code.contains("exports.Button = () => 'button'"));
+  }
+}
@@ -0,0 +1,560 @@
+use crate::call_sites::CallSiteCollector;
+use crate::imports::collect_processor_imports;
+use crate::resolver::ModuleResolver;
+use crate::static_values::{ModuleLoader, StaticValue, StaticValueResolver, Unresolved};
+use crate::template_dependencies::{collect_template_dependencies, ExpressionKind};
+use crate::{parse_error, resolve_bindings};
+use oxc::allocator::Allocator;
+use oxc::parser::Parser;
+use oxc::span::{GetSpan, SourceType, Span};
+use oxc_semantic::{SemanticBuilder, SymbolId};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use wyw_processor::error::WywResult;
+use wyw_processor::registry::{ImportBinding, Imported, ProcessorRegistry, TagSource};
+use wyw_processor::static_semantics::{
+  can_skip_file_evaluation, InterpolationKind, StaticSemantics,
+};
+use wyw_traverse::local_identifier::LocalIdentifier;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StaticNeedReason {
+  // An imported binding is used in an interpolation that could not be resolved statically
+  ProcessorStaticInterpolation,
+  // A local binding is used in an interpolation that could not be resolved statically
+  UnresolvedStaticValue,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StaticNeed {
+  // An export of a dependency, `*` for a whole namespace
+  Export {
+    importer: String,
+    source: String,
+    name: String,
+    reason: StaticNeedReason,
+  },
+  // Bindings of the entrypoint itself that have to be evaluated
+  Eval {
+    importer: String,
+    source: String,
+    only: Vec<String>,
+    reason: StaticNeedReason,
+  },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StaticNeedRequestKind {
+  Dependency,
+  Eval,
+}
+
+// Needs merged by the module that has to provide them
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StaticNeedRequest {
+  pub importer: String,
+  pub source: String,
+  pub kind: StaticNeedRequestKind,
+  pub only: Vec<String>,
+  pub reasons: Vec<StaticNeedReason>,
+}
+
+#[derive(Debug, Default)]
+pub struct StaticEnv {
+  // Resolved files that were read to compute the values
+  pub dependencies: Vec<String>,
+  // Values of interpolations by their spans. The same code can mean different bindings
+  // in different scopes, so it can't be a key.
+  pub values: BTreeMap<Span, StaticValue>,
+  // Why an interpolation could not be resolved, by its span
+  pub unresolved: BTreeMap<Span, Unresolved>,
+}
+
+#[derive(Debug)]
+pub struct ProcessorUsagePlan {
+  pub processor: String,
+  pub tag_source: TagSource,
+  pub local: String,
+  pub span: Span,
+  pub semantics: StaticSemantics,
+  pub interpolations: Vec<InterpolationKind>,
+  // Keys of `StaticEnv::values` used by the call site
+  pub static_values: Vec<Span>,
+}
+
+impl ProcessorUsagePlan {
+  pub fn can_skip_evaluation(&self) -> bool {
+    self
+      .semantics
+      .can_skip_evaluation(self.interpolations.iter().copied())
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StaticPlanAttribution {
+  pub need_count: usize,
+  pub need_request_count: usize,
+  pub runtime_dependency_count: usize,
+  pub static_value_count: usize,
+  pub unresolved_count: usize,
+  pub usage_count: usize,
+}
+
+// The same as `StaticPlan` from `static-plan/types.ts`
+#[derive(Debug)]
+pub struct StaticPlan {
+  pub filename: String,
+  pub env: StaticEnv,
+  pub needs: Vec<StaticNeed>,
+  pub need_requests: Vec<StaticNeedRequest>,
+  pub processor_usages: Vec<ProcessorUsagePlan>,
+  pub attribution: StaticPlanAttribution,
+}
+
+impl StaticPlan {
+  // `true` if none of the call sites needs the evaluator
+  pub fn can_skip_evaluation(&self) -> bool {
+    can_skip_file_evaluation(
+      self
+        .processor_usages
+        .iter()
+        .map(|usage| (usage.semantics, usage.interpolations.iter().copied())),
+    )
+  }
+}
+
+fn add_unique<T: PartialEq>(items: &mut Vec<T>, item: T) {
+  if !items.contains(&item) {
+    items.push(item);
+  }
+}
+
+// The same as `planStaticNeedRequests`
+pub fn plan_static_need_requests(needs: &[StaticNeed]) -> Vec<StaticNeedRequest> {
+  let mut requests = Vec::<StaticNeedRequest>::new();
+  for need in needs {
+    let (importer, source, only, reason, kind) = match need {
+      StaticNeed::Export {
+        importer,
+        source,
+        name,
+        reason,
+      } => (
+        importer,
+        source,
+        std::slice::from_ref(name),
+        reason,
+        StaticNeedRequestKind::Dependency,
+      ),
+      StaticNeed::Eval {
+        importer,
+        source,
+        only,
+        reason,
+      } => (
+        importer,
+        source,
+        only.as_slice(),
+        reason,
+        StaticNeedRequestKind::Eval,
+      ),
+    };
+    if only.is_empty() {
+      continue;
+    }
+
+    let idx = match requests.iter().position(|request| {
+      request.kind == kind && request.importer == *importer && request.source == *source
+    }) {
+      Some(idx) => idx,
+      None => {
+        requests.push(StaticNeedRequest {
+          importer: importer.clone(),
+          source: source.clone(),
+          kind,
+          only: vec![],
+          reasons: vec![],
+        });
+        requests.len() - 1
+      }
+    };
+
+    let request = &mut requests[idx];
+    for name in only {
+      add_unique(&mut request.only, name.clone());
+    }
+    add_unique(&mut request.reasons, *reason);
+  }
+
+  requests
+}
+
+// Remembers every file that was read while resolving static values
+struct RecordingLoader<'l> {
+  loader: &'l dyn ModuleLoader,
+  loaded: RefCell<Vec<String>>,
+}
+
+impl ModuleResolver for RecordingLoader<'_> {
+  fn resolve(&self, importer: &str, source: &str) -> WywResult<String> {
+    self.loader.resolve(importer, source)
+  }
+}
+
+impl ModuleLoader for RecordingLoader<'_> {
+  fn load(&self, importer: &str, source: &str) -> WywResult<(String, String)> {
+    let (filename, code) = self.loader.load(importer, source)?;
+    add_unique(&mut self.loaded.borrow_mut(), filename.clone());
+    Ok((filename, code))
+  }
+}
+
+fn export_need(filename: &str, binding: &ImportBinding, property: Option<&str>) -> StaticNeed {
+  let name = match (&binding.imported, property) {
+    (Imported::Named(name), _) => name.clone(),
+    (Imported::Default, _) => "default".to_string(),
+    (Imported::Namespace, Some(property)) => property.to_string(),
+    (Imported::Namespace, None) => "*".to_string(),
+  };
+
+  StaticNeed::Export {
+    importer: filename.to_string(),
+    source: binding.source.clone(),
+    name,
+    reason: StaticNeedReason::ProcessorStaticInterpolation,
+  }
+}
+
+// Decides which interpolations of processor call sites are known statically
+// and what has to be evaluated for the rest of them
+pub fn build_static_plan(
+  filename: &str,
+  source_text: &str,
+  registry: &ProcessorRegistry,
+  loader: &dyn ModuleLoader,
+) -> WywResult<StaticPlan> {
+  let allocator = Allocator::default();
+  let source_type = SourceType::from_path(filename)
+    .unwrap_or_default()
+    .with_unambiguous(true);
+  let ret = Parser::new(&allocator, source_text, source_type).parse();
+  if let Some(error) = ret.errors.first() {
+    return Err(parse_error(filename, error));
+  }
+
+  let program = ret.program;
+  let (symbols, scopes) = SemanticBuilder::new()
+    .build(&program)
+    .semantic
+    .into_symbol_table_and_scope_tree();
+
+  let loader = RecordingLoader {
+    loader,
+    loaded: RefCell::default(),
+  };
+
+  let imports = collect_processor_imports(&program);
+  let bindings = imports
+    .iter()
+    .map(|import| (import.symbol_id, &import.binding))
+    .collect::<HashMap<SymbolId, _>>();
+  let mut resolved_bindings = imports
+    .iter()
+    .map(|import| import.binding.clone())
+    .collect::<Vec<_>>();
+  resolve_bindings(filename, registry, &loader, &mut resolved_bindings);
+  let defined_tags = registry.defined_tags(&resolved_bindings, Some(filename));
+
+  let mut collector =
+    CallSiteCollector::new(&defined_tags, imports.iter().map(|import| import.symbol_id));
+  wyw_traverse::walk(&mut collector, &program, &symbols);
+  let call_sites = collector.call_sites;
+
+  let dependencies =
+    collect_template_dependencies(call_sites.iter().map(|site| site.node), &program, &symbols);
+  let resolver = StaticValueResolver::new(&loader);
+
+  let mut env = StaticEnv::default();
+  let mut needs = vec![];
+  let mut eval_names = vec![];
+  let mut processor_usages = vec![];
+  for (call_site, template) in call_sites.iter().zip(&dependencies) {
+    let expressions = &call_site.node.quasi.expressions;
+    let results = resolver.resolve_expressions(filename, &program, &symbols, expressions);
+
+    let mut interpolations = vec![];
+    let mut static_values = vec![];
+    for ((expression, dependency), result) in
+      expressions.iter().zip(&template.expressions).zip(results)
+    {
+      let kind = match (dependency.kind, result) {
+        (ExpressionKind::Literal, _) => InterpolationKind::Literal,
+        (ExpressionKind::Function, _) => InterpolationKind::RuntimeCallback,
+        (_, Ok(value)) => {
+          add_unique(&mut static_values, expression.span());
+          env.values.insert(expression.span(), value);
+          InterpolationKind::StaticBinding
+        }
+        (_, Err(unresolved)) => {
+          env.unresolved.insert(expression.span(), unresolved);
+
+          for identifier in &dependency.identifiers {
+            let (symbol, property) = match identifier {
+              LocalIdentifier::Identifier(symbol) => (symbol, None),
+              LocalIdentifier::MemberExpression(symbol, property) => {
+                (symbol, Some(property.as_str()))
+              }
+            };
+
+            if let Some(binding) = bindings.get(&symbol.symbol_id) {
+              add_unique(&mut needs, export_need(filename, binding, property));
+            } else if symbols.get_scope_id(symbol.symbol_id) == scopes.root_scope_id() {
+              // Bindings of nested scopes, e.g. params of a wrapping function, can't be requested
+              add_unique(&mut eval_names, symbol.name.clone());
+            }
+          }
+
+          InterpolationKind::Dynamic
+        }
+      };
+      interpolations.push(kind);
+    }
+
+    let processor = &call_site.tag.processor;
+    processor_usages.push(ProcessorUsagePlan {
+      processor: processor.id().to_string(),
+      tag_source: call_site.tag.tag_source.clone(),
+      local: symbols.get_name(call_site.symbol_id).to_string(),
+      span: call_site.node.span,
+      semantics: processor.static_semantics(),
+      interpolations,
+      static_values,
+    });
+  }
+
+  if !eval_names.is_empty() {
+    needs.push(StaticNeed::Eval {
+      importer: filename.to_string(),
+      source: filename.to_string(),
+      only: eval_names,
+      reason: StaticNeedReason::UnresolvedStaticValue,
+    });
+  }
+
+  env.dependencies = loader.loaded.into_inner();
+  let need_requests = plan_static_need_requests(&needs);
+  let attribution = StaticPlanAttribution {
+    need_count: needs.len(),
+    need_request_count: need_requests.len(),
+    runtime_dependency_count: env.dependencies.len(),
+    static_value_count: env.values.len(),
+    unresolved_count: env.unresolved.len(),
+    usage_count: processor_usages.len(),
+  };
+
+  Ok(StaticPlan {
+    filename: filename.to_string(),
+    env,
+    needs,
+    need_requests,
+    processor_usages,
+    attribution,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::contains;
+  use std::sync::Arc;
+  use wyw_processor::diagnostics::Diagnostics;
+  use wyw_processor::error::WywError;
+  use wyw_processor::{Processor, ProcessorOutput, ProcessorParams};
+
+  struct StyledProcessor;
+
+  impl Processor for StyledProcessor {
+    fn id(&self) -> &str {
+      "styled"
+    }
+
+    fn transform(
+      &self,
+      _params: &ProcessorParams,
+      _diagnostics: &mut Diagnostics,
+    ) -> Option<ProcessorOutput> {
+      None
+    }
+
+    fn static_semantics(&self) -> StaticSemantics {
+      StaticSemantics::Pure {
+        runtime_callbacks: true,
+      }
+    }
+  }
+
+  struct MemoryLoader;
+
+  impl ModuleResolver for MemoryLoader {
+    fn resolve(&self, importer: &str, source: &str) -> WywResult<String> {
+      self.load(importer, source).map(|(filename, _)| filename)
+    }
+  }
+
+  impl ModuleLoader for MemoryLoader {
+    fn load(&self, _importer: &str, source: &str) -> WywResult<(String, String)> {
+      let code = match source {
+        "./theme" => "export const colors = { primary: 'red' }; export const gap = 4;",
+        "./runtime" => "export const size = window.innerWidth;",
+        _ => return Err(WywError::new("wyw/resolve-error", "Cannot resolve")),
+      };
+
+      Ok((format!("/{}.ts", &source[2..]), code.to_string()))
+    }
+  }
+
+  fn codes<'s, T>(source_text: &'s str, map: &BTreeMap<Span, T>) -> Vec<&'s str> {
+    map
+      .keys()
+      .map(|span| span.source_text(source_text))
+      .collect()
+  }
+
+  fn plan(source_text: &str) -> StaticPlan {
+    let mut registry = ProcessorRegistry::new();
+    registry
+      .register(Arc::new(StyledProcessor))
+      .add_tag("lib", "css", "styled");
+    build_static_plan("/index.ts", source_text, &registry, &MemoryLoader).unwrap()
+  }
+
+  #[test]
+  fn test_static_plan() {
+    let source_text = r#"
+      import { css } from "lib";
+      import { colors, gap } from "./theme";
+      const double = gap * 2;
+      export const a = css`
+        color: ${colors.primary};
+        gap: ${double}px;
+        width: ${(props) => props.width};
+        border: ${"1px solid"};
+      `;
+      "#;
+    let plan = plan(source_text);
+
+    assert!(plan.can_skip_evaluation());
+    assert!(plan.needs.is_empty());
+    assert_eq!(plan.env.dependencies, ["/theme.ts"]);
+    assert_eq!(
+      codes(source_text, &plan.env.values),
+      ["colors.primary", "double"]
+    );
+    assert_eq!(
+      plan.env.values.values().nth(1),
+      Some(&StaticValue::Number(8.0))
+    );
+
+    let usage = &plan.processor_usages[0];
+    assert_eq!(usage.local, "css");
+    assert_eq!(
+      usage.interpolations,
+      [
+        InterpolationKind::StaticBinding,
+        InterpolationKind::StaticBinding,
+        InterpolationKind::RuntimeCallback,
+        InterpolationKind::Literal,
+      ]
+    );
+  }
+
+  #[test]
+  fn test_needs() {
+    let source_text = r#"
+      import { css } from "lib";
+      import * as runtime from "./runtime";
+      import { colors } from "./theme";
+      let counter = 0;
+      counter++;
+      export const a = css`
+        width: ${runtime.size}px;
+        color: ${colors.primary};
+        content: "${counter}";
+      `;
+      "#;
+    let plan = plan(source_text);
+
+    assert!(!plan.can_skip_evaluation());
+    assert_eq!(
+      codes(source_text, &plan.env.unresolved),
+      ["runtime.size", "counter"]
+    );
+    let unresolved = plan.env.unresolved.values().next().unwrap();
+    assert_eq!(unresolved.filename, "/runtime.ts");
+    assert_eq!(
+      plan.needs,
+      [
+        StaticNeed::Export {
+          importer: "/index.ts".to_string(),
+          source: "./runtime".to_string(),
+          name: "size".to_string(),
+          reason: StaticNeedReason::ProcessorStaticInterpolation,
+        },
+        StaticNeed::Eval {
+          importer: "/index.ts".to_string(),
+          source: "/index.ts".to_string(),
+          only: vec!["counter".to_string()],
+          reason: StaticNeedReason::UnresolvedStaticValue,
+        },
+      ]
+    );
+    assert_eq!(plan.need_requests.len(), 2);
+    assert_eq!(plan.attribution.static_value_count, 1);
+  }
+
+  #[test]
+  fn test_same_code_in_different_scopes() {
+    let source_text = r#"
+      import { css } from "lib";
+      const size = 4;
+      export const a = css`width: ${size}px;`;
+      export function make(size) {
+        return css`width: ${size}px;`;
+      }
+      "#;
+    let plan = plan(source_text);
+
+    let [top_level, param] = [plan.processor_usages[0].span, plan.processor_usages[1].span];
+    let values = plan.env.values.keys().collect::<Vec<_>>();
+    let unresolved = plan.env.unresolved.keys().collect::<Vec<_>>();
+    assert_eq!(values.len(), 1);
+    assert_eq!(unresolved.len(), 1);
+    assert!(contains(top_level, *values[0]));
+    assert!(contains(param, *unresolved[0]));
+    assert_eq!(plan.processor_usages[0].static_values, [*values[0]]);
+    assert!(plan.processor_usages[1].static_values.is_empty());
+  }
+
+  #[test]
+  fn test_plan_static_need_requests() {
+    let need = |source: &str, name: &str| StaticNeed::Export {
+      importer: "/index.ts".to_string(),
+      source: source.to_string(),
+      name: name.to_string(),
+      reason: StaticNeedReason::ProcessorStaticInterpolation,
+    };
+
+    let requests =
+      plan_static_need_requests(&[need("./a", "x"), need("./b", "y"), need("./a", "z")]);
+    assert_eq!(
+      requests
+        .iter()
+        .map(|request| (request.source.as_str(), request.only.clone()))
+        .collect::<Vec<_>>(),
+      [
+        ("./a", vec!["x".to_string(), "z".to_string()]),
+        ("./b", vec!["y".to_string()]),
+      ]
+    );
+  }
+}
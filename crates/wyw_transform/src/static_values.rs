@@ -0,0 +1,1612 @@
+use crate::exports_and_imports::{binding_identifiers, reference_symbol};
+use crate::resolver::{ModuleResolver, NativeResolver};
+use crate::shaker::object_mutation_target;
+use oxc::allocator::Allocator;
+use oxc::ast::ast::{
+  Argument, ArrayExpressionElement, ArrowFunctionExpression, AssignmentExpression,
+  AssignmentTarget, BinaryOperator, BindingPattern, BindingPatternKind, CallExpression,
+  ChainElement, Class, Declaration, ExportDefaultDeclarationKind, Expression, FormalParameters,
+  Function, IdentifierReference, ImportDeclaration, ImportDeclarationSpecifier, LogicalOperator,
+  MemberExpression, ModuleExportName, ObjectPropertyKind, Program, PropertyKind,
+  SimpleAssignmentTarget, Statement, UnaryExpression, UnaryOperator, UpdateExpression,
+  VariableDeclaration,
+};
+use oxc::parser::Parser;
+use oxc::span::{GetSpan, SourceType, Span};
+use oxc::syntax::number::ToJsString;
+use oxc_semantic::{SemanticBuilder, SymbolId, SymbolTable};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use wyw_processor::error::{WywError, WywResult};
+use wyw_traverse::{walk, EnterAction, TraverseCtx, TraverseHooks};
+
+// Calls and bindings that are resolved recursively
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StaticValue {
+  Undefined,
+  Null,
+  Boolean(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<StaticValue>),
+  // Properties in the order of definition
+  Object(Vec<(String, StaticValue)>),
+}
+
+fn number_to_string(value: f64) -> String {
+  value.to_js_string()
+}
+
+pub(crate) fn quote(value: &str) -> String {
+  let mut result = String::with_capacity(value.len() + 2);
+  result.push('"');
+  for ch in value.chars() {
+    match ch {
+      '"' => result.push_str("\\\""),
+      '\\' => result.push_str("\\\\"),
+      '\n' => result.push_str("\\n"),
+      '\r' => result.push_str("\\r"),
+      '\t' => result.push_str("\\t"),
+      '\u{2028}' => result.push_str("\\u2028"),
+      '\u{2029}' => result.push_str("\\u2029"),
+      ch if ch.is_control() => result.push_str(&format!("\\u{:04x}", ch as u32)),
+      ch => result.push(ch),
+    }
+  }
+  result.push('"');
+  result
+}
+
+// `ToNumber` for strings
+fn parse_number(value: &str) -> f64 {
+  let value = value.trim();
+  if value.is_empty() {
+    return 0.0;
+  }
+
+  let radix = match value.get(..2) {
+    Some("0x" | "0X") => 16,
+    Some("0o" | "0O") => 8,
+    Some("0b" | "0B") => 2,
+    _ => 10,
+  };
+  if radix != 10 {
+    return u64::from_str_radix(&value[2..], radix).map_or(f64::NAN, |value| value as f64);
+  }
+
+  match value {
+    "Infinity" | "+Infinity" => f64::INFINITY,
+    "-Infinity" => f64::NEG_INFINITY,
+    // Rust also accepts `inf` and `nan`
+    value
+      if value
+        .bytes()
+        .any(|byte| byte.is_ascii_alphabetic() && byte != b'e' && byte != b'E') =>
+    {
+      f64::NAN
+    }
+    value => value.parse().unwrap_or(f64::NAN),
+  }
+}
+
+fn to_int32(value: f64) -> i32 {
+  if !value.is_finite() {
+    return 0;
+  }
+
+  (value.trunc() % 4_294_967_296.0) as i64 as u32 as i32
+}
+
+impl StaticValue {
+  pub fn type_of(&self) -> &'static str {
+    match self {
+      Self::Undefined => "undefined",
+      Self::Boolean(_) => "boolean",
+      Self::Number(_) => "number",
+      Self::String(_) => "string",
+      Self::Null | Self::Array(_) | Self::Object(_) => "object",
+    }
+  }
+
+  pub(crate) fn is_nullish(&self) -> bool {
+    matches!(self, Self::Undefined | Self::Null)
+  }
+
+  pub(crate) fn is_truthy(&self) -> bool {
+    match self {
+      Self::Undefined | Self::Null => false,
+      Self::Boolean(value) => *value,
+      Self::Number(value) => *value != 0.0 && !value.is_nan(),
+      Self::String(value) => !value.is_empty(),
+      Self::Array(_) | Self::Object(_) => true,
+    }
+  }
+
+  // `ToString`, objects are converted with the default `toString`
+  pub fn to_js_string(&self) -> String {
+    match self {
+      Self::Undefined => "undefined".to_string(),
+      Self::Null => "null".to_string(),
+      Self::Boolean(value) => value.to_string(),
+      Self::Number(value) => number_to_string(*value),
+      Self::String(value) => value.clone(),
+      Self::Array(items) => items
+        .iter()
+        .map(|item| match item {
+          Self::Undefined | Self::Null => String::new(),
+          item => item.to_js_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(","),
+      Self::Object(_) => "[object Object]".to_string(),
+    }
+  }
+
+  fn to_number(&self) -> f64 {
+    match self {
+      Self::Undefined | Self::Object(_) => f64::NAN,
+      Self::Null => 0.0,
+      Self::Boolean(value) => f64::from(u8::from(*value)),
+      Self::Number(value) => *value,
+      Self::String(value) => parse_number(value),
+      Self::Array(_) => parse_number(&self.to_js_string()),
+    }
+  }
+
+  // The value of an own property, `undefined` if there is no such property
+  fn get(&self, key: &str) -> StaticValue {
+    match self {
+      Self::Object(properties) => properties
+        .iter()
+        .find(|(name, _)| name == key)
+        .map_or(Self::Undefined, |(_, value)| value.clone()),
+      Self::Array(items) if key == "length" => Self::Number(items.len() as f64),
+      Self::Array(items) => key
+        .parse::<usize>()
+        .ok()
+        .and_then(|idx| items.get(idx).cloned())
+        .unwrap_or(Self::Undefined),
+      Self::String(value) if key == "length" => Self::Number(value.encode_utf16().count() as f64),
+      Self::String(value) => key
+        .parse::<usize>()
+        .ok()
+        .and_then(|idx| value.encode_utf16().nth(idx))
+        .map_or(Self::Undefined, |unit| {
+          Self::String(String::from_utf16_lossy(&[unit]))
+        }),
+      _ => Self::Undefined,
+    }
+  }
+
+  fn set(properties: &mut Vec<(String, StaticValue)>, key: String, value: StaticValue) {
+    match properties.iter_mut().find(|(name, _)| *name == key) {
+      Some(property) => property.1 = value,
+      None => properties.push((key, value)),
+    }
+  }
+
+  // A JS literal that evaluates to the value
+  pub fn to_code(&self) -> String {
+    match self {
+      Self::Undefined => "undefined".to_string(),
+      Self::Number(value) if value.is_nan() => "NaN".to_string(),
+      Self::Number(value) if *value == 0.0 && value.is_sign_negative() => "-0".to_string(),
+      Self::String(value) => quote(value),
+      Self::Array(items) => format!(
+        "[{}]",
+        items
+          .iter()
+          .map(Self::to_code)
+          .collect::<Vec<_>>()
+          .join(", ")
+      ),
+      Self::Object(properties) => format!(
+        "{{{}}}",
+        properties
+          .iter()
+          .map(|(key, value)| format!("{}: {}", quote(key), value.to_code()))
+          .collect::<Vec<_>>()
+          .join(", ")
+      ),
+      value => value.to_js_string(),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UnresolvedReason {
+  // e.g. `new Foo()` or `await foo`
+  UnsupportedExpression(String),
+  // A global or an undeclared binding
+  UnknownIdentifier(String),
+  // A binding that is reassigned or mutated after the declaration
+  MutatedBinding(String),
+  // A class, a parameter outside of a call, a catch clause, etc.
+  UnsupportedBinding(String),
+  // Functions and classes can't be represented as static values
+  NonSerializable(String),
+  // The callee is not a local function that returns a single expression
+  UnsupportedCall(String),
+  // e.g. `null.foo` or `1 < {}`
+  TypeError(String),
+  MissingExport(String),
+  // The import can't be resolved, read or parsed
+  ImportFailed { source: String, message: String },
+  Cycle(String),
+  TooDeep,
+}
+
+impl Display for UnresolvedReason {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::UnsupportedExpression(code) => write!(f, "`{code}` is not supported"),
+      Self::UnknownIdentifier(name) => write!(f, "`{name}` is not declared in the module"),
+      Self::MutatedBinding(name) => write!(f, "`{name}` is changed after the declaration"),
+      Self::UnsupportedBinding(name) => write!(f, "the declaration of `{name}` is not supported"),
+      Self::NonSerializable(code) => write!(f, "`{code}` is not a serializable value"),
+      Self::UnsupportedCall(code) => write!(
+        f,
+        "`{code}` is not a call of a local function that returns an expression"
+      ),
+      Self::TypeError(message) => write!(f, "{message}"),
+      Self::MissingExport(name) => write!(f, "there is no `{name}` export"),
+      Self::ImportFailed { source, message } => write!(f, "cannot load {source}: {message}"),
+      Self::Cycle(name) => write!(f, "`{name}` depends on itself"),
+      Self::TooDeep => write!(f, "the value is nested too deeply"),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unresolved {
+  // The module where the resolution failed, it can be imported by the original one
+  pub filename: String,
+  pub span: Span,
+  pub reason: UnresolvedReason,
+}
+
+impl Display for Unresolved {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}:{}: {}", self.filename, self.span.start, self.reason)
+  }
+}
+
+pub type StaticResult = Result<StaticValue, Unresolved>;
+
+pub trait ModuleLoader: ModuleResolver {
+  // Returns the resolved filename and the code of `source` imported by `importer`
+  fn load(&self, importer: &str, source: &str) -> WywResult<(String, String)>;
+}
+
+impl ModuleLoader for NativeResolver {
+  fn load(&self, importer: &str, source: &str) -> WywResult<(String, String)> {
+    let resolved = self.resolve(importer, source)?;
+    let path = resolved.split(['?', '#']).next().unwrap_or_default();
+    let code = std::fs::read_to_string(path).map_err(|error| {
+      WywError::new("wyw/read-error", format!("Cannot read {path}: {error}"))
+        .with_filename(importer)
+    })?;
+
+    Ok((resolved, code))
+  }
+}
+
+#[derive(Clone)]
+enum PathKey {
+  Property(String),
+  Index(usize),
+  // The rest of an object pattern without the listed properties
+  ObjectRest(Vec<String>),
+  ArrayRest(usize),
+}
+
+#[derive(Clone)]
+struct PathStep<'a> {
+  key: PathKey,
+  default: Option<&'a Expression<'a>>,
+}
+
+enum Binding<'a> {
+  Variable {
+    init: Option<&'a Expression<'a>>,
+    // The path to the value in `init` for destructured bindings
+    path: Vec<PathStep<'a>>,
+  },
+  Function(&'a Function<'a>),
+  // Classes, destructuring with computed keys, etc.
+  Unsupported,
+  Import {
+    source: &'a str,
+    // `None` for namespace imports
+    imported: Option<&'a str>,
+  },
+}
+
+fn pattern_bindings<'a>(
+  pattern: &'a BindingPattern<'a>,
+  init: Option<&'a Expression<'a>>,
+  path: Vec<PathStep<'a>>,
+  bindings: &mut HashMap<SymbolId, Binding<'a>>,
+) {
+  let step = |key, default| {
+    let mut path = path.clone();
+    path.push(PathStep { key, default });
+    path
+  };
+
+  match &pattern.kind {
+    BindingPatternKind::BindingIdentifier(ident) => {
+      if let Some(symbol_id) = ident.symbol_id.get() {
+        bindings.insert(symbol_id, Binding::Variable { init, path });
+      }
+    }
+    // Defaults are attached to the last step by the parent pattern
+    BindingPatternKind::AssignmentPattern(assignment) => {
+      pattern_bindings(&assignment.left, init, path, bindings)
+    }
+    BindingPatternKind::ObjectPattern(object) => {
+      let mut names = vec![];
+      for property in &object.properties {
+        let (value, default) = match &property.value.kind {
+          BindingPatternKind::AssignmentPattern(assignment) => {
+            (&assignment.left, Some(&assignment.right))
+          }
+          _ => (&property.value, None),
+        };
+
+        match property.key.static_name().filter(|_| !property.computed) {
+          Some(name) => {
+            names.push(name.to_string());
+            let key = PathKey::Property(name.to_string());
+            pattern_bindings(value, init, step(key, default), bindings);
+          }
+          // Computed keys are not supported, so the bindings have no values
+          None => {
+            let mut idents = vec![];
+            binding_identifiers(value, &mut idents);
+            for ident in idents {
+              if let Some(symbol_id) = ident.symbol_id.get() {
+                bindings.insert(symbol_id, Binding::Unsupported);
+              }
+            }
+          }
+        }
+      }
+
+      if let Some(rest) = &object.rest {
+        let key = PathKey::ObjectRest(names);
+        pattern_bindings(&rest.argument, init, step(key, None), bindings);
+      }
+    }
+    BindingPatternKind::ArrayPattern(array) => {
+      for (idx, element) in array.elements.iter().enumerate() {
+        let Some(element) = element else {
+          continue;
+        };
+
+        let (value, default) = match &element.kind {
+          BindingPatternKind::AssignmentPattern(assignment) => {
+            (&assignment.left, Some(&assignment.right))
+          }
+          _ => (element, None),
+        };
+        pattern_bindings(value, init, step(PathKey::Index(idx), default), bindings);
+      }
+
+      if let Some(rest) = &array.rest {
+        let key = PathKey::ArrayRest(array.elements.len());
+        pattern_bindings(&rest.argument, init, step(key, None), bindings);
+      }
+    }
+  }
+}
+
+// Declarations of the module and the bindings that are changed after them
+struct BindingsCollector<'a> {
+  bindings: HashMap<SymbolId, Binding<'a>>,
+  mutated: HashSet<SymbolId>,
+}
+
+impl BindingsCollector<'_> {
+  fn mutate(&mut self, expression: &Expression, symbols: &SymbolTable) {
+    match expression.get_inner_expression() {
+      Expression::Identifier(ident) => {
+        if let Some(symbol_id) = reference_symbol(ident, symbols) {
+          self.mutated.insert(symbol_id);
+        }
+      }
+      expression => {
+        if let Some(member) = expression.as_member_expression() {
+          self.mutate(member.object(), symbols);
+        }
+      }
+    }
+  }
+
+  fn mutate_member(&mut self, member: Option<&MemberExpression>, symbols: &SymbolTable) {
+    if let Some(member) = member {
+      self.mutate(member.object(), symbols);
+    }
+  }
+}
+
+impl<'a> TraverseHooks<'a> for BindingsCollector<'a> {
+  fn enter_variable_declaration(
+    &mut self,
+    node: &'a VariableDeclaration<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    for declarator in &node.declarations {
+      pattern_bindings(
+        &declarator.id,
+        declarator.init.as_ref(),
+        vec![],
+        &mut self.bindings,
+      );
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_function(&mut self, node: &'a Function<'a>, _ctx: &mut TraverseCtx<'a>) -> EnterAction {
+    if let Some(symbol_id) = node.id.as_ref().and_then(|id| id.symbol_id.get()) {
+      self.bindings.insert(symbol_id, Binding::Function(node));
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_class(&mut self, node: &'a Class<'a>, _ctx: &mut TraverseCtx<'a>) -> EnterAction {
+    if let Some(symbol_id) = node.id.as_ref().and_then(|id| id.symbol_id.get()) {
+      self.bindings.insert(symbol_id, Binding::Unsupported);
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_import_declaration(
+    &mut self,
+    node: &'a ImportDeclaration<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let source = node.source.value.as_str();
+    for specifier in node.specifiers.iter().flatten() {
+      let (local, imported) = match specifier {
+        ImportDeclarationSpecifier::ImportSpecifier(specifier) => {
+          (&specifier.local, Some(specifier.imported.name().as_str()))
+        }
+        ImportDeclarationSpecifier::ImportDefaultSpecifier(specifier) => {
+          (&specifier.local, Some("default"))
+        }
+        ImportDeclarationSpecifier::ImportNamespaceSpecifier(specifier) => (&specifier.local, None),
+      };
+
+      if let Some(symbol_id) = local.symbol_id.get() {
+        self
+          .bindings
+          .insert(symbol_id, Binding::Import { source, imported });
+      }
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_identifier_reference(
+    &mut self,
+    node: &'a IdentifierReference<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let symbols = ctx.symbols();
+    let reference = node
+      .reference_id
+      .get()
+      .map(|reference_id| symbols.get_reference(reference_id));
+    if let Some(symbol_id) = reference
+      .filter(|reference| reference.is_write())
+      .and_then(|reference| reference.symbol_id())
+    {
+      self.mutated.insert(symbol_id);
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_assignment_expression(
+    &mut self,
+    node: &'a AssignmentExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if !matches!(node.left, AssignmentTarget::AssignmentTargetIdentifier(_)) {
+      self.mutate_member(node.left.as_member_expression(), ctx.symbols());
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_update_expression(
+    &mut self,
+    node: &'a UpdateExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if !matches!(
+      node.argument,
+      SimpleAssignmentTarget::AssignmentTargetIdentifier(_)
+    ) {
+      self.mutate_member(node.argument.as_member_expression(), ctx.symbols());
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_unary_expression(
+    &mut self,
+    node: &'a UnaryExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if node.operator == UnaryOperator::Delete {
+      self.mutate_member(
+        node.argument.get_inner_expression().as_member_expression(),
+        ctx.symbols(),
+      );
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_call_expression(
+    &mut self,
+    node: &'a CallExpression<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if let Some(target) = object_mutation_target(node) {
+      self.mutate(target, ctx.symbols());
+    }
+
+    EnterAction::Continue
+  }
+}
+
+// Resolves values across modules and caches exports
+pub struct StaticValueResolver<'l> {
+  loader: &'l dyn ModuleLoader,
+  // `None` while the export is being resolved
+  exports: RefCell<HashMap<(String, String), Option<StaticResult>>>,
+}
+
+struct Evaluator<'a, 'r> {
+  resolver: &'r StaticValueResolver<'r>,
+  filename: &'r str,
+  source_text: &'a str,
+  symbols: &'a SymbolTable,
+  bindings: HashMap<SymbolId, Binding<'a>>,
+  mutated: HashSet<SymbolId>,
+  values: HashMap<SymbolId, StaticResult>,
+  // Bindings that are being resolved
+  resolving: HashSet<SymbolId>,
+  // Arguments of the functions that are being called
+  scopes: Vec<HashMap<SymbolId, StaticValue>>,
+  depth: usize,
+}
+
+impl<'a, 'r> Evaluator<'a, 'r> {
+  fn new(
+    resolver: &'r StaticValueResolver<'r>,
+    filename: &'r str,
+    program: &'a Program<'a>,
+    symbols: &'a SymbolTable,
+  ) -> Self {
+    let mut collector = BindingsCollector {
+      bindings: HashMap::new(),
+      mutated: HashSet::new(),
+    };
+    walk(&mut collector, program, symbols);
+
+    Self {
+      resolver,
+      filename,
+      source_text: program.source_text,
+      symbols,
+      bindings: collector.bindings,
+      mutated: collector.mutated,
+      values: HashMap::new(),
+      resolving: HashSet::new(),
+      scopes: vec![],
+      depth: 0,
+    }
+  }
+
+  fn unresolved(&self, span: Span, reason: UnresolvedReason) -> Unresolved {
+    Unresolved {
+      filename: self.filename.to_string(),
+      span,
+      reason,
+    }
+  }
+
+  fn fail<T>(&self, span: Span, reason: UnresolvedReason) -> Result<T, Unresolved> {
+    Err(self.unresolved(span, reason))
+  }
+
+  fn code(&self, span: Span) -> String {
+    self.source_text[span.start as usize..span.end as usize].to_string()
+  }
+
+  fn unsupported<T>(&self, span: Span) -> Result<T, Unresolved> {
+    self.fail(
+      span,
+      UnresolvedReason::UnsupportedExpression(self.code(span)),
+    )
+  }
+
+  fn eval(&mut self, expression: &'a Expression<'a>) -> StaticResult {
+    let span = expression.span();
+    match expression.get_inner_expression() {
+      Expression::StringLiteral(literal) => Ok(StaticValue::String(literal.value.to_string())),
+      Expression::NumericLiteral(literal) => Ok(StaticValue::Number(literal.value)),
+      Expression::BooleanLiteral(literal) => Ok(StaticValue::Boolean(literal.value)),
+      Expression::NullLiteral(_) => Ok(StaticValue::Null),
+      Expression::TemplateLiteral(template) => {
+        let mut result = String::new();
+        for (idx, quasi) in template.quasis.iter().enumerate() {
+          let Some(cooked) = &quasi.value.cooked else {
+            return self.unsupported(span);
+          };
+          result.push_str(cooked);
+          if let Some(expression) = template.expressions.get(idx) {
+            result.push_str(&self.eval(expression)?.to_js_string());
+          }
+        }
+
+        Ok(StaticValue::String(result))
+      }
+      Expression::Identifier(ident) => self.eval_identifier(ident),
+      Expression::ArrayExpression(array) => {
+        let mut items = vec![];
+        for element in &array.elements {
+          match element {
+            ArrayExpressionElement::SpreadElement(spread) => match self.eval(&spread.argument)? {
+              StaticValue::Array(values) => items.extend(values),
+              StaticValue::String(value) => {
+                items.extend(value.chars().map(|ch| StaticValue::String(ch.to_string())))
+              }
+              value => {
+                return self.fail(
+                  spread.span,
+                  UnresolvedReason::TypeError(format!("{} is not iterable", value.type_of())),
+                )
+              }
+            },
+            ArrayExpressionElement::Elision(_) => items.push(StaticValue::Undefined),
+            element => match element.as_expression() {
+              Some(expression) => items.push(self.eval(expression)?),
+              None => return self.unsupported(span),
+            },
+          }
+        }
+
+        Ok(StaticValue::Array(items))
+      }
+      Expression::ObjectExpression(object) => {
+        let mut properties = vec![];
+        for property in &object.properties {
+          match property {
+            ObjectPropertyKind::ObjectProperty(property) => {
+              if property.kind != PropertyKind::Init || property.method {
+                return self.fail(
+                  property.span,
+                  UnresolvedReason::NonSerializable(self.code(property.span)),
+                );
+              }
+
+              let key = match property.key.static_name().filter(|_| !property.computed) {
+                Some(name) => name.to_string(),
+                None => match property.key.as_expression() {
+                  Some(key) => self.eval(key)?.to_js_string(),
+                  None => return self.unsupported(property.key.span()),
+                },
+              };
+              let value = self.eval(&property.value)?;
+              StaticValue::set(&mut properties, key, value);
+            }
+            ObjectPropertyKind::SpreadProperty(spread) => match self.eval(&spread.argument)? {
+              StaticValue::Object(values) => {
+                for (key, value) in values {
+                  StaticValue::set(&mut properties, key, value);
+                }
+              }
+              StaticValue::Array(values) => {
+                for (idx, value) in values.into_iter().enumerate() {
+                  StaticValue::set(&mut properties, idx.to_string(), value);
+                }
+              }
+              StaticValue::String(value) => {
+                for (idx, ch) in value.chars().enumerate() {
+                  StaticValue::set(
+                    &mut properties,
+                    idx.to_string(),
+                    StaticValue::String(ch.into()),
+                  );
+                }
+              }
+              // Primitives don't have own enumerable properties
+              _ => {}
+            },
+          }
+        }
+
+        Ok(StaticValue::Object(properties))
+      }
+      Expression::UnaryExpression(unary) => {
+        if unary.operator == UnaryOperator::Typeof {
+          if let Expression::Identifier(ident) = unary.argument.get_inner_expression() {
+            if reference_symbol(ident, self.symbols).is_none() && !is_known_global(&ident.name) {
+              return Ok(StaticValue::String("undefined".to_string()));
+            }
+          }
+        }
+
+        let value = self.eval(&unary.argument)?;
+        match unary.operator {
+          UnaryOperator::UnaryNegation => Ok(StaticValue::Number(-value.to_number())),
+          UnaryOperator::UnaryPlus => Ok(StaticValue::Number(value.to_number())),
+          UnaryOperator::LogicalNot => Ok(StaticValue::Boolean(!value.is_truthy())),
+          UnaryOperator::BitwiseNot => {
+            Ok(StaticValue::Number(f64::from(!to_int32(value.to_number()))))
+          }
+          UnaryOperator::Typeof => Ok(StaticValue::String(value.type_of().to_string())),
+          UnaryOperator::Void => Ok(StaticValue::Undefined),
+          UnaryOperator::Delete => self.unsupported(span),
+        }
+      }
+      Expression::BinaryExpression(binary) => {
+        let left = self.eval(&binary.left)?;
+        let right = self.eval(&binary.right)?;
+        binary_operation(binary.operator, left, right)
+          .map_err(|reason| self.unresolved(span, reason))
+      }
+      Expression::LogicalExpression(logical) => {
+        let left = self.eval(&logical.left)?;
+        let short_circuit = match logical.operator {
+          LogicalOperator::And => !left.is_truthy(),
+          LogicalOperator::Or => left.is_truthy(),
+          LogicalOperator::Coalesce => !left.is_nullish(),
+        };
+        if short_circuit {
+          Ok(left)
+        } else {
+          self.eval(&logical.right)
+        }
+      }
+      Expression::ConditionalExpression(conditional) => {
+        if self.eval(&conditional.test)?.is_truthy() {
+          self.eval(&conditional.consequent)
+        } else {
+          self.eval(&conditional.alternate)
+        }
+      }
+      Expression::SequenceExpression(sequence) => {
+        let mut result = StaticValue::Undefined;
+        for expression in &sequence.expressions {
+          result = self.eval(expression)?;
+        }
+
+        Ok(result)
+      }
+      Expression::ChainExpression(chain) => {
+        let result = match &chain.expression {
+          ChainElement::CallExpression(call) => self.eval_call(call).map(Some),
+          ChainElement::TSNonNullExpression(expression) => {
+            self.eval_in_chain(&expression.expression)
+          }
+          element => match element.as_member_expression() {
+            Some(member) => self.eval_member(member),
+            None => return self.unsupported(span),
+          },
+        };
+
+        Ok(result?.unwrap_or(StaticValue::Undefined))
+      }
+      Expression::CallExpression(call) => self.eval_call(call),
+      Expression::ArrowFunctionExpression(_)
+      | Expression::FunctionExpression(_)
+      | Expression::ClassExpression(_) => {
+        self.fail(span, UnresolvedReason::NonSerializable(self.code(span)))
+      }
+      expression => match expression.as_member_expression() {
+        Some(member) => Ok(self.eval_member(member)?.unwrap_or(StaticValue::Undefined)),
+        None => self.unsupported(span),
+      },
+    }
+  }
+
+  // `None` if an optional chain is short-circuited
+  fn eval_in_chain(
+    &mut self,
+    expression: &'a Expression<'a>,
+  ) -> Result<Option<StaticValue>, Unresolved> {
+    match expression {
+      Expression::CallExpression(call) => self.eval_call(call).map(Some),
+      expression => match expression.as_member_expression() {
+        Some(member) => self.eval_member(member),
+        None => self.eval(expression).map(Some),
+      },
+    }
+  }
+
+  fn property_key(&mut self, member: &'a MemberExpression<'a>) -> Result<String, Unresolved> {
+    match member {
+      MemberExpression::ComputedMemberExpression(member) => {
+        Ok(self.eval(&member.expression)?.to_js_string())
+      }
+      MemberExpression::StaticMemberExpression(member) => Ok(member.property.name.to_string()),
+      MemberExpression::PrivateFieldExpression(member) => self.unsupported(member.span),
+    }
+  }
+
+  fn eval_member(
+    &mut self,
+    member: &'a MemberExpression<'a>,
+  ) -> Result<Option<StaticValue>, Unresolved> {
+    // Members of namespace imports are resolved as named imports
+    if let Expression::Identifier(ident) = member.object().get_inner_expression() {
+      let binding = reference_symbol(ident, self.symbols).and_then(|id| self.bindings.get(&id));
+      if let Some(&Binding::Import {
+        source,
+        imported: None,
+      }) = binding
+      {
+        let key = self.property_key(member)?;
+        return self.eval_import(member.span(), source, &key).map(Some);
+      }
+    }
+
+    let Some(object) = self.eval_in_chain(member.object())? else {
+      return Ok(None);
+    };
+    if object.is_nullish() {
+      if member.optional() {
+        return Ok(None);
+      }
+
+      let key = self.property_key(member)?;
+      return self.fail(
+        member.span(),
+        UnresolvedReason::TypeError(format!("cannot read `{key}` of {}", object.to_js_string())),
+      );
+    }
+
+    let key = self.property_key(member)?;
+    Ok(Some(object.get(&key)))
+  }
+
+  fn eval_import(&mut self, span: Span, source: &str, imported: &str) -> StaticResult {
+    let (filename, source_text) = match self.resolver.loader.load(self.filename, source) {
+      Ok(module) => module,
+      Err(error) => {
+        return self.fail(
+          span,
+          UnresolvedReason::ImportFailed {
+            source: source.to_string(),
+            message: error.message,
+          },
+        )
+      }
+    };
+
+    self
+      .resolver
+      .resolve_export(&filename, &source_text, imported)
+  }
+
+  fn eval_identifier(&mut self, ident: &'a IdentifierReference<'a>) -> StaticResult {
+    let Some(symbol_id) = reference_symbol(ident, self.symbols) else {
+      return match ident.name.as_str() {
+        "undefined" => Ok(StaticValue::Undefined),
+        "NaN" => Ok(StaticValue::Number(f64::NAN)),
+        "Infinity" => Ok(StaticValue::Number(f64::INFINITY)),
+        name => self.fail(
+          ident.span,
+          UnresolvedReason::UnknownIdentifier(name.to_string()),
+        ),
+      };
+    };
+
+    if let Some(value) = self
+      .scopes
+      .iter()
+      .rev()
+      .find_map(|scope| scope.get(&symbol_id))
+    {
+      return Ok(value.clone());
+    }
+
+    self.eval_symbol(symbol_id, ident.span)
+  }
+
+  fn eval_symbol(&mut self, symbol_id: SymbolId, span: Span) -> StaticResult {
+    if let Some(value) = self.values.get(&symbol_id) {
+      return value.clone();
+    }
+
+    let name = self.symbols.get_name(symbol_id).to_string();
+    if self.mutated.contains(&symbol_id) {
+      return self.fail(span, UnresolvedReason::MutatedBinding(name));
+    }
+
+    if !self.resolving.insert(symbol_id) {
+      return self.fail(span, UnresolvedReason::Cycle(name));
+    }
+
+    let value = match self.bindings.get(&symbol_id) {
+      Some(Binding::Variable { init, path }) => {
+        let (init, path) = (*init, path.clone());
+        self.eval_variable(init, &path)
+      }
+      Some(Binding::Import {
+        source,
+        imported: Some(imported),
+      }) => {
+        let (source, imported) = (*source, *imported);
+        self.eval_import(span, source, imported)
+      }
+      Some(Binding::Import { imported: None, .. }) => {
+        self.fail(span, UnresolvedReason::UnsupportedBinding(name))
+      }
+      Some(Binding::Function(_)) => self.fail(span, UnresolvedReason::NonSerializable(name)),
+      Some(Binding::Unsupported) | None => {
+        self.fail(span, UnresolvedReason::UnsupportedBinding(name))
+      }
+    };
+
+    self.resolving.remove(&symbol_id);
+    self.values.insert(symbol_id, value.clone());
+    value
+  }
+
+  fn eval_variable(
+    &mut self,
+    init: Option<&'a Expression<'a>>,
+    path: &[PathStep<'a>],
+  ) -> StaticResult {
+    let Some(init) = init else {
+      return Ok(StaticValue::Undefined);
+    };
+
+    let mut value = self.eval(init)?;
+    for step in path {
+      if value.is_nullish() {
+        return self.fail(
+          init.span(),
+          UnresolvedReason::TypeError(format!("cannot destructure {}", value.to_js_string())),
+        );
+      }
+
+      value = match &step.key {
+        PathKey::Property(name) => value.get(name),
+        PathKey::Index(idx) => value.get(&idx.to_string()),
+        PathKey::ObjectRest(excluded) => match value {
+          StaticValue::Object(properties) => StaticValue::Object(
+            properties
+              .into_iter()
+              .filter(|(key, _)| !excluded.contains(key))
+              .collect(),
+          ),
+          _ => StaticValue::Object(vec![]),
+        },
+        PathKey::ArrayRest(start) => match value {
+          StaticValue::Array(items) => StaticValue::Array(items.into_iter().skip(*start).collect()),
+          _ => return self.unsupported(init.span()),
+        },
+      };
+
+      if let (StaticValue::Undefined, Some(default)) = (&value, step.default) {
+        value = self.eval(default)?;
+      }
+    }
+
+    Ok(value)
+  }
+
+  fn eval_call(&mut self, call: &'a CallExpression<'a>) -> StaticResult {
+    let unsupported_call = |this: &Self| {
+      this.fail(
+        call.span,
+        UnresolvedReason::UnsupportedCall(this.code(call.span)),
+      )
+    };
+
+    let Expression::Identifier(callee) = call.callee.get_inner_expression() else {
+      return unsupported_call(self);
+    };
+    let Some(symbol_id) = reference_symbol(callee, self.symbols) else {
+      return unsupported_call(self);
+    };
+    if self.mutated.contains(&symbol_id) {
+      let name = callee.name.to_string();
+      return self.fail(callee.span, UnresolvedReason::MutatedBinding(name));
+    }
+
+    let (params, body) = match self.bindings.get(&symbol_id) {
+      Some(Binding::Function(function)) => (&function.params, function_body(function)),
+      Some(Binding::Variable {
+        init: Some(init),
+        path,
+      }) if path.is_empty() => match init.get_inner_expression() {
+        Expression::ArrowFunctionExpression(arrow) => (&arrow.params, arrow_body(arrow)),
+        Expression::FunctionExpression(function) => (&function.params, function_body(function)),
+        _ => return unsupported_call(self),
+      },
+      _ => return unsupported_call(self),
+    };
+    let Some(body) = body else {
+      return unsupported_call(self);
+    };
+
+    let mut args = vec![];
+    for argument in &call.arguments {
+      match argument {
+        Argument::SpreadElement(spread) => match self.eval(&spread.argument)? {
+          StaticValue::Array(items) => args.extend(items),
+          _ => return self.unsupported(spread.span),
+        },
+        argument => match argument.as_expression() {
+          Some(expression) => args.push(self.eval(expression)?),
+          None => return self.unsupported(argument.span()),
+        },
+      }
+    }
+
+    if self.depth >= MAX_DEPTH {
+      return self.fail(call.span, UnresolvedReason::TooDeep);
+    }
+
+    self.depth += 1;
+    let result = self
+      .bind_params(params, args)
+      .and_then(|()| self.eval(body));
+    self.scopes.pop();
+    self.depth -= 1;
+    result
+  }
+
+  // Pushes a scope with the arguments, even if some of the params are not supported
+  fn bind_params(
+    &mut self,
+    params: &'a FormalParameters<'a>,
+    mut args: Vec<StaticValue>,
+  ) -> Result<(), Unresolved> {
+    self.scopes.push(HashMap::new());
+    let rest_args = args.split_off(params.items.len().min(args.len()));
+    let mut args = args.into_iter();
+    for param in &params.items {
+      let mut value = args.next().unwrap_or(StaticValue::Undefined);
+      let ident = match &param.pattern.kind {
+        BindingPatternKind::BindingIdentifier(ident) => ident,
+        BindingPatternKind::AssignmentPattern(assignment) => {
+          let BindingPatternKind::BindingIdentifier(ident) = &assignment.left.kind else {
+            return self.unsupported(param.span);
+          };
+          if value == StaticValue::Undefined {
+            value = self.eval(&assignment.right)?;
+          }
+          ident
+        }
+        _ => return self.unsupported(param.span),
+      };
+
+      if let Some(symbol_id) = ident.symbol_id.get() {
+        self.scopes.last_mut().unwrap().insert(symbol_id, value);
+      }
+    }
+
+    if let Some(rest) = &params.rest {
+      let BindingPatternKind::BindingIdentifier(ident) = &rest.argument.kind else {
+        return self.unsupported(rest.span);
+      };
+      if let Some(symbol_id) = ident.symbol_id.get() {
+        self
+          .scopes
+          .last_mut()
+          .unwrap()
+          .insert(symbol_id, StaticValue::Array(rest_args));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+fn is_known_global(name: &str) -> bool {
+  matches!(name, "undefined" | "NaN" | "Infinity")
+}
+
+// `{ return expression; }`
+fn function_body<'a>(function: &'a Function<'a>) -> Option<&'a Expression<'a>> {
+  if function.r#async || function.generator {
+    return None;
+  }
+
+  match function.body.as_ref()?.statements.as_slice() {
+    [Statement::ReturnStatement(statement)] => statement.argument.as_ref(),
+    _ => None,
+  }
+}
+
+fn arrow_body<'a>(arrow: &'a ArrowFunctionExpression<'a>) -> Option<&'a Expression<'a>> {
+  if arrow.r#async {
+    return None;
+  }
+
+  match arrow.get_expression() {
+    Some(expression) => Some(expression),
+    None => match arrow.body.statements.as_slice() {
+      [Statement::ReturnStatement(statement)] => statement.argument.as_ref(),
+      _ => None,
+    },
+  }
+}
+
+pub(crate) fn strict_equals(
+  left: &StaticValue,
+  right: &StaticValue,
+) -> Result<bool, UnresolvedReason> {
+  match (left, right) {
+    // Objects are compared by identity, which is not known
+    (StaticValue::Array(_) | StaticValue::Object(_), _)
+    | (_, StaticValue::Array(_) | StaticValue::Object(_)) => Err(UnresolvedReason::TypeError(
+      "objects are compared by identity".to_string(),
+    )),
+    (StaticValue::Number(left), StaticValue::Number(right)) => Ok(left == right),
+    (left, right) => Ok(left == right),
+  }
+}
+
+pub(crate) fn loose_equals(
+  left: &StaticValue,
+  right: &StaticValue,
+) -> Result<bool, UnresolvedReason> {
+  match (left, right) {
+    (left, right) if left.type_of() == right.type_of() && !left.is_nullish() => {
+      strict_equals(left, right)
+    }
+    (left, right) if left.is_nullish() || right.is_nullish() => {
+      Ok(left.is_nullish() && right.is_nullish())
+    }
+    (StaticValue::Array(_) | StaticValue::Object(_), _)
+    | (_, StaticValue::Array(_) | StaticValue::Object(_)) => Err(UnresolvedReason::TypeError(
+      "objects are compared by identity".to_string(),
+    )),
+    (left, right) => Ok(left.to_number() == right.to_number()),
+  }
+}
+
+fn compare(
+  left: &StaticValue,
+  right: &StaticValue,
+  check: fn(std::cmp::Ordering) -> bool,
+) -> StaticValue {
+  let ordering = match (left, right) {
+    (StaticValue::String(left), StaticValue::String(right)) => {
+      Some(left.encode_utf16().cmp(right.encode_utf16()))
+    }
+    (left, right) => left.to_number().partial_cmp(&right.to_number()),
+  };
+
+  StaticValue::Boolean(ordering.is_some_and(check))
+}
+
+fn binary_operation(
+  operator: BinaryOperator,
+  left: StaticValue,
+  right: StaticValue,
+) -> Result<StaticValue, UnresolvedReason> {
+  let number =
+    |op: fn(f64, f64) -> f64| Ok(StaticValue::Number(op(left.to_number(), right.to_number())));
+  let int32 = |op: fn(i32, i32) -> i32| {
+    Ok(StaticValue::Number(f64::from(op(
+      to_int32(left.to_number()),
+      to_int32(right.to_number()),
+    ))))
+  };
+
+  match operator {
+    BinaryOperator::Addition => match (&left, &right) {
+      (StaticValue::String(_) | StaticValue::Array(_) | StaticValue::Object(_), _)
+      | (_, StaticValue::String(_) | StaticValue::Array(_) | StaticValue::Object(_)) => Ok(
+        StaticValue::String(left.to_js_string() + &right.to_js_string()),
+      ),
+      _ => number(|a, b| a + b),
+    },
+    BinaryOperator::Subtraction => number(|a, b| a - b),
+    BinaryOperator::Multiplication => number(|a, b| a * b),
+    BinaryOperator::Division => number(|a, b| a / b),
+    BinaryOperator::Remainder => number(|a, b| a % b),
+    BinaryOperator::Exponential => number(f64::powf),
+    BinaryOperator::BitwiseOR => int32(|a, b| a | b),
+    BinaryOperator::BitwiseXOR => int32(|a, b| a ^ b),
+    BinaryOperator::BitwiseAnd => int32(|a, b| a & b),
+    BinaryOperator::ShiftLeft => int32(|a, b| a.wrapping_shl(b as u32 & 31)),
+    BinaryOperator::ShiftRight => int32(|a, b| a.wrapping_shr(b as u32 & 31)),
+    BinaryOperator::ShiftRightZeroFill => Ok(StaticValue::Number(f64::from(
+      (to_int32(left.to_number()) as u32) >> (to_int32(right.to_number()) as u32 & 31),
+    ))),
+    BinaryOperator::StrictEquality => strict_equals(&left, &right).map(StaticValue::Boolean),
+    BinaryOperator::StrictInequality => {
+      strict_equals(&left, &right).map(|equals| StaticValue::Boolean(!equals))
+    }
+    BinaryOperator::Equality => loose_equals(&left, &right).map(StaticValue::Boolean),
+    BinaryOperator::Inequality => {
+      loose_equals(&left, &right).map(|equals| StaticValue::Boolean(!equals))
+    }
+    BinaryOperator::LessThan => Ok(compare(&left, &right, std::cmp::Ordering::is_lt)),
+    BinaryOperator::LessEqualThan => Ok(compare(&left, &right, std::cmp::Ordering::is_le)),
+    BinaryOperator::GreaterThan => Ok(compare(&left, &right, std::cmp::Ordering::is_gt)),
+    BinaryOperator::GreaterEqualThan => Ok(compare(&left, &right, std::cmp::Ordering::is_ge)),
+    BinaryOperator::In | BinaryOperator::Instanceof => Err(UnresolvedReason::TypeError(format!(
+      "`{}` is not supported",
+      operator.as_str()
+    ))),
+  }
+}
+
+fn parse<'a>(
+  allocator: &'a Allocator,
+  filename: &str,
+  source_text: &'a str,
+) -> Result<(Program<'a>, SymbolTable), Unresolved> {
+  let source_type = SourceType::from_path(filename.split(['?', '#']).next().unwrap_or(filename))
+    .unwrap_or_default()
+    .with_unambiguous(true);
+  let ret = Parser::new(allocator, source_text, source_type).parse();
+  if let Some(error) = ret.errors.first() {
+    return Err(Unresolved {
+      filename: filename.to_string(),
+      span: Span::default(),
+      reason: UnresolvedReason::ImportFailed {
+        source: filename.to_string(),
+        message: error.message.to_string(),
+      },
+    });
+  }
+
+  let program = ret.program;
+  let (symbols, _) = SemanticBuilder::new()
+    .build(&program)
+    .semantic
+    .into_symbol_table_and_scope_tree();
+
+  Ok((program, symbols))
+}
+
+impl<'l> StaticValueResolver<'l> {
+  pub fn new(loader: &'l dyn ModuleLoader) -> Self {
+    Self {
+      loader,
+      exports: RefCell::default(),
+    }
+  }
+
+  // Resolves expressions of an already parsed module
+  pub fn resolve_expressions<'a>(
+    &self,
+    filename: &str,
+    program: &'a Program<'a>,
+    symbols: &'a SymbolTable,
+    expressions: impl IntoIterator<Item = &'a Expression<'a>>,
+  ) -> Vec<StaticResult> {
+    let mut evaluator = Evaluator::new(self, filename, program, symbols);
+    expressions
+      .into_iter()
+      .map(|expression| evaluator.eval(expression))
+      .collect()
+  }
+
+  pub fn resolve_export(&self, filename: &str, source_text: &str, name: &str) -> StaticResult {
+    let key = (filename.to_string(), name.to_string());
+    if let Some(result) = self.exports.borrow().get(&key) {
+      return result.clone().unwrap_or_else(|| {
+        Err(Unresolved {
+          filename: filename.to_string(),
+          span: Span::default(),
+          reason: UnresolvedReason::Cycle(name.to_string()),
+        })
+      });
+    }
+
+    self.exports.borrow_mut().insert(key.clone(), None);
+    let result = self.find_export(filename, source_text, name);
+    self.exports.borrow_mut().insert(key, Some(result.clone()));
+    result
+  }
+
+  fn find_export(&self, filename: &str, source_text: &str, name: &str) -> StaticResult {
+    let allocator = Allocator::default();
+    let (program, symbols) = parse(&allocator, filename, source_text)?;
+    let mut evaluator = Evaluator::new(self, filename, &program, &symbols);
+
+    let mut star_sources = vec![];
+    for statement in &program.body {
+      match statement {
+        Statement::ExportNamedDeclaration(export) => {
+          if let Some(declaration) = &export.declaration {
+            let (ids, span) = match declaration {
+              Declaration::VariableDeclaration(declaration) => {
+                let mut ids = vec![];
+                for declarator in &declaration.declarations {
+                  binding_identifiers(&declarator.id, &mut ids);
+                }
+                (ids, declaration.span)
+              }
+              Declaration::FunctionDeclaration(function) => {
+                (function.id.iter().collect(), function.span)
+              }
+              Declaration::ClassDeclaration(class) => (class.id.iter().collect(), class.span),
+              Declaration::TSEnumDeclaration(declaration) if declaration.id.name == name => {
+                return evaluator.fail(
+                  declaration.span,
+                  UnresolvedReason::UnsupportedBinding(name.to_string()),
+                );
+              }
+              _ => continue,
+            };
+
+            if let Some(symbol_id) = ids
+              .into_iter()
+              .find(|id| id.name == name)
+              .and_then(|id| id.symbol_id.get())
+            {
+              return evaluator.eval_symbol(symbol_id, span);
+            }
+          }
+
+          for specifier in &export.specifiers {
+            if specifier.exported.name() != name {
+              continue;
+            }
+
+            return match (&export.source, &specifier.local) {
+              (Some(source), local) => {
+                evaluator.eval_import(specifier.span, &source.value, &local.name())
+              }
+              (None, ModuleExportName::IdentifierReference(local)) => {
+                evaluator.eval_identifier(local)
+              }
+              (None, _) => evaluator.unsupported(specifier.span),
+            };
+          }
+        }
+        Statement::ExportDefaultDeclaration(export) if name == "default" => {
+          return match &export.declaration {
+            ExportDefaultDeclarationKind::FunctionDeclaration(_)
+            | ExportDefaultDeclarationKind::ClassDeclaration(_) => evaluator.fail(
+              export.span,
+              UnresolvedReason::NonSerializable(evaluator.code(export.declaration.span())),
+            ),
+            declaration => match declaration.as_expression() {
+              Some(expression) => evaluator.eval(expression),
+              None => evaluator.unsupported(export.span),
+            },
+          };
+        }
+        Statement::ExportAllDeclaration(export) => match &export.exported {
+          Some(exported) if exported.name() == name => {
+            return evaluator.fail(
+              export.span,
+              UnresolvedReason::UnsupportedBinding(name.to_string()),
+            )
+          }
+          Some(_) => {}
+          None => star_sources.push((export.span, export.source.value.as_str())),
+        },
+        _ => {}
+      }
+    }
+
+    for (span, source) in star_sources {
+      match evaluator.eval_import(span, source, name) {
+        Err(Unresolved {
+          reason: UnresolvedReason::MissingExport(_),
+          ..
+        }) => continue,
+        result => return result,
+      }
+    }
+
+    evaluator.fail(
+      Span::default(),
+      UnresolvedReason::MissingExport(name.to_string()),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  struct MemoryLoader(HashMap<&'static str, &'static str>);
+
+  impl ModuleResolver for MemoryLoader {
+    fn resolve(&self, importer: &str, source: &str) -> WywResult<String> {
+      self.load(importer, source).map(|(filename, _)| filename)
+    }
+  }
+
+  impl ModuleLoader for MemoryLoader {
+    fn load(&self, _importer: &str, source: &str) -> WywResult<(String, String)> {
+      self
+        .0
+        .get(source)
+        .map(|code| {
+          (
+            format!("/{}.ts", source.trim_start_matches("./")),
+            code.to_string(),
+          )
+        })
+        .ok_or_else(|| {
+          WywError::new(
+            "wyw/resolve-error",
+            format!("Cannot resolve module {source}"),
+          )
+        })
+    }
+  }
+
+  fn resolve_with(modules: &[(&'static str, &'static str)], source_text: &str) -> StaticResult {
+    let loader = MemoryLoader(modules.iter().copied().collect());
+    let resolver = StaticValueResolver::new(&loader);
+    resolver.resolve_export("/entry.ts", source_text, "value")
+  }
+
+  fn resolve(source_text: &str) -> StaticResult {
+    resolve_with(&[], source_text)
+  }
+
+  fn reason(result: StaticResult) -> UnresolvedReason {
+    result.unwrap_err().reason
+  }
+
+  #[test]
+  fn test_literals_and_operators() {
+    let value = resolve(
+      r#"
+      const base = 4;
+      const unit = "px";
+      const sizes = [base, base * 2, ...[base * 4]];
+      const theme = { colors: { primary: '#fff' }, space: sizes };
+      const { colors: { primary }, ...rest } = theme;
+      export const value = {
+        padding: `${sizes[1]}${unit}`,
+        primary,
+        rest: rest.space.length,
+        flag: !(base > 3) || typeof missing,
+        ...{ half: base / 8, bits: ~base | 1 },
+        optional: theme?.nope?.deep ?? "fallback",
+      };
+      "#,
+    );
+
+    assert_eq!(
+      value.unwrap().to_code(),
+      r##"{"padding": "8px", "primary": "#fff", "rest": 3, "flag": "undefined", "half": 0.5, "bits": -5, "optional": "fallback"}"##
+    );
+  }
+
+  #[test]
+  fn test_calls() {
+    let value = resolve(
+      r#"
+      const scale = (n, factor = 2) => n * factor;
+      function px(value) {
+        return `${value}px`;
+      }
+      export const value = [px(scale(4)), px(scale(1, 3)), ((n) => n)(1)];
+      "#,
+    );
+
+    assert_eq!(
+      reason(value),
+      UnresolvedReason::UnsupportedCall("((n) => n)(1)".to_string())
+    );
+
+    let value = resolve(
+      r#"
+      const scale = (n, factor = 2) => n * factor;
+      function px(value) {
+        return `${value}px`;
+      }
+      export const value = [px(scale(4)), px(scale(1, 3))];
+      "#,
+    );
+    assert_eq!(value.unwrap().to_code(), r#"["8px", "3px"]"#);
+  }
+
+  #[test]
+  fn test_imports() {
+    let modules = [
+      (
+        "./theme",
+        r#"
+        import { base } from "./tokens";
+        export const colors = { primary: base.primary, muted: `${base.primary}80` };
+        export * from "./tokens";
+        export { colors as default };
+        "#,
+      ),
+      (
+        "./tokens",
+        "export const base = { primary: '#000' }; export const gap = 8;",
+      ),
+    ];
+
+    let value = resolve_with(
+      &modules,
+      r#"
+      import theme, { colors, gap } from "./theme";
+      import * as tokens from "./tokens";
+      export const value = [colors.muted, gap, tokens.base.primary, theme.primary];
+      "#,
+    );
+    assert_eq!(
+      value.unwrap().to_code(),
+      r##"["#00080", 8, "#000", "#000"]"##
+    );
+
+    let value = resolve_with(
+      &modules,
+      r#"import { missing } from "./theme"; export const value = missing;"#,
+    );
+    assert_eq!(
+      value.unwrap_err(),
+      Unresolved {
+        filename: "/theme.ts".to_string(),
+        span: Span::default(),
+        reason: UnresolvedReason::MissingExport("missing".to_string()),
+      }
+    );
+
+    let value = resolve_with(
+      &modules,
+      r#"import { a } from "./nope"; export const value = a;"#,
+    );
+    assert_eq!(
+      reason(value),
+      UnresolvedReason::ImportFailed {
+        source: "./nope".to_string(),
+        message: "Cannot resolve module ./nope".to_string(),
+      }
+    );
+  }
+
+  #[test]
+  fn test_reasons() {
+    assert_eq!(
+      reason(resolve("let value = 1; value += 1; export { value };")),
+      UnresolvedReason::MutatedBinding("value".to_string())
+    );
+    assert_eq!(
+      reason(resolve(
+        "const theme = {}; theme.color = 'red'; export const value = theme;"
+      )),
+      UnresolvedReason::MutatedBinding("theme".to_string())
+    );
+    assert_eq!(
+      reason(resolve("export const value = window.innerWidth;")),
+      UnresolvedReason::UnknownIdentifier("window".to_string())
+    );
+    assert_eq!(
+      reason(resolve("export const value = () => 1;")),
+      UnresolvedReason::NonSerializable("() => 1".to_string())
+    );
+    assert_eq!(
+      reason(resolve("const a = b; const b = a; export const value = a;")),
+      UnresolvedReason::Cycle("a".to_string())
+    );
+    assert_eq!(
+      reason(resolve("export const value = new Date();")),
+      UnresolvedReason::UnsupportedExpression("new Date()".to_string())
+    );
+  }
+}
@@ -0,0 +1,483 @@
+use crate::contains;
+use crate::exports_and_imports::reference_symbol;
+use oxc::ast::ast::{
+  Expression, IdentifierReference, Program, TaggedTemplateExpression, VariableDeclarator,
+};
+use oxc::span::{GetSpan, Span};
+use oxc_semantic::{SymbolId, SymbolTable};
+use wyw_processor::static_semantics::InterpolationKind;
+use wyw_traverse::local_identifier::LocalIdentifier;
+use wyw_traverse::symbol::Symbol;
+use wyw_traverse::{walk, Ancestor, AnyNode, EnterAction, TraverseCtx, TraverseHooks};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExpressionKind {
+  // A string, number, boolean or null literal
+  Literal,
+  // An inline arrow or function expression
+  Function,
+  // An imported binding or a member of it
+  Import,
+  // Anything that has to be evaluated
+  Evaluated,
+}
+
+impl ExpressionKind {
+  pub fn interpolation_kind(self) -> InterpolationKind {
+    match self {
+      Self::Literal => InterpolationKind::Literal,
+      Self::Function => InterpolationKind::RuntimeCallback,
+      Self::Import | Self::Evaluated => InterpolationKind::Dynamic,
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct TemplateExpression<'a> {
+  pub span: Span,
+  pub kind: ExpressionKind,
+  // Bindings declared outside of the expression, in the order of the first usage
+  pub identifiers: Vec<LocalIdentifier<'a>>,
+}
+
+#[derive(Debug)]
+pub struct TemplateDependencies<'a> {
+  // The span of the whole tagged template
+  pub span: Span,
+  pub expressions: Vec<TemplateExpression<'a>>,
+}
+
+// The root identifier of `foo`, `foo.bar` or `foo['bar'].baz`
+fn member_root<'a>(expression: &'a Expression<'a>) -> Option<&'a IdentifierReference<'a>> {
+  match expression.get_inner_expression() {
+    Expression::Identifier(ident) => Some(ident),
+    Expression::StaticMemberExpression(member) => member_root(&member.object),
+    Expression::ComputedMemberExpression(member) => match &member.expression {
+      Expression::StringLiteral(_) => member_root(&member.object),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+fn classify(expression: &Expression, symbols: &SymbolTable) -> ExpressionKind {
+  match expression.get_inner_expression() {
+    Expression::StringLiteral(_)
+    | Expression::NumericLiteral(_)
+    | Expression::BooleanLiteral(_)
+    | Expression::NullLiteral(_) => ExpressionKind::Literal,
+    Expression::ArrowFunctionExpression(_) | Expression::FunctionExpression(_) => {
+      ExpressionKind::Function
+    }
+    expression => {
+      let is_import = member_root(expression)
+        .and_then(|root| reference_symbol(root, symbols))
+        .is_some_and(|symbol_id| symbols.get_flags(symbol_id).is_import());
+      if is_import {
+        ExpressionKind::Import
+      } else {
+        ExpressionKind::Evaluated
+      }
+    }
+  }
+}
+
+struct IdentifiersCollector<'a, 'd> {
+  dependencies: &'d mut [TemplateDependencies<'a>],
+}
+
+impl<'a> TraverseHooks<'a> for IdentifiersCollector<'a, '_> {
+  fn enter_identifier_reference(
+    &mut self,
+    node: &'a IdentifierReference<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    let symbols = ctx.symbols();
+    let Some(symbol_id) = reference_symbol(node, symbols) else {
+      return EnterAction::Continue;
+    };
+
+    let decl = symbols.get_span(symbol_id);
+    let property = match ctx.parent() {
+      Some(Ancestor::Field(AnyNode::StaticMemberExpression(member), "object")) => {
+        Some(member.property.name.clone())
+      }
+      Some(Ancestor::Field(AnyNode::ComputedMemberExpression(member), "object")) => {
+        match &member.expression {
+          Expression::StringLiteral(literal) => Some(literal.value.clone()),
+          _ => None,
+        }
+      }
+      _ => None,
+    };
+
+    let expressions = self
+      .dependencies
+      .iter_mut()
+      .flat_map(|template| template.expressions.iter_mut());
+    for expression in expressions {
+      // Bindings declared inside the expression, e.g. arrow params, are not dependencies
+      if !contains(expression.span, node.span) || contains(expression.span, decl) {
+        continue;
+      }
+
+      let symbol = Symbol::new(symbols, symbol_id, decl);
+      let identifier = match &property {
+        Some(property) => LocalIdentifier::MemberExpression(symbol, property.clone()),
+        None => LocalIdentifier::Identifier(symbol),
+      };
+      if !expression.identifiers.contains(&identifier) {
+        expression.identifiers.push(identifier);
+      }
+    }
+
+    EnterAction::Continue
+  }
+}
+
+pub fn collect_template_dependencies<'a>(
+  templates: impl IntoIterator<Item = &'a TaggedTemplateExpression<'a>>,
+  program: &'a Program<'a>,
+  symbols: &'a SymbolTable,
+) -> Vec<TemplateDependencies<'a>> {
+  let mut dependencies = templates
+    .into_iter()
+    .map(|template| TemplateDependencies {
+      span: template.span,
+      expressions: template
+        .quasi
+        .expressions
+        .iter()
+        .map(|expression| TemplateExpression {
+          span: expression.span(),
+          kind: classify(expression, symbols),
+          identifiers: vec![],
+        })
+        .collect(),
+    })
+    .collect::<Vec<_>>();
+
+  walk(
+    &mut IdentifiersCollector {
+      dependencies: &mut dependencies,
+    },
+    program,
+    symbols,
+  );
+
+  dependencies
+}
+
+// References and initialized variables of a module
+#[derive(Default)]
+struct LocalBindings {
+  references: Vec<(Span, SymbolId)>,
+  // Spans of the binding pattern and of the initializer
+  declarators: Vec<(Span, Span)>,
+}
+
+impl<'a> TraverseHooks<'a> for LocalBindings {
+  fn enter_identifier_reference(
+    &mut self,
+    node: &'a IdentifierReference<'a>,
+    ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if let Some(symbol_id) = reference_symbol(node, ctx.symbols()) {
+      self.references.push((node.span, symbol_id));
+    }
+
+    EnterAction::Continue
+  }
+
+  fn enter_variable_declarator(
+    &mut self,
+    node: &'a VariableDeclarator<'a>,
+    _ctx: &mut TraverseCtx<'a>,
+  ) -> EnterAction {
+    if let Some(init) = &node.init {
+      self.declarators.push((node.id.span(), init.span()));
+    }
+
+    EnterAction::Continue
+  }
+}
+
+impl LocalBindings {
+  fn visit(&self, symbol_id: SymbolId, symbols: &SymbolTable, result: &mut Vec<SymbolId>) {
+    if result.contains(&symbol_id) {
+      return;
+    }
+
+    result.push(symbol_id);
+    let decl = symbols.get_span(symbol_id);
+    let init = self
+      .declarators
+      .iter()
+      .find(|(pattern, _)| contains(*pattern, decl))
+      .map(|(_, init)| *init);
+    if let Some(init) = init {
+      for (span, symbol_id) in &self.references {
+        if contains(init, *span) {
+          self.visit(*symbol_id, symbols, result);
+        }
+      }
+    }
+  }
+}
+
+// Bindings that the templates depend on, either directly or through initializers
+// of local variables, e.g. `theme` in `const c = theme.color; css`${c}``.
+// They are in the order of the first usage.
+pub fn collect_transitive_dependencies(
+  templates: &[TemplateDependencies],
+  program: &Program,
+  symbols: &SymbolTable,
+) -> Vec<SymbolId> {
+  let mut bindings = LocalBindings::default();
+  walk(&mut bindings, program, symbols);
+
+  let mut result = vec![];
+  let identifiers = templates
+    .iter()
+    .flat_map(|template| &template.expressions)
+    .flat_map(|expression| &expression.identifiers);
+  for identifier in identifiers {
+    let (LocalIdentifier::Identifier(symbol) | LocalIdentifier::MemberExpression(symbol, _)) =
+      identifier;
+    bindings.visit(symbol.symbol_id, symbols, &mut result);
+  }
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use oxc::allocator::Allocator;
+  use oxc::parser::Parser;
+  use oxc::span::SourceType;
+  use oxc_semantic::SemanticBuilder;
+
+  #[derive(Default)]
+  struct TaggedTemplates<'a> {
+    templates: Vec<&'a TaggedTemplateExpression<'a>>,
+  }
+
+  impl<'a> TraverseHooks<'a> for TaggedTemplates<'a> {
+    fn enter_tagged_template_expression(
+      &mut self,
+      node: &'a TaggedTemplateExpression<'a>,
+      _ctx: &mut TraverseCtx<'a>,
+    ) -> EnterAction {
+      self.templates.push(node);
+      EnterAction::Continue
+    }
+  }
+
+  fn describe(identifier: &LocalIdentifier) -> String {
+    match identifier {
+      LocalIdentifier::Identifier(symbol) => symbol.name.clone(),
+      LocalIdentifier::MemberExpression(symbol, property) => format!("{}.{property}", symbol.name),
+    }
+  }
+
+  fn collect(source_text: &str) -> Vec<(ExpressionKind, Vec<String>)> {
+    collect_with(source_text, |identifier, _| describe(identifier))
+  }
+
+  // Identifiers with the line of the declaration, to tell apart bindings with the same name
+  fn collect_declarations(source_text: &str) -> Vec<Vec<String>> {
+    collect_with(source_text, |identifier, source_text| {
+      let (LocalIdentifier::Identifier(symbol) | LocalIdentifier::MemberExpression(symbol, _)) =
+        identifier;
+      let line = source_text[..symbol.decl.start as usize].lines().count();
+      format!("{}:{line}", describe(identifier))
+    })
+    .into_iter()
+    .map(|(_, identifiers)| identifiers)
+    .collect()
+  }
+
+  fn collect_with(
+    source_text: &str,
+    describe: impl Fn(&LocalIdentifier, &str) -> String,
+  ) -> Vec<(ExpressionKind, Vec<String>)> {
+    let allocator = Allocator::default();
+    let program = Parser::new(&allocator, source_text, SourceType::mjs())
+      .parse()
+      .program;
+    let (symbols, _) = SemanticBuilder::new()
+      .build(&program)
+      .semantic
+      .into_symbol_table_and_scope_tree();
+
+    let mut templates = TaggedTemplates::default();
+    walk(&mut templates, &program, &symbols);
+
+    collect_template_dependencies(templates.templates, &program, &symbols)
+      .into_iter()
+      .flat_map(|template| template.expressions)
+      .map(|expression| {
+        let identifiers = expression
+          .identifiers
+          .iter()
+          .map(|identifier| describe(identifier, source_text))
+          .collect();
+        (expression.kind, identifiers)
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_kinds() {
+    let result = collect(
+      r#"
+      import { css } from "@linaria/core";
+      import { theme } from "./theme";
+      const size = 12;
+      export const a = css`
+        color: ${"red"};
+        margin: ${0};
+        width: ${(props) => props.width};
+        background: ${theme.colors.bg};
+        font-size: ${size * 2}px;
+      `;
+      "#,
+    );
+
+    assert_eq!(
+      result,
+      vec![
+        (ExpressionKind::Literal, vec![]),
+        (ExpressionKind::Literal, vec![]),
+        (ExpressionKind::Function, vec![]),
+        (ExpressionKind::Import, vec!["theme.colors".to_string()]),
+        (ExpressionKind::Evaluated, vec!["size".to_string()]),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_identifiers() {
+    let result = collect(
+      r#"
+      import { css } from "@linaria/core";
+      import * as tokens from "./tokens";
+      const local = { gap: 4 };
+      function scale(value) {
+        return value * 2;
+      }
+      const theme = "shadowed";
+      export const a = css`
+        gap: ${scale(local.gap) + local["gap"]};
+        color: ${(theme) => theme.color};
+        border: ${tokens["border"]};
+      `;
+      "#,
+    );
+
+    assert_eq!(
+      result,
+      vec![
+        (
+          ExpressionKind::Evaluated,
+          vec!["scale".to_string(), "local.gap".to_string()],
+        ),
+        (ExpressionKind::Function, vec![]),
+        (ExpressionKind::Import, vec!["tokens.border".to_string()]),
+      ]
+    );
+  }
+
+  // The cases below are ported from the fixtures of `collectOxcTemplateDependencies`
+
+  #[test]
+  fn test_nested_shadowing() {
+    let result = collect_declarations(
+      r#"const value = 'root';
+{
+  const value = 'block';
+  css`${(() => value)()}`;
+}
+css`${value}`;
+"#,
+    );
+
+    assert_eq!(result, [vec!["value:3"], vec!["value:1"]]);
+  }
+
+  #[test]
+  fn test_var_redeclarations() {
+    // Unlike the JS binding index, oxc merges `var` redeclarations into one symbol
+    let result = collect_declarations(
+      r#"var value = 'first';
+css`${value}`;
+var value = 'second';
+css`${value}`;
+"#,
+    );
+
+    assert_eq!(result, [vec!["value:1"], vec!["value:1"]]);
+  }
+
+  #[test]
+  fn test_same_offset_bindings() {
+    let result = collect_declarations(
+      r#"const first = 1;
+const second = 2;
+{
+  const first = 3;
+  const second = 4;
+  css`${first + second}`;
+}
+{ const hidden = 1; }
+const visible = 3;
+{ const visible = 4; }
+css`${visible} ${hidden}`;
+"#,
+    );
+
+    assert_eq!(
+      result,
+      [vec!["first:4", "second:5"], vec!["visible:9"], vec![]]
+    );
+  }
+
+  #[test]
+  fn test_globals() {
+    let result = collect("css`${typeof missingGlobal} ${process.env.NODE_ENV}`;\n");
+    assert_eq!(
+      result,
+      vec![
+        (ExpressionKind::Evaluated, vec![]),
+        (ExpressionKind::Evaluated, vec![]),
+      ]
+    );
+
+    // A local `process` is not the build-time global
+    let result =
+      collect("const process = { env: { NODE_ENV: 'local' } };\ncss`${process.env.NODE_ENV}`;\n");
+    assert_eq!(
+      result,
+      vec![(ExpressionKind::Evaluated, vec!["process.env".to_string()])]
+    );
+  }
+
+  #[test]
+  fn test_hoisted_and_self_bindings() {
+    let result = collect_declarations(
+      r#"css`${helper()}`;
+function helper() { return 304; }
+const self = 304;
+css`${function self() { return typeof self; }}`;
+const Value = 304;
+{
+  css`${Value}`;
+  class Value {}
+}
+"#,
+    );
+
+    assert_eq!(result, [vec!["helper:2"], vec![], vec!["Value:8"]]);
+  }
+}
@@ -1,9 +1,10 @@
 use crate::symbol::Symbol;
 use oxc::span::Atom;
 
+// A binding that a template expression depends on, with the property if it's accessed statically
 #[derive(Clone, Debug, PartialEq)]
 pub enum LocalIdentifier<'a> {
-  Identifier(&'a Symbol),
+  Identifier(Symbol),
   // Reference(ReferenceId),
-  MemberExpression(&'a Symbol, Atom<'a>),
+  MemberExpression(Symbol, Atom<'a>),
 }
@@ -0,0 +1,21 @@
+import { readFileSync } from 'fs';
+import { join } from 'path';
+
+import type { CSSable } from '../../types';
+import toCSS from '../toCSS';
+
+// The same fixtures are checked by `crates/wyw_processor/src/to_css.rs`
+const fixtures: { input: CSSable; output: string }[] = JSON.parse(
+  readFileSync(
+    join(__dirname, '../../../../../crates/wyw_processor/fixtures/to_css.json'),
+    'utf8'
+  )
+);
+
+describe('toCSS', () => {
+  it('should match the fixtures shared with wyw_processor', () => {
+    for (const { input, output } of fixtures) {
+      expect(toCSS(input)).toBe(output);
+    }
+  });
+});
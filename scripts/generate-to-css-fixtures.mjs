@@ -0,0 +1,153 @@
+#!/usr/bin/env bun
+
+// Generates random style objects, runs them through `toCSS.ts` and saves
+// the results for `crates/wyw_processor/src/to_css.rs` and `toCSS.test.ts`.
+// The unit lists from `units.ts` are saved for `crates/wyw_processor/src/units.rs`,
+// and identifiers from `toValidCSSIdentifier.ts` for `crates/wyw_processor/src/slug.rs`.
+// Usage: bun scripts/generate-to-css-fixtures.mjs [count]
+
+import { mkdirSync, writeFileSync } from 'node:fs';
+import path from 'node:path';
+import { argv } from 'node:process';
+
+import toCSS from '../packages/processor-utils/src/utils/toCSS.ts';
+import { toValidCSSIdentifier } from '../packages/processor-utils/src/utils/toValidCSSIdentifier.ts';
+import { unitless, units } from '../packages/processor-utils/src/utils/units.ts';
+
+const output = path.resolve(
+  import.meta.dirname,
+  '../crates/wyw_processor/fixtures/to_css.json'
+);
+
+const unitsOutput = path.resolve(
+  import.meta.dirname,
+  '../crates/wyw_processor/fixtures/units.json'
+);
+
+const identifiersOutput = path.resolve(
+  import.meta.dirname,
+  '../crates/wyw_processor/fixtures/css_identifiers.json'
+);
+
+const identifiers = [
+  '1abc.def',
+  'Title-1_ü',
+  'a b\tc',
+  '0',
+  '\u00a0\u009f\u00ff',
+  '\uffff',
+  'a\u{1F600}b',
+  '\u{1F600}',
+  '9\u{1F600}',
+  '\u{10FFFF}x',
+  'ſK\u212a',
+  '[index]/(props) => {}',
+];
+
+const count = Number(argv[2] ?? 150);
+
+// mulberry32, so the fixtures are stable between runs
+let seed = 0x5eed;
+const random = () => {
+  seed = (seed + 0x6d2b79f5) | 0;
+  let t = Math.imul(seed ^ (seed >>> 15), 1 | seed);
+  t = (t + Math.imul(t ^ (t >>> 7), 61 | t)) ^ t;
+  return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+};
+
+const pick = (items) => items[Math.floor(random() * items.length)];
+
+const properties = [
+  'color',
+  'backgroundColor',
+  'fontSize',
+  'marginTop',
+  'width',
+  'lineHeight',
+  'opacity',
+  'zIndex',
+  'flexGrow',
+  'strokeWidth',
+  'gridColumnStart',
+  'WebkitFlexGrow',
+  'WebkitTransitionDelay',
+  'MozBoxFlex',
+  'OOrder',
+  'msFlexShrink',
+  'msTransition',
+  'Webkit',
+  '--customProperty',
+  '--gap',
+];
+
+const selectors = [
+  '&:hover',
+  '& > span',
+  '.child',
+  '@media (min-width: 200px)',
+  '@supports (display: grid)',
+];
+
+const numbers = [
+  0, -0, 1, 2, -3, 0.5, 1.25, 12, 100, 1e21, 1.5e-7, 0.000001, 0.1 + 0.2,
+];
+
+const strings = ['red', 'none', '10px', '1px solid black', 'inherit', ''];
+
+const falsy = [null, false, ''];
+
+const value = (depth) => {
+  const roll = random();
+  if (depth < 3 && roll < 0.12) {
+    return object(depth + 1);
+  }
+
+  if (depth < 3 && roll < 0.18) {
+    return Array.from({ length: 1 + Math.floor(random() * 2) }, () =>
+      object(depth + 1)
+    );
+  }
+
+  if (roll < 0.25) {
+    return pick(falsy);
+  }
+
+  if (roll < 0.6) {
+    return random() < 0.5
+      ? pick(numbers)
+      : Math.round((random() - 0.3) * 1000) / 100;
+  }
+
+  return pick(strings);
+};
+
+const object = (depth) => {
+  const result = {};
+  const size = 1 + Math.floor(random() * 5);
+  for (let i = 0; i < size; i += 1) {
+    const key = random() < 0.8 ? pick(properties) : pick(selectors);
+    result[key] = value(depth);
+  }
+
+  return result;
+};
+
+const fixtures = Array.from({ length: count }, () => {
+  const input = random() < 0.1 ? [object(0), pick(strings)] : object(0);
+  return { input, output: toCSS(input) };
+});
+
+mkdirSync(path.dirname(output), { recursive: true });
+writeFileSync(output, `${JSON.stringify(fixtures, null, 2)}\n`);
+writeFileSync(
+  unitsOutput,
+  `${JSON.stringify({ units, unitless: Object.keys(unitless) }, null, 2)}\n`
+);
+writeFileSync(
+  identifiersOutput,
+  `${JSON.stringify(
+    identifiers.map((input) => ({ input, output: toValidCSSIdentifier(input) })),
+    null,
+    2
+  )}\n`
+);